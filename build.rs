@@ -10,6 +10,9 @@ mod config;
 #[path = "src/git.rs"]
 mod git;
 
+#[path = "src/error.rs"]
+mod error;
+
 #[path = "src/init_directory.rs"]
 mod dir;
 
@@ -25,9 +28,12 @@ fn main() {
 
     if let Err(err) = dir::init_directory(
         nias::get_command_runner(),
+        nias::get_file_reader(),
         nias::get_file_writer(),
         nias::get_file_existence_checker(),
         Some(&target_directory),
+        None,
+        vec![],
         vec![],
     ) {
         println!("Fatal error encountered during initialization. Details: {err}");
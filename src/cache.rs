@@ -0,0 +1,114 @@
+use crate::TaskConfig;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+
+/// Changes whenever the cache key is computed differently, so that results
+/// recorded by older versions are not reused.
+const CACHE_VERSION: &str = "1";
+
+/// The results of the tasks that passed before, each one stored as a file
+/// named after its cache key.
+#[derive(Debug)]
+pub(crate) struct TaskCache {
+    pub(crate) directory: PathBuf,
+    /// Id of the tree object holding the working tree.
+    pub(crate) tree: String,
+    /// Paths and object ids of the files in `tree`, only listed when a task
+    /// selects `inputs`.
+    pub(crate) objects: Option<Vec<(String, String)>>,
+    /// Whether recorded results are reused, which `--no-cache` turns off.
+    pub(crate) reuse: bool,
+}
+
+impl TaskCache {
+    /// The key of `task` when run with `files` and the environment and
+    /// stdin of the hook, on the current working tree or its `inputs`.
+    pub(crate) fn key(
+        &self,
+        task: &TaskConfig,
+        files: Option<&[String]>,
+        env: &HashMap<String, String>,
+        stdin: Option<&str>,
+    ) -> String {
+        let mut hasher = Fnv1a::default();
+
+        hasher.write(CACHE_VERSION);
+        hasher.write(&task.run);
+        hasher.write(&format!("{:?}", task.shell));
+        hasher.write(task.cwd.as_deref().unwrap_or_default());
+
+        let env: BTreeMap<_, _> = env.iter().collect();
+
+        for (k, v) in task.env.iter().chain(env) {
+            hasher.write(k);
+            hasher.write(v);
+        }
+
+        hasher.write(stdin.unwrap_or_default());
+
+        for file in files.unwrap_or_default() {
+            hasher.write(file);
+        }
+
+        if task.inputs.is_empty() {
+            hasher.write(&self.tree);
+        } else {
+            let patterns: Vec<glob::Pattern> = task
+                .inputs
+                .iter()
+                .filter_map(|x| glob::Pattern::new(x).ok())
+                .collect();
+
+            for (path, object) in self.objects.as_deref().unwrap_or_default() {
+                if patterns.iter().any(|x| x.matches(path)) {
+                    hasher.write(path);
+                    hasher.write(object);
+                }
+            }
+        }
+
+        format!("{:032x}", hasher.0)
+    }
+
+    /// Whether a task with `key` passed before.
+    pub(crate) fn contains(&self, key: &str) -> bool {
+        self.reuse && self.directory.join(key).exists()
+    }
+
+    /// Records that the task `name` with `key` passed.
+    pub(crate) fn insert(&self, key: &str, name: &str) -> Result<(), std::io::Error> {
+        std::fs::create_dir_all(&self.directory)?;
+        std::fs::write(self.directory.join(key), name)
+    }
+}
+
+/// Whether the result of `task` is cached.
+pub(crate) fn is_cached(task: &TaskConfig) -> bool {
+    task.cache || !task.inputs.is_empty()
+}
+
+/// The 128 bit FNV-1a hash, which unlike the hashers of the standard library
+/// stays the same across releases.
+struct Fnv1a(u128);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0x6c62272e07bb014262b821756295c58d)
+    }
+}
+
+impl Fnv1a {
+    /// Hashes `value` prefixed with its length, so that consecutive values
+    /// cannot run into each other.
+    fn write(&mut self, value: &str) {
+        for byte in value.len().to_le_bytes().iter().chain(value.as_bytes()) {
+            self.0 ^= u128::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0000000001000000000000000000013b);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,100 @@
+use super::*;
+
+fn cache(directory: PathBuf) -> TaskCache {
+    TaskCache {
+        directory,
+        tree: String::from("1234"),
+        objects: Some(vec![
+            (String::from("src/lib.rs"), String::from("aaaa")),
+            (String::from("README.md"), String::from("bbbb")),
+        ]),
+        reuse: true,
+    }
+}
+
+fn key(cache: &TaskCache, task: &TaskConfig) -> String {
+    cache.key(task, None, &HashMap::new(), None)
+}
+
+#[cfg(test)]
+mod key_tests {
+    use super::*;
+
+    #[test]
+    fn is_stable() {
+        let cache = cache(PathBuf::new());
+        let task = TaskConfig::from("cargo test");
+        assert_eq!(key(&cache, &task), key(&cache, &task));
+        assert_eq!(key(&cache, &task).len(), 32);
+    }
+
+    #[test]
+    fn changes_with_command_and_env() {
+        let cache = cache(PathBuf::new());
+        let task = TaskConfig::from("cargo test");
+        let other = TaskConfig {
+            env: [(String::from("RUST_LOG"), String::from("debug"))].into(),
+            ..task.clone()
+        };
+        let env = [(String::from("HOOK"), String::from("x"))].into();
+
+        assert_ne!(
+            key(&cache, &task),
+            key(&cache, &TaskConfig::from("cargo build"))
+        );
+        assert_ne!(key(&cache, &task), key(&cache, &other));
+        assert_ne!(key(&cache, &task), cache.key(&task, None, &env, None));
+        assert_ne!(
+            key(&cache, &task),
+            cache.key(&task, None, &HashMap::new(), Some("x"))
+        );
+    }
+
+    #[test]
+    fn changes_with_tree() {
+        let task = TaskConfig::from("cargo test");
+        let other = TaskCache {
+            tree: String::from("5678"),
+            ..cache(PathBuf::new())
+        };
+        assert_ne!(key(&cache(PathBuf::new()), &task), key(&other, &task));
+    }
+
+    #[test]
+    fn only_hashes_inputs() {
+        let task = TaskConfig {
+            inputs: vec![String::from("src/**")],
+            ..TaskConfig::from("cargo test")
+        };
+        let mut other = TaskCache {
+            tree: String::from("5678"),
+            ..cache(PathBuf::new())
+        };
+        assert_eq!(key(&cache(PathBuf::new()), &task), key(&other, &task));
+
+        other.objects.as_mut().unwrap()[0].1 = String::from("cccc");
+        assert_ne!(key(&cache(PathBuf::new()), &task), key(&other, &task));
+    }
+}
+
+#[cfg(test)]
+mod contains_tests {
+    use super::*;
+
+    #[test]
+    fn contains_inserted_keys() {
+        let directory = crate::tests::utils::temp_path("cache");
+        let _ = std::fs::remove_dir_all(&directory);
+        let mut cache = cache(directory.join("cache"));
+
+        assert!(!cache.contains("abcd"));
+        assert!(cache.insert("abcd", "test").is_ok());
+        assert!(cache.contains("abcd"));
+        assert!(!cache.contains("ef01"));
+
+        cache.reuse = false;
+        assert!(!cache.contains("abcd"));
+
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{
+    HookConfig, TaskCondition, git,
+    refs::{self, RefUpdate},
+};
+use std::collections::HashMap;
+
+/// What the `when` conditions of tasks are checked against.
+#[derive(Debug, Default)]
+pub(crate) struct ConditionState {
+    /// The branch being committed to, or the branches being pushed to.
+    pub(crate) branches: Vec<String>,
+    /// The staged files, or the files changed by the pushed commits.
+    pub(crate) changed_files: Vec<String>,
+}
+
+impl ConditionState {
+    /// Queries the state the conditions of `hook` depend on, from the
+    /// repository at `root` and the `stdin` of `hook_name`.
+    ///
+    /// Without `stdin`, the conditions are checked against the current branch
+    /// and `staged_files`, which stand for whichever files the hook runs on.
+    pub(crate) fn new<F>(
+        run_command: F,
+        root: &str,
+        hook_name: &str,
+        hook: &HookConfig,
+        stdin: Option<&str>,
+        staged_files: Option<&[String]>,
+    ) -> Result<Self, String>
+    where
+        F: Fn(
+            &str,
+            Option<&str>,
+            bool,
+            Option<&HashMap<String, String>>,
+        ) -> Result<Option<String>, Option<String>>,
+    {
+        let conditions: Vec<&TaskCondition> = hook
+            .groups
+            .iter()
+            .flatten()
+            .filter_map(|x| x.when.as_ref())
+            .collect();
+        let needs_branches = conditions.iter().any(|x| x.branch.is_some());
+        let needs_changed_files = conditions.iter().any(|x| !x.changed.is_empty());
+
+        let mut state = Self::default();
+
+        // Run by hand, a `pre-push` hook has no ref updates to check.
+        if hook_name == "pre-push"
+            && let Some(stdin) = stdin
+        {
+            let updates = refs::parse_ref_updates(hook_name, stdin);
+
+            for update in updates {
+                let RefUpdate::Push {
+                    local_sha,
+                    remote_ref,
+                    remote_sha,
+                    ..
+                } = update
+                else {
+                    continue;
+                };
+
+                if needs_branches && let Some(x) = remote_ref.strip_prefix("refs/heads/") {
+                    state.branches.push(x.to_string());
+                }
+
+                // Deleting a ref changes no files.
+                if needs_changed_files && !git::is_null_sha(&local_sha) {
+                    state.changed_files.extend(git::get_pushed_files(
+                        &run_command,
+                        root,
+                        &local_sha,
+                        &remote_sha,
+                    )?);
+                }
+            }
+
+            state.changed_files.sort();
+            state.changed_files.dedup();
+
+            return Ok(state);
+        }
+
+        if needs_branches {
+            state.branches = git::get_current_branch(&run_command, root)
+                .into_iter()
+                .collect();
+        }
+
+        if needs_changed_files {
+            state.changed_files = match staged_files {
+                Some(x) => x.to_vec(),
+                None => git::get_staged_files(&run_command, root)?,
+            };
+        }
+
+        Ok(state)
+    }
+}
+
+/// Why `condition` does not hold in `state`, with `get_env` reading the
+/// environment, or `None` when it holds.
+pub(crate) fn unmet_condition<F>(
+    condition: &TaskCondition,
+    state: &ConditionState,
+    get_env: F,
+) -> Option<String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if let Some(os) = &condition.os
+        && os != std::env::consts::OS
+        && os != std::env::consts::FAMILY
+    {
+        return Some(format!("only runs on {os}"));
+    }
+
+    if let Some(var) = &condition.env
+        && get_env(var).is_none_or(|x| x.is_empty())
+    {
+        return Some(format!("`{var}` is not set"));
+    }
+
+    if let Some(branch) = &condition.branch {
+        let pattern = glob::Pattern::new(branch).ok()?;
+
+        if !state.branches.iter().any(|x| pattern.matches(x)) {
+            if state.branches.is_empty() {
+                return Some(format!("not on a branch matching `{branch}`"));
+            }
+
+            return Some(format!(
+                "{} does not match `{branch}`",
+                state.branches.join(", ")
+            ));
+        }
+    }
+
+    if !condition.changed.is_empty() {
+        let patterns: Vec<glob::Pattern> = condition
+            .changed
+            .iter()
+            .filter_map(|x| glob::Pattern::new(x).ok())
+            .collect();
+
+        if !state
+            .changed_files
+            .iter()
+            .any(|x| patterns.iter().any(|pattern| pattern.matches(x)))
+        {
+            return Some(format!(
+                "no changed file matches `{}`",
+                condition.changed.join("`, `")
+            ));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,190 @@
+use super::*;
+use crate::{ConfigFile, tests::utils::build_simple_command_runner};
+
+fn state(branches: &[&str], changed_files: &[&str]) -> ConditionState {
+    ConditionState {
+        branches: branches.iter().map(|x| x.to_string()).collect(),
+        changed_files: changed_files.iter().map(|x| x.to_string()).collect(),
+    }
+}
+
+fn unmet(condition: &TaskCondition, state: &ConditionState) -> Option<String> {
+    unmet_condition(condition, state, |var| {
+        (var == "CI_STRICT").then(|| String::from("1"))
+    })
+}
+
+#[cfg(test)]
+mod unmet_condition_tests {
+    use super::*;
+
+    #[test]
+    fn holds_without_conditions() {
+        assert_eq!(unmet(&TaskCondition::default(), &state(&[], &[])), None);
+    }
+
+    #[test]
+    fn matches_branch_glob() {
+        let condition = TaskCondition {
+            branch: Some(String::from("release/*")),
+            ..TaskCondition::default()
+        };
+        assert_eq!(unmet(&condition, &state(&["release/1.0"], &[])), None);
+        assert_eq!(
+            unmet(&condition, &state(&["main", "release/1.0"], &[])),
+            None
+        );
+        assert_eq!(
+            unmet(&condition, &state(&["main"], &[])),
+            Some(String::from("main does not match `release/*`"))
+        );
+        assert_eq!(
+            unmet(&condition, &state(&[], &[])),
+            Some(String::from("not on a branch matching `release/*`"))
+        );
+    }
+
+    #[test]
+    fn matches_changed_files() {
+        let condition = TaskCondition {
+            changed: vec![String::from("Cargo.lock"), String::from("crates/**")],
+            ..TaskCondition::default()
+        };
+        assert_eq!(
+            unmet(&condition, &state(&[], &["README.md", "crates/a/lib.rs"])),
+            None
+        );
+        assert_eq!(
+            unmet(&condition, &state(&[], &["README.md"])),
+            Some(String::from(
+                "no changed file matches `Cargo.lock`, `crates/**`"
+            ))
+        );
+    }
+
+    #[test]
+    fn requires_env_var() {
+        let set = TaskCondition {
+            env: Some(String::from("CI_STRICT")),
+            ..TaskCondition::default()
+        };
+        let unset = TaskCondition {
+            env: Some(String::from("CI_NIGHTLY")),
+            ..TaskCondition::default()
+        };
+        assert_eq!(unmet(&set, &state(&[], &[])), None);
+        assert_eq!(
+            unmet(&unset, &state(&[], &[])),
+            Some(String::from("`CI_NIGHTLY` is not set"))
+        );
+        assert_eq!(
+            unmet_condition(&set, &state(&[], &[]), |_| Some(String::new())),
+            Some(String::from("`CI_STRICT` is not set"))
+        );
+    }
+
+    #[test]
+    fn matches_os_or_family() {
+        let os = |os: &str| TaskCondition {
+            os: Some(os.to_string()),
+            ..TaskCondition::default()
+        };
+        assert_eq!(unmet(&os(std::env::consts::OS), &state(&[], &[])), None);
+        assert_eq!(unmet(&os(std::env::consts::FAMILY), &state(&[], &[])), None);
+        assert_eq!(
+            unmet(&os("plan9"), &state(&[], &[])),
+            Some(String::from("only runs on plan9"))
+        );
+    }
+}
+
+#[cfg(test)]
+mod condition_state_tests {
+    use super::*;
+
+    fn hook(when: &str) -> HookConfig {
+        let contents = format!("[[hooks.pre-push]]\nrun = \"true\"\nwhen = {when}");
+        ConfigFile::try_from_str(&contents)
+            .unwrap()
+            .hooks
+            .remove("pre-push")
+            .unwrap()
+    }
+
+    #[test]
+    fn uses_pushed_branches_and_files() {
+        let run_command = |cmd: &str,
+                           _dir: Option<&str>,
+                           _stream_io: bool,
+                           _env: Option<&HashMap<String, String>>| {
+            match cmd {
+                "git log -z --name-only --format= 2222..1111" => Ok(Some(String::from("b\0a\0"))),
+                "git log -z --name-only --format= 3333 --not --remotes" => {
+                    Ok(Some(String::from("a\0c\0")))
+                }
+                "git log -z --name-only --format= 5555 --not --remotes" => Ok(None),
+                _ => panic!("{cmd}"),
+            }
+        };
+        let stdin = "refs/heads/main 1111 refs/heads/release/1.0 2222\n\
+                     refs/heads/new 3333 refs/heads/new 0000\n\
+                     (delete) 0000 refs/heads/old 4444\n\
+                     refs/tags/v1 5555 refs/tags/v1 0000\n";
+        let hook = hook(r#"{ branch = "release/*", changed = ["*"] }"#);
+
+        let state = ConditionState::new(run_command, "/repo", "pre-push", &hook, Some(stdin), None)
+            .unwrap();
+
+        assert_eq!(state.branches, ["release/1.0", "new", "old"]);
+        assert_eq!(state.changed_files, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn uses_current_branch_and_staged_files() {
+        let run_command = build_simple_command_runner(Ok(Some(String::from("main"))));
+        let hook = hook(r#"{ branch = "main", changed = ["*"] }"#);
+        let staged_files = [String::from("a")];
+
+        let state = ConditionState::new(
+            run_command,
+            "/repo",
+            "pre-commit",
+            &hook,
+            None,
+            Some(&staged_files),
+        )
+        .unwrap();
+
+        assert_eq!(state.branches, ["main"]);
+        assert_eq!(state.changed_files, ["a"]);
+    }
+
+    #[test]
+    fn uses_current_branch_for_pre_push_run_by_hand() {
+        let run_command = build_simple_command_runner(Ok(Some(String::from("main"))));
+        let hook = hook(r#"{ branch = "main", changed = ["*"] }"#);
+        let files = [String::from("a")];
+
+        let state =
+            ConditionState::new(run_command, "/repo", "pre-push", &hook, None, Some(&files))
+                .unwrap();
+
+        assert_eq!(state.branches, ["main"]);
+        assert_eq!(state.changed_files, ["a"]);
+    }
+
+    #[test]
+    fn only_queries_what_conditions_use() {
+        let run_command =
+            |cmd: &str,
+             _dir: Option<&str>,
+             _stream_io: bool,
+             _env: Option<&HashMap<String, String>>| panic!("{cmd}");
+        let hook = hook(r#"{ os = "linux" }"#);
+
+        let state =
+            ConditionState::new(run_command, "/repo", "pre-commit", &hook, None, None).unwrap();
+
+        assert!(state.branches.is_empty() && state.changed_files.is_empty());
+    }
+}
@@ -1,4 +1,16 @@
-use std::collections::HashMap;
+use crate::error::Error;
+use serde::{
+    Deserialize as _,
+    de::{
+        self, Deserializer, MapAccess, SeqAccess, Visitor,
+        value::{MapAccessDeserializer, SeqAccessDeserializer},
+    },
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    num::NonZeroUsize,
+};
 
 const CONFIG_FILE_TEMPLATE: &str = "[hooks]
 pre-commit = [
@@ -16,7 +28,10 @@ pub const NO_CONFIG_FILE_FOUND: &str = "No config file found";
 pub(crate) const FATAL_ERROR_DURING_CONFIG_LOOKUP: &str =
     "Fatal error encountered while looking for existing config";
 
-fn find_config_file<F>(root_directory_path: &str, file_exists: F) -> Result<String, String>
+pub(super) fn find_config_file<F>(
+    root_directory_path: &str,
+    file_exists: F,
+) -> Result<String, String>
 where
     F: Fn(&str) -> Result<bool, ()>,
 {
@@ -37,6 +52,29 @@ where
     Ok(String::from(NO_CONFIG_FILE_FOUND))
 }
 
+/// Removes the config file of the repository, returning its path if one existed.
+pub(super) fn remove_config_file<F, G>(
+    remove_file: F,
+    file_exists: G,
+    root_directory_path: &str,
+) -> Result<Option<String>, String>
+where
+    F: Fn(&str) -> Result<(), String>,
+    G: Fn(&str) -> Result<bool, ()>,
+{
+    let path = find_config_file(root_directory_path, &file_exists)?;
+
+    if path == NO_CONFIG_FILE_FOUND {
+        return Ok(None);
+    }
+
+    if remove_file(&path).is_err() {
+        return Err(String::from("Failed to remove config file"));
+    }
+
+    Ok(Some(path))
+}
+
 pub(super) fn create_default_config_file<F, G>(
     write_file: F,
     file_exists: G,
@@ -96,9 +134,634 @@ where
     Ok(())
 }
 
+/// Settings from the `[logging]` table controlling how task output is shown.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Print the output of every task once it finishes, not only of failed ones.
+    pub verbose: bool,
+    /// Only print the output of failed tasks. Takes precedence over `verbose`.
+    pub quiet: bool,
+    /// Print the output of every task live, prefixed with the task name.
+    pub stream: bool,
+}
+
+/// A single task of a hook.
+///
+/// Tasks are either written as a bare command string, which only sets `run`,
+/// or as a table such as:
+///
+/// ```toml
+/// [[hooks.pre-commit]]
+/// name = "clippy"
+/// run = "cargo clippy -- -D warnings"
+/// env = { RUSTFLAGS = "-Dwarnings" }
+/// cwd = "crates/core"
+/// timeout = 300
+/// allow_failure = true
+/// description = "Lint the core crate"
+/// files = "*.rs"
+/// fix = true
+/// depends_on = ["build"]
+/// exclusive_group = "cargo"
+/// shell = "bash"
+/// inputs = ["src/**/*.rs", "Cargo.*"]
+/// when = { branch = "release/*", changed = ["crates/**"] }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskConfig {
+    /// Name used in logs and reports. Defaults to the command.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The command to run, optionally prefixed with `KEY=VALUE` assignments.
+    pub run: String,
+    /// Extra environment variables for the command.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Working directory, relative to the repository root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Maximum run time in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Report a failure of this task without rejecting the hook.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_failure: bool,
+    /// Human readable explanation of what the task checks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Glob selecting the staged files passed to the command. The task is
+    /// skipped when no staged file matches.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_glob",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub files: Option<String>,
+    /// Run the invocations a long file list is split into in parallel.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub parallel_batches: bool,
+    /// The task rewrites files, so the staged files it modifies are added
+    /// back to the index.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub fix: bool,
+    /// Reject the hook when the task fixed staged files, so that the fixes
+    /// can be reviewed before committing again. Implies `fix`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub fail_on_fix: bool,
+    /// Names of the tasks that must succeed before this one starts.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// Tasks sharing an exclusive group never run at the same time, e.g. to
+    /// keep `cargo` invocations from waiting on each other's build lock.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exclusive_group: Option<String>,
+    /// Shell the command runs in, overriding the one of the hook.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<Shell>,
+    /// Skip the task when it already passed on an identical working tree.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cache: bool,
+    /// Globs selecting the files whose contents the cached result depends
+    /// on, instead of the whole working tree. Implies `cache`.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_globs",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub inputs: Vec<String>,
+    /// Conditions the task only runs under. The task is skipped otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<TaskCondition>,
+}
+
+/// Conditions a task only runs under, all of which must hold.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskCondition {
+    /// Glob matching the branch being committed to, or for `pre-push`, one
+    /// of the branches being pushed to.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_glob",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub branch: Option<String>,
+    /// Globs, one of which must match a staged file, or for `pre-push`, a
+    /// file changed by the pushed commits.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_globs",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub changed: Vec<String>,
+    /// Environment variable that must be set to a non-empty value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<String>,
+    /// Operating system, such as `linux`, `macos` or `windows`, or family of
+    /// operating systems, `unix` or `windows`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<String>,
+}
+
+fn deserialize_globs<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let patterns = Vec::<String>::deserialize(deserializer)?;
+
+    for pattern in &patterns {
+        glob::Pattern::new(pattern)
+            .map_err(|e| de::Error::custom(format!("invalid glob `{pattern}`: {e}")))?;
+    }
+
+    Ok(patterns)
+}
+
+fn deserialize_glob<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let pattern = String::deserialize(deserializer)?;
+
+    glob::Pattern::new(&pattern)
+        .map_err(|e| de::Error::custom(format!("invalid glob `{pattern}`: {e}")))?;
+
+    Ok(Some(pattern))
+}
+
+/// How the command of a task is run.
+///
+/// Written as the name of a shell, such as `shell = "bash"`, or as `false`.
+/// `true` stands for `sh`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Shell {
+    /// Split the command into arguments and run the program directly.
+    Disabled,
+    /// Pass the command to this shell with `-c`, so that operators, pipes,
+    /// redirections, globs and variables work.
+    Program(String),
+}
+
+impl serde::Serialize for Shell {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Shell::Disabled => serializer.serialize_bool(false),
+            Shell::Program(program) => serializer.serialize_str(program),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Shell {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ShellVisitor;
+
+        impl Visitor<'_> for ShellVisitor {
+            type Value = Shell;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("the name of a shell or a boolean")
+            }
+
+            fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+                Ok(if v {
+                    Shell::Program(String::from("sh"))
+                } else {
+                    Shell::Disabled
+                })
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                if v.trim().is_empty() {
+                    return Err(E::invalid_value(de::Unexpected::Str(v), &self));
+                }
+
+                Ok(Shell::Program(v.to_string()))
+            }
+        }
+
+        deserializer.deserialize_any(ShellVisitor)
+    }
+}
+
+impl TaskConfig {
+    /// The name the task is reported under.
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.run)
+    }
+}
+
+impl From<&str> for TaskConfig {
+    fn from(run: &str) -> Self {
+        Self {
+            run: run.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A task written either as a command string or as a table.
+struct TaskElement(TaskConfig);
+
+impl<'de> serde::Deserialize<'de> for TaskElement {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct TaskElementVisitor;
+
+        impl<'de> Visitor<'de> for TaskElementVisitor {
+            type Value = TaskElement;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a command string or a task table")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(TaskElement(TaskConfig::from(v)))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                TaskConfig::deserialize(MapAccessDeserializer::new(map)).map(TaskElement)
+            }
+        }
+
+        deserializer.deserialize_any(TaskElementVisitor)
+    }
+}
+
+/// The tasks and settings configured for a single hook.
+///
+/// A hook accepts four forms in the config file, which are all normalized
+/// into parallel groups of sequentially executed tasks:
+///
+/// * a string: `pre-commit = "cargo test"`
+/// * an array of tasks, run sequentially:
+///   `pre-push = ["cargo check", "cargo fmt -- --check"]`, which also covers
+///   `[[hooks.pre-push]]` tables
+/// * an array of arrays of tasks, whose groups run in parallel:
+///   `pre-commit = [["cargo clippy"], ["cargo test"]]`
+/// * a `[hooks.pre-commit]` table holding any of the above under `tasks`,
+///   next to settings that apply to the whole hook
+///
+/// Wherever a task is expected, either a command string or a task table is
+/// accepted, see [`TaskConfig`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct HookConfig {
+    #[serde(rename = "tasks")]
+    pub groups: Vec<Vec<TaskConfig>>,
+    /// Timeout in seconds for the whole hook.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Timeout in seconds for every task that does not set its own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_timeout: Option<u64>,
+    /// Cancel the remaining tasks as soon as one fails, instead of running
+    /// every group to completion and reporting all failures.
+    pub fail_fast: bool,
+    /// Stash unstaged and untracked changes while the hook runs, so that the
+    /// tasks see exactly what is about to be committed.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stash_unstaged: bool,
+    /// Shell the commands run in, unless a task sets its own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shell: Option<Shell>,
+}
+
+impl Default for HookConfig {
+    fn default() -> Self {
+        Self {
+            groups: vec![],
+            timeout: None,
+            task_timeout: None,
+            fail_fast: true,
+            stash_unstaged: false,
+            shell: None,
+        }
+    }
+}
+
+impl HookConfig {
+    /// The tasks of the hook, in order, with the `task_timeout` and `shell`
+    /// of the hook unless they set their own.
+    pub(crate) fn tasks(&self) -> Vec<TaskConfig> {
+        self.groups
+            .iter()
+            .flatten()
+            .map(|x| TaskConfig {
+                timeout: x.timeout.or(self.task_timeout),
+                shell: x.shell.clone().or_else(|| self.shell.clone()),
+                ..x.clone()
+            })
+            .collect()
+    }
+
+    /// For each task of the hook, in order, the indices of the tasks it waits
+    /// for.
+    ///
+    /// Once a task sets `depends_on`, the hook is ordered by the dependencies
+    /// alone. Otherwise, each task waits for the previous task of its group.
+    pub(crate) fn dependencies(&self) -> Result<Vec<Vec<usize>>, String> {
+        let tasks: Vec<&TaskConfig> = self.groups.iter().flatten().collect();
+
+        if tasks.iter().all(|x| x.depends_on.is_empty()) {
+            let mut dependencies = vec![];
+
+            for group in &self.groups {
+                for i in 0..group.len() {
+                    let previous = (i > 0).then(|| dependencies.len() - 1);
+                    dependencies.push(previous.into_iter().collect());
+                }
+            }
+
+            return Ok(dependencies);
+        }
+
+        let dependencies = tasks
+            .iter()
+            .map(|task| {
+                task.depends_on
+                    .iter()
+                    .map(|name| {
+                        let mut matches = (0..tasks.len()).filter(|&i| tasks[i].name() == name);
+
+                        match (matches.next(), matches.next()) {
+                            (Some(i), None) => Ok(i),
+                            (None, _) => Err(format!(
+                                "task `{}` depends on unknown task `{name}`",
+                                task.name()
+                            )),
+                            (Some(_), Some(_)) => Err(format!(
+                                "task `{}` depends on `{name}`, which names several tasks",
+                                task.name()
+                            )),
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(cycle) = find_cycle(&dependencies) {
+            let names: Vec<String> = cycle
+                .iter()
+                .map(|&i| format!("`{}`", tasks[i].name()))
+                .collect();
+
+            return Err(format!(
+                "tasks depend on each other in a cycle: {}",
+                names.join(" -> ")
+            ));
+        }
+
+        Ok(dependencies)
+    }
+}
+
+/// A path of dependencies that leads back to the task it starts with, if any.
+fn find_cycle(dependencies: &[Vec<usize>]) -> Option<Vec<usize>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mark {
+        New,
+        Visiting,
+        Done,
+    }
+
+    fn visit(
+        i: usize,
+        dependencies: &[Vec<usize>],
+        marks: &mut [Mark],
+        path: &mut Vec<usize>,
+    ) -> Option<Vec<usize>> {
+        match marks[i] {
+            Mark::Done => return None,
+            Mark::Visiting => {
+                let start = path.iter().position(|&x| x == i).unwrap_or_default();
+                let mut cycle = path[start..].to_vec();
+                cycle.push(i);
+
+                return Some(cycle);
+            }
+            Mark::New => {}
+        }
+
+        marks[i] = Mark::Visiting;
+        path.push(i);
+
+        for &x in &dependencies[i] {
+            if let Some(cycle) = visit(x, dependencies, marks, path) {
+                return Some(cycle);
+            }
+        }
+
+        path.pop();
+        marks[i] = Mark::Done;
+
+        None
+    }
+
+    let mut marks = vec![Mark::New; dependencies.len()];
+
+    (0..dependencies.len()).find_map(|i| visit(i, dependencies, &mut marks, &mut vec![]))
+}
+
+impl From<Vec<Vec<TaskConfig>>> for HookConfig {
+    fn from(groups: Vec<Vec<TaskConfig>>) -> Self {
+        Self {
+            groups,
+            ..Default::default()
+        }
+    }
+}
+
+/// The `[hooks.<hook>]` table form of a hook.
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HookTable {
+    tasks: HookTasks,
+    #[serde(default)]
+    timeout: Option<u64>,
+    #[serde(default)]
+    task_timeout: Option<u64>,
+    #[serde(default = "default_fail_fast")]
+    fail_fast: bool,
+    #[serde(default)]
+    stash_unstaged: bool,
+    #[serde(default)]
+    shell: Option<Shell>,
+}
+
+fn default_fail_fast() -> bool {
+    true
+}
+
+/// The tasks of a hook in any of the non-table forms.
+struct HookTasks(Vec<Vec<TaskConfig>>);
+
+/// A single element of a hook array, which decides which form the array has.
+enum HookElement {
+    Task(Box<TaskConfig>),
+    Group(Vec<TaskConfig>),
+}
+
+impl<'de> serde::Deserialize<'de> for HookElement {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct HookElementVisitor;
+
+        impl<'de> Visitor<'de> for HookElementVisitor {
+            type Value = HookElement;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a command string, a task table or an array of tasks")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(HookElement::Task(Box::new(TaskConfig::from(v))))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                TaskConfig::deserialize(MapAccessDeserializer::new(map))
+                    .map(|task| HookElement::Task(Box::new(task)))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut group = Vec::new();
+
+                while let Some(TaskElement(task)) = seq.next_element()? {
+                    group.push(task);
+                }
+
+                Ok(HookElement::Group(group))
+            }
+        }
+
+        deserializer.deserialize_any(HookElementVisitor)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for HookTasks {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct HookTasksVisitor;
+
+        impl<'de> Visitor<'de> for HookTasksVisitor {
+            type Value = HookTasks;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a command string, an array of tasks or an array of arrays of tasks")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(HookTasks(vec![vec![TaskConfig::from(v)]]))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut tasks = Vec::new();
+                let mut groups = Vec::new();
+
+                while let Some(element) = seq.next_element::<HookElement>()? {
+                    match element {
+                        HookElement::Task(task) if groups.is_empty() => tasks.push(*task),
+                        HookElement::Group(group) if tasks.is_empty() => groups.push(group),
+                        _ => {
+                            return Err(de::Error::custom(
+                                "cannot mix tasks and arrays of tasks in the same hook",
+                            ));
+                        }
+                    }
+                }
+
+                if !tasks.is_empty() {
+                    groups.push(tasks);
+                }
+
+                Ok(HookTasks(groups))
+            }
+        }
+
+        deserializer.deserialize_any(HookTasksVisitor)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for HookConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct HookConfigVisitor;
+
+        impl<'de> Visitor<'de> for HookConfigVisitor {
+            type Value = HookConfig;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(
+                    "a command string, an array of tasks, an array of arrays of tasks or a hook table",
+                )
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(HookConfig::from(vec![vec![TaskConfig::from(v)]]))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                let HookTasks(groups) = HookTasks::deserialize(SeqAccessDeserializer::new(seq))?;
+
+                Ok(HookConfig::from(groups))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                let table = HookTable::deserialize(MapAccessDeserializer::new(map))?;
+
+                Ok(HookConfig {
+                    groups: table.tasks.0,
+                    timeout: table.timeout,
+                    task_timeout: table.task_timeout,
+                    fail_fast: table.fail_fast,
+                    stash_unstaged: table.stash_unstaged,
+                    shell: table.shell,
+                })
+            }
+        }
+
+        let hook = deserializer.deserialize_any(HookConfigVisitor)?;
+
+        hook.dependencies().map_err(de::Error::custom)?;
+
+        Ok(hook)
+    }
+}
+
+/// When a hook that existed before crusty-hooks was installed runs, relative
+/// to the configured tasks.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LegacyHooks {
+    #[default]
+    Before,
+    After,
+    /// Never run the pre-existing hook.
+    Skip,
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct ConfigFile {
-    pub hooks: HashMap<String, Vec<Vec<String>>>,
+    #[serde(default)]
+    pub legacy_hooks: LegacyHooks,
+    /// Maximum number of tasks running at the same time. Defaults to the
+    /// number of CPUs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<NonZeroUsize>,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    pub hooks: HashMap<String, HookConfig>,
 }
 
 impl ConfigFile {
@@ -111,24 +774,21 @@ pub(super) fn get_config_file_contents<F, G>(
     read_file: F,
     file_exists: G,
     root_directory_path: &str,
-) -> Result<String, String>
+) -> Result<(String, String), Error>
 where
     F: Fn(&str) -> Result<String, ()>,
     G: Fn(&str) -> Result<bool, ()>,
 {
     let path = match find_config_file(root_directory_path, &file_exists) {
-        Ok(path) => {
-            if path == NO_CONFIG_FILE_FOUND {
-                return Err(String::from(NO_CONFIG_FILE_FOUND));
-            } else {
-                path
-            }
-        }
-        Err(_) => return Err(String::from(NO_CONFIG_FILE_FOUND)),
+        Ok(path) if path != NO_CONFIG_FILE_FOUND => path,
+        _ => return Err(Error::ConfigNotFound),
     };
 
     match read_file(&path) {
-        Ok(contents) => Ok(contents),
-        Err(_) => Err(String::from("Failure reading file")),
+        Ok(contents) => Ok((path, contents)),
+        Err(_) => Err(Error::ConfigRead { path }),
     }
 }
+
+#[cfg(test)]
+mod tests;
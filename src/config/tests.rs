@@ -56,6 +56,39 @@ mod create_default_config_file_tests {
     }
 }
 
+#[cfg(test)]
+mod remove_config_file_tests {
+    use super::*;
+
+    #[test]
+    fn returns_none_when_no_config_exists() {
+        let remove_file = |_path: &str| panic!("Should not get here");
+        let file_exists = |_path: &str| Ok(false);
+        let result = remove_config_file(remove_file, file_exists, "/usr/mine/foo");
+        assert_eq!(result, Ok(None));
+    }
+
+    #[test]
+    fn removes_found_config() {
+        let exp_path = "/usr/mine/foo/crusty-hooks.toml";
+        let remove_file = |path: &str| {
+            assert_eq!(exp_path, path);
+            Ok(())
+        };
+        let file_exists = |path: &str| Ok(path == exp_path);
+        let result = remove_config_file(remove_file, file_exists, "/usr/mine/foo");
+        assert_eq!(result, Ok(Some(String::from(exp_path))));
+    }
+
+    #[test]
+    fn returns_error_when_remove_fails() {
+        let remove_file = |_path: &str| Err(String::from(""));
+        let file_exists = |_path: &str| Ok(true);
+        let result = remove_config_file(remove_file, file_exists, "/usr/mine/foo");
+        assert_eq!(result, Err(String::from("Failed to remove config file")));
+    }
+}
+
 #[cfg(test)]
 mod create_config_file_tests {
     use super::*;
@@ -135,7 +168,7 @@ mod get_config_file_contents_tests {
         let file_exists = |_path: &str| Err(());
         let read_file = |_path: &str| panic!("Should not call here");
         let result = get_config_file_contents(read_file, file_exists, "");
-        assert_eq!(result, Err(String::from(NO_CONFIG_FILE_FOUND)));
+        assert!(matches!(result, Err(Error::ConfigNotFound)));
     }
 
     #[test]
@@ -143,16 +176,18 @@ mod get_config_file_contents_tests {
         let file_exists = |_path: &str| Ok(false);
         let read_file = |_path: &str| panic!("Should not call here");
         let result = get_config_file_contents(read_file, file_exists, "");
-        assert_eq!(result, Err(String::from(NO_CONFIG_FILE_FOUND)));
+        assert!(matches!(result, Err(Error::ConfigNotFound)));
     }
 
     #[test]
     fn fails_on_config_file_read_error() {
-        let exp_err = "Failure reading file";
         let file_exists = |_path: &str| Ok(true);
         let read_file = |_path: &str| Err(());
         let result = get_config_file_contents(read_file, file_exists, "/var/foo");
-        assert_eq!(result, Err(String::from(exp_err)));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Failed to read /var/foo/.crusty-hooks.toml"
+        );
     }
 
     #[test]
@@ -163,80 +198,158 @@ mod get_config_file_contents_tests {
         let file_exists = |_path: &str| Ok(true);
         let read_file = |_path: &str| Ok(String::from(exp_contents));
         let result = get_config_file_contents(read_file, file_exists, "/var/foo");
-        assert_eq!(result.unwrap(), String::from(exp_contents));
+        assert_eq!(
+            result.unwrap(),
+            (
+                String::from("/var/foo/.crusty-hooks.toml"),
+                String::from(exp_contents)
+            )
+        );
     }
 }
 
 #[cfg(test)]
-mod get_table_key_value_from_config {
+mod jobs_tests {
     use super::*;
 
     #[test]
-    fn handles_invalid_toml() {
-        let invalid_contents = "90827342089734";
-        let result = get_table_key_value_from_config(invalid_contents, "", "");
-        assert_eq!(result, Err(String::from("Error parsing config file")));
+    fn defaults_to_none() {
+        let result = ConfigFile::try_from_str("[hooks]").unwrap();
+        assert_eq!(result.jobs, None);
     }
 
     #[test]
-    fn handles_missing_table() {
-        let contents = "[hooks]";
-        let result = get_table_key_value_from_config(contents, "foo", "");
-        assert_eq!(result, Err(String::from("Missing config table")));
+    fn parses_jobs() {
+        let result = ConfigFile::try_from_str("jobs = 2\n[hooks]").unwrap();
+        assert_eq!(result.jobs, NonZeroUsize::new(2));
     }
 
     #[test]
-    fn handles_missing_table_key() {
-        let contents = r#"[hooks]
-            pre-commit = "cargo test"
+    fn returns_err_on_zero_jobs() {
+        let result = ConfigFile::try_from_str("jobs = 0\n[hooks]");
+        insta::assert_snapshot!(result.unwrap_err(), @"
+        TOML parse error at line 1, column 8
+          |
+        1 | jobs = 0
+          |        ^
+        invalid value: integer `0`, expected a nonzero usize
+        ");
+    }
+}
+
+#[cfg(test)]
+mod legacy_hooks_tests {
+    use super::*;
+
+    #[test]
+    fn defaults_to_before() {
+        let result = ConfigFile::try_from_str("[hooks]").unwrap();
+        assert_eq!(result.legacy_hooks, LegacyHooks::Before);
+    }
+
+    #[test]
+    fn parses_order() {
+        let contents = r#"legacy_hooks = "after"
+
+            [hooks]
         "#;
-        let result = get_table_key_value_from_config(contents, "hooks", "pre-push");
-        assert_eq!(result, Err(String::from("Missing config key")));
+        let result = ConfigFile::try_from_str(contents).unwrap();
+        assert_eq!(result.legacy_hooks, LegacyHooks::After);
+
+        let contents = r#"legacy_hooks = "skip"
+
+            [hooks]
+        "#;
+        let result = ConfigFile::try_from_str(contents).unwrap();
+        assert_eq!(result.legacy_hooks, LegacyHooks::Skip);
     }
 
     #[test]
-    fn parses_hook_value() {
-        let contents = r#"[hooks]
-            pre-commit = "cargo test"
+    fn returns_err_on_unknown_order() {
+        let contents = r#"legacy_hooks = "during"
+
+            [hooks]
         "#;
-        let table = "hooks";
-        let key = "pre-commit";
-        let exp_value = "cargo test";
-        let result = get_table_key_value_from_config(contents, table, key).unwrap();
-        assert_eq!(
-            String::from(result.as_str().unwrap()),
-            String::from(exp_value)
-        );
+        let e = ConfigFile::try_from_str(contents).unwrap_err();
+
+        insta::assert_snapshot!(e, @r#"
+        TOML parse error at line 1, column 16
+          |
+        1 | legacy_hooks = "during"
+          |                ^^^^^^^^
+        unknown variant `during`, expected one of `before`, `after`, `skip`
+        "#);
     }
 }
 
 #[cfg(test)]
-mod get_log_setting_tests {
+mod logging_config_tests {
     use super::*;
 
     #[test]
-    fn returns_true_when_content_not_found() {
-        let invalid_contents = "90827342089734";
-        let result = get_log_setting(invalid_contents);
-        assert!(result);
+    fn defaults_to_failure_output_when_table_missing() {
+        let contents = "[hooks]";
+        let result = ConfigFile::try_from_str(contents).unwrap();
+        assert_eq!(result.logging, LoggingConfig::default());
+        assert!(!result.logging.verbose);
+        assert!(!result.logging.quiet);
+        assert!(!result.logging.stream);
     }
 
     #[test]
-    fn returns_true_when_log_not_boolean() {
-        let contents = r#"[logging]
+    fn returns_err_when_verbose_not_boolean() {
+        let contents = r#"[hooks]
+
+            [logging]
             verbose = "cargo test"
         "#;
-        let result = get_log_setting(contents);
-        assert!(result);
+        let e = ConfigFile::try_from_str(contents).unwrap_err();
+
+        insta::assert_snapshot!(e, @r#"
+        TOML parse error at line 4, column 23
+          |
+        4 |             verbose = "cargo test"
+          |                       ^^^^^^^^^^^^
+        invalid type: string "cargo test", expected a boolean
+        "#);
     }
 
     #[test]
-    fn returns_result_when_value_valid() {
-        let contents = "[logging]
+    fn returns_err_on_unknown_key() {
+        let contents = "[hooks]
+
+            [logging]
+            loud = true
+        ";
+        let e = ConfigFile::try_from_str(contents).unwrap_err();
+
+        insta::assert_snapshot!(e, @r"
+        TOML parse error at line 4, column 13
+          |
+        4 |             loud = true
+          |             ^^^^
+        unknown field `loud`, expected one of `verbose`, `quiet`, `stream`
+        ");
+    }
+
+    #[test]
+    fn parses_all_settings() {
+        let contents = "[hooks]
+
+            [logging]
             verbose = false
+            quiet = true
+            stream = true
         ";
-        let result = get_log_setting(contents);
-        assert!(!result);
+        let result = ConfigFile::try_from_str(contents).unwrap();
+        assert_eq!(
+            result.logging,
+            LoggingConfig {
+                verbose: false,
+                quiet: true,
+                stream: true,
+            }
+        );
     }
 }
 
@@ -266,12 +379,12 @@ mod get_hook_script_tests {
         ";
         let e = ConfigFile::try_from_str(contents).unwrap_err();
 
-        insta::assert_snapshot!(e, @r"
+        insta::assert_snapshot!(e, @"
         TOML parse error at line 2, column 24
           |
         2 |             pre-push = false
           |                        ^^^^^
-        invalid type: boolean `false`, expected a sequence
+        invalid type: boolean `false`, expected a command string, an array of tasks, an array of arrays of tasks or a hook table
         ");
     }
 
@@ -290,15 +403,44 @@ mod get_hook_script_tests {
 
         insta::assert_debug_snapshot!(xs, @r#"
         ConfigFile {
-            logging: Logging {
+            legacy_hooks: Before,
+            jobs: None,
+            logging: LoggingConfig {
                 verbose: false,
+                quiet: false,
+                stream: false,
             },
             hooks: {
-                "pre-commit": [
-                    [
-                        "cargo test",
+                "pre-commit": HookConfig {
+                    groups: [
+                        [
+                            TaskConfig {
+                                name: None,
+                                run: "cargo test",
+                                env: {},
+                                cwd: None,
+                                timeout: None,
+                                allow_failure: false,
+                                description: None,
+                                files: None,
+                                parallel_batches: false,
+                                fix: false,
+                                fail_on_fix: false,
+                                depends_on: [],
+                                exclusive_group: None,
+                                shell: None,
+                                cache: false,
+                                inputs: [],
+                                when: None,
+                            },
+                        ],
                     ],
-                ],
+                    timeout: None,
+                    task_timeout: None,
+                    fail_fast: true,
+                    stash_unstaged: false,
+                    shell: None,
+                },
             },
         }
         "#);
@@ -317,14 +459,63 @@ mod get_hook_script_tests {
 
         insta::assert_debug_snapshot!(xs, @r#"
         ConfigFile {
-            logging: None,
+            legacy_hooks: Before,
+            jobs: None,
+            logging: LoggingConfig {
+                verbose: false,
+                quiet: false,
+                stream: false,
+            },
             hooks: {
-                "pre-commit": [
-                    [
-                        "cargo test",
-                        "cargo fmt",
+                "pre-commit": HookConfig {
+                    groups: [
+                        [
+                            TaskConfig {
+                                name: None,
+                                run: "cargo test",
+                                env: {},
+                                cwd: None,
+                                timeout: None,
+                                allow_failure: false,
+                                description: None,
+                                files: None,
+                                parallel_batches: false,
+                                fix: false,
+                                fail_on_fix: false,
+                                depends_on: [],
+                                exclusive_group: None,
+                                shell: None,
+                                cache: false,
+                                inputs: [],
+                                when: None,
+                            },
+                            TaskConfig {
+                                name: None,
+                                run: "cargo fmt",
+                                env: {},
+                                cwd: None,
+                                timeout: None,
+                                allow_failure: false,
+                                description: None,
+                                files: None,
+                                parallel_batches: false,
+                                fix: false,
+                                fail_on_fix: false,
+                                depends_on: [],
+                                exclusive_group: None,
+                                shell: None,
+                                cache: false,
+                                inputs: [],
+                                when: None,
+                            },
+                        ],
                     ],
-                ],
+                    timeout: None,
+                    task_timeout: None,
+                    fail_fast: true,
+                    stash_unstaged: false,
+                    shell: None,
+                },
             },
         }
         "#);
@@ -341,12 +532,683 @@ mod get_hook_script_tests {
 
         let e = ConfigFile::try_from_str(contents).unwrap_err();
 
+        insta::assert_snapshot!(e, @"
+        TOML parse error at line 4, column 17
+          |
+        4 |                 8
+          |                 ^
+        invalid type: integer `8`, expected a command string, a task table or an array of tasks
+        ");
+    }
+}
+
+#[cfg(test)]
+mod hook_config_tests {
+    use super::*;
+
+    fn groups(contents: &str) -> Vec<Vec<String>> {
+        let mut config = ConfigFile::try_from_str(contents).unwrap();
+        config
+            .hooks
+            .remove("pre-commit")
+            .unwrap()
+            .groups
+            .into_iter()
+            .map(|group| group.into_iter().map(|task| task.run).collect())
+            .collect()
+    }
+
+    #[test]
+    fn parses_string_form() {
+        let result = groups(
+            r#"[hooks]
+            pre-commit = "cargo test"
+        "#,
+        );
+        assert_eq!(result, vec![vec![String::from("cargo test")]]);
+    }
+
+    #[test]
+    fn parses_flat_array_as_sequential_group() {
+        let result = groups(
+            r#"[hooks]
+            pre-commit = ["cargo check", "cargo fmt -- --check"]
+        "#,
+        );
+        assert_eq!(
+            result,
+            vec![vec![
+                String::from("cargo check"),
+                String::from("cargo fmt -- --check"),
+            ]]
+        );
+    }
+
+    #[test]
+    fn parses_nested_arrays_as_parallel_groups() {
+        let result = groups(
+            r#"[hooks]
+            pre-commit = [["cargo clippy"], ["cargo build", "cargo test"]]
+        "#,
+        );
+        assert_eq!(
+            result,
+            vec![
+                vec![String::from("cargo clippy")],
+                vec![String::from("cargo build"), String::from("cargo test")],
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_empty_array_as_no_groups() {
+        let result = groups(
+            "[hooks]
+            pre-commit = []
+        ",
+        );
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn returns_err_when_forms_mixed() {
+        let contents = r#"[hooks]
+            pre-commit = ["cargo check", ["cargo test"]]
+        "#;
+        let e = ConfigFile::try_from_str(contents).unwrap_err();
+
+        insta::assert_snapshot!(e, @r#"
+        TOML parse error at line 2, column 26
+          |
+        2 |             pre-commit = ["cargo check", ["cargo test"]]
+          |                          ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+        cannot mix tasks and arrays of tasks in the same hook
+        "#);
+    }
+
+    #[test]
+    fn returns_err_when_group_contains_non_string() {
+        let contents = r#"[hooks]
+            pre-commit = [["cargo check", 8]]
+        "#;
+        let e = ConfigFile::try_from_str(contents).unwrap_err();
+
         insta::assert_snapshot!(e, @r#"
-        TOML parse error at line 3, column 17
+        TOML parse error at line 2, column 43
           |
-        3 |                 "cargo test",
-          |                 ^^^^^^^^^^^^
-        invalid type: string "cargo test", expected a sequence
+        2 |             pre-commit = [["cargo check", 8]]
+          |                                           ^
+        invalid type: integer `8`, expected a command string or a task table
+        "#);
+    }
+
+    #[test]
+    fn serializes_normalized_groups() {
+        let config = ConfigFile::try_from_str(
+            r#"[hooks]
+            pre-commit = "cargo test"
+        "#,
+        )
+        .unwrap();
+        let result = toml::to_string(&config).unwrap();
+
+        insta::assert_snapshot!(result, @r#"
+        legacy_hooks = "before"
+
+        [logging]
+        verbose = false
+        quiet = false
+        stream = false
+
+        [hooks.pre-commit]
+        tasks = [[{ run = "cargo test" }]]
+        fail_fast = true
         "#);
     }
 }
+
+#[cfg(test)]
+mod task_config_tests {
+    use super::*;
+
+    fn tasks(contents: &str) -> Vec<Vec<TaskConfig>> {
+        let mut config = ConfigFile::try_from_str(contents).unwrap();
+        config.hooks.remove("pre-commit").unwrap().groups
+    }
+
+    #[test]
+    fn parses_array_of_tables_as_sequential_group() {
+        let result = tasks(
+            r#"[[hooks.pre-commit]]
+            name = "clippy"
+            run = "cargo clippy -- -D warnings"
+            env = { RUSTFLAGS = "-Dwarnings" }
+            cwd = "crates/core"
+            timeout = 300
+            allow_failure = true
+            description = "Lint the core crate"
+
+            [[hooks.pre-commit]]
+            run = "cargo test"
+        "#,
+        );
+
+        insta::assert_debug_snapshot!(result, @r#"
+        [
+            [
+                TaskConfig {
+                    name: Some(
+                        "clippy",
+                    ),
+                    run: "cargo clippy -- -D warnings",
+                    env: {
+                        "RUSTFLAGS": "-Dwarnings",
+                    },
+                    cwd: Some(
+                        "crates/core",
+                    ),
+                    timeout: Some(
+                        300,
+                    ),
+                    allow_failure: true,
+                    description: Some(
+                        "Lint the core crate",
+                    ),
+                    files: None,
+                    parallel_batches: false,
+                    fix: false,
+                    fail_on_fix: false,
+                    depends_on: [],
+                    exclusive_group: None,
+                    shell: None,
+                    cache: false,
+                    inputs: [],
+                    when: None,
+                },
+                TaskConfig {
+                    name: None,
+                    run: "cargo test",
+                    env: {},
+                    cwd: None,
+                    timeout: None,
+                    allow_failure: false,
+                    description: None,
+                    files: None,
+                    parallel_batches: false,
+                    fix: false,
+                    fail_on_fix: false,
+                    depends_on: [],
+                    exclusive_group: None,
+                    shell: None,
+                    cache: false,
+                    inputs: [],
+                    when: None,
+                },
+            ],
+        ]
+        "#);
+    }
+
+    #[test]
+    fn parses_tables_and_strings_in_parallel_groups() {
+        let result = tasks(
+            r#"[hooks]
+            pre-commit = [
+                [{ name = "fmt", run = "cargo fmt --check" }, "cargo clippy"],
+                ["cargo test"],
+            ]
+        "#,
+        );
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0][0].name(), "fmt");
+        assert_eq!(result[0][1], TaskConfig::from("cargo clippy"));
+        assert_eq!(result[1][0].name(), "cargo test");
+    }
+
+    #[test]
+    fn parses_single_task_table_in_flat_array() {
+        let result = tasks(
+            r#"[hooks]
+            pre-commit = ["cargo check", { run = "cargo test", allow_failure = true }]
+        "#,
+        );
+        assert_eq!(result.len(), 1);
+        assert!(!result[0][0].allow_failure);
+        assert!(result[0][1].allow_failure);
+    }
+
+    #[test]
+    fn returns_err_when_run_missing() {
+        let contents = r#"[[hooks.pre-commit]]
+            name = "clippy"
+        "#;
+        let e = ConfigFile::try_from_str(contents).unwrap_err();
+
+        insta::assert_snapshot!(e, @"
+        TOML parse error at line 1, column 1
+          |
+        1 | [[hooks.pre-commit]]
+          | ^^^^^^^^^^^^^^^^^^^^
+        missing field `run`
+        ");
+    }
+
+    #[test]
+    fn returns_err_on_unknown_task_key() {
+        let contents = r#"[[hooks.pre-commit]]
+            run = "cargo test"
+            command = "cargo test"
+        "#;
+        let e = ConfigFile::try_from_str(contents).unwrap_err();
+
+        insta::assert_snapshot!(e, @r#"
+        TOML parse error at line 3, column 13
+          |
+        3 |             command = "cargo test"
+          |             ^^^^^^^
+        unknown field `command`, expected one of `name`, `run`, `env`, `cwd`, `timeout`, `allow_failure`, `description`, `files`, `parallel_batches`, `fix`, `fail_on_fix`, `depends_on`, `exclusive_group`, `shell`, `cache`, `inputs`, `when`
+        "#);
+    }
+
+    #[test]
+    fn parses_files_glob() {
+        let result = tasks(
+            r#"[[hooks.pre-commit]]
+            run = "rustfmt --check"
+            files = "*.rs"
+        "#,
+        );
+        assert_eq!(result[0][0].files.as_deref(), Some("*.rs"));
+    }
+
+    #[test]
+    fn parses_fixers() {
+        let result = tasks(
+            r#"[[hooks.pre-commit]]
+            run = "cargo fmt"
+            fix = true
+
+            [[hooks.pre-commit]]
+            run = "cargo clippy --fix --allow-staged"
+            fail_on_fix = true
+        "#,
+        );
+        assert!(result[0][0].fix && !result[0][0].fail_on_fix);
+        assert!(!result[0][1].fix && result[0][1].fail_on_fix);
+    }
+
+    #[test]
+    fn parses_cache_inputs() {
+        let result = tasks(
+            r#"[[hooks.pre-commit]]
+            run = "cargo test"
+            cache = true
+
+            [[hooks.pre-commit]]
+            run = "cargo doc"
+            inputs = ["src/**/*.rs", "Cargo.*"]
+        "#,
+        );
+        assert!(result[0][0].cache && result[0][0].inputs.is_empty());
+        assert_eq!(result[0][1].inputs, ["src/**/*.rs", "Cargo.*"]);
+    }
+
+    #[test]
+    fn parses_conditions() {
+        let result = tasks(
+            r#"[[hooks.pre-commit]]
+            run = "cargo test --test integration"
+            when = { branch = "release/*", changed = ["Cargo.lock"], env = "CI_STRICT", os = "linux" }
+        "#,
+        );
+        assert_eq!(
+            result[0][0].when,
+            Some(TaskCondition {
+                branch: Some(String::from("release/*")),
+                changed: vec![String::from("Cargo.lock")],
+                env: Some(String::from("CI_STRICT")),
+                os: Some(String::from("linux")),
+            })
+        );
+    }
+
+    #[test]
+    fn returns_err_on_unknown_condition() {
+        let contents = r#"[[hooks.pre-push]]
+            run = "cargo test"
+            when = { tag = "v*" }
+        "#;
+        let e = ConfigFile::try_from_str(contents).unwrap_err();
+
+        insta::assert_snapshot!(e, @r#"
+        TOML parse error at line 3, column 22
+          |
+        3 |             when = { tag = "v*" }
+          |                      ^^^
+        unknown field `tag`, expected one of `branch`, `changed`, `env`, `os`
+        "#);
+    }
+
+    #[test]
+    fn parses_shell() {
+        let result = tasks(
+            r#"[hooks.pre-commit]
+            shell = "bash"
+            tasks = [
+                { run = "cargo fmt --check && cargo clippy" },
+                { run = "cargo test", shell = false },
+                { run = "cargo doc", shell = true },
+            ]
+        "#,
+        );
+        assert_eq!(result[0][0].shell, None);
+        assert_eq!(result[0][1].shell, Some(Shell::Disabled));
+        assert_eq!(result[0][2].shell, Some(Shell::Program(String::from("sh"))));
+
+        let mut config = ConfigFile::try_from_str(
+            r#"[hooks.pre-commit]
+            shell = "bash"
+            tasks = "cargo test"
+        "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.hooks.remove("pre-commit").unwrap().shell,
+            Some(Shell::Program(String::from("bash")))
+        );
+    }
+
+    #[test]
+    fn returns_err_on_invalid_shell() {
+        let result = ConfigFile::try_from_str(
+            r#"[[hooks.pre-commit]]
+            run = "cargo test"
+            shell = 1
+        "#,
+        );
+        insta::assert_snapshot!(result.unwrap_err(), @"
+        TOML parse error at line 3, column 21
+          |
+        3 |             shell = 1
+          |                     ^
+        invalid type: integer `1`, expected the name of a shell or a boolean
+        ");
+    }
+
+    #[test]
+    fn returns_err_on_invalid_files_glob() {
+        let contents = r#"[[hooks.pre-commit]]
+            run = "rustfmt --check"
+            files = "src/[a"
+        "#;
+        let e = ConfigFile::try_from_str(contents).unwrap_err();
+
+        insta::assert_snapshot!(e, @r#"
+        TOML parse error at line 3, column 21
+          |
+        3 |             files = "src/[a"
+          |                     ^^^^^^^^
+        invalid glob `src/[a`: Pattern syntax error near position 4: invalid range pattern
+        "#);
+    }
+
+    #[test]
+    fn returns_err_on_invalid_inputs_glob() {
+        let contents = r#"[[hooks.pre-commit]]
+            run = "cargo test"
+            inputs = ["src/**", "src/[a"]
+        "#;
+        let e = ConfigFile::try_from_str(contents).unwrap_err();
+
+        insta::assert_snapshot!(e, @r#"
+        TOML parse error at line 3, column 22
+          |
+        3 |             inputs = ["src/**", "src/[a"]
+          |                      ^^^^^^^^^^^^^^^^^^^^
+        invalid glob `src/[a`: Pattern syntax error near position 4: invalid range pattern
+        "#);
+    }
+}
+
+#[cfg(test)]
+mod dependencies_tests {
+    use super::*;
+
+    fn hook(contents: &str) -> HookConfig {
+        ConfigFile::try_from_str(contents)
+            .unwrap()
+            .hooks
+            .remove("pre-commit")
+            .unwrap()
+    }
+
+    #[test]
+    fn orders_tasks_of_each_group() {
+        let hook = hook(
+            r#"[hooks]
+            pre-commit = [["cargo build", "cargo test"], ["cargo fmt --check"]]
+        "#,
+        );
+        assert_eq!(hook.dependencies(), Ok(vec![vec![], vec![0], vec![]]));
+    }
+
+    #[test]
+    fn orders_tasks_by_depends_on_alone() {
+        let hook = hook(
+            r#"[[hooks.pre-commit]]
+            name = "build"
+            run = "cargo build"
+
+            [[hooks.pre-commit]]
+            run = "cargo clippy"
+            depends_on = ["build"]
+
+            [[hooks.pre-commit]]
+            run = "cargo test"
+            depends_on = ["build", "cargo clippy"]
+
+            [[hooks.pre-commit]]
+            run = "cargo fmt --check"
+        "#,
+        );
+        assert_eq!(
+            hook.dependencies(),
+            Ok(vec![vec![], vec![0], vec![0, 1], vec![]])
+        );
+    }
+
+    #[test]
+    fn returns_err_on_unknown_dependency() {
+        let result = ConfigFile::try_from_str(
+            r#"[[hooks.pre-commit]]
+            run = "cargo test"
+            depends_on = ["build"]
+        "#,
+        );
+        insta::assert_snapshot!(result.unwrap_err(), @"
+        TOML parse error at line 1, column 1
+          |
+        1 | [[hooks.pre-commit]]
+          | ^^^^^^^^^^^^^^^^^^^^
+        task `cargo test` depends on unknown task `build`
+        ");
+    }
+
+    #[test]
+    fn returns_err_on_ambiguous_dependency() {
+        let result = ConfigFile::try_from_str(
+            r#"[hooks]
+            pre-commit = [
+              ["cargo build"],
+              ["cargo build"],
+              [{ run = "cargo test", depends_on = ["cargo build"] }],
+            ]
+        "#,
+        );
+        insta::assert_snapshot!(result.unwrap_err(), @"
+        TOML parse error at line 2, column 26
+          |
+        2 |             pre-commit = [
+          |                          ^
+        task `cargo test` depends on `cargo build`, which names several tasks
+        ");
+    }
+
+    #[test]
+    fn returns_err_on_cycle() {
+        let result = ConfigFile::try_from_str(
+            r#"[[hooks.pre-commit]]
+            name = "build"
+            run = "cargo build"
+            depends_on = ["test"]
+
+            [[hooks.pre-commit]]
+            name = "clippy"
+            run = "cargo clippy"
+
+            [[hooks.pre-commit]]
+            name = "test"
+            run = "cargo test"
+            depends_on = ["clippy", "build"]
+        "#,
+        );
+        insta::assert_snapshot!(result.unwrap_err(), @"
+        TOML parse error at line 1, column 1
+          |
+        1 | [[hooks.pre-commit]]
+          | ^^^^^^^^^^^^^^^^^^^^
+        tasks depend on each other in a cycle: `build` -> `test` -> `build`
+        ");
+    }
+
+    #[test]
+    fn returns_err_on_self_dependency() {
+        let hook = HookConfig::from(vec![vec![TaskConfig {
+            depends_on: vec![String::from("cargo test")],
+            ..TaskConfig::from("cargo test")
+        }]]);
+        assert_eq!(
+            hook.dependencies(),
+            Err(String::from(
+                "tasks depend on each other in a cycle: `cargo test` -> `cargo test`"
+            ))
+        );
+    }
+}
+
+#[cfg(test)]
+mod hook_table_tests {
+    use super::*;
+
+    #[test]
+    fn parses_hook_table_with_timeout() {
+        let contents = r#"[hooks.pre-commit]
+            timeout = 600
+            tasks = [["cargo clippy"], ["cargo test"]]
+        "#;
+        let mut config = ConfigFile::try_from_str(contents).unwrap();
+        let hook = config.hooks.remove("pre-commit").unwrap();
+        assert_eq!(hook.timeout, Some(600));
+        assert_eq!(hook.groups.len(), 2);
+    }
+
+    #[test]
+    fn applies_task_timeout_to_tasks_without_one() {
+        let contents = r#"[hooks.pre-commit]
+            timeout = 600
+            task_timeout = 60
+            tasks = ["cargo clippy", { run = "cargo test", timeout = 300 }]
+        "#;
+        let mut config = ConfigFile::try_from_str(contents).unwrap();
+        let hook = config.hooks.remove("pre-commit").unwrap();
+        let timeouts: Vec<_> = hook.tasks().into_iter().map(|x| x.timeout).collect();
+        assert_eq!(timeouts, [Some(60), Some(300)]);
+    }
+
+    #[test]
+    fn parses_hook_table_with_string_tasks() {
+        let contents = r#"[hooks.pre-commit]
+            tasks = "cargo test"
+        "#;
+        let mut config = ConfigFile::try_from_str(contents).unwrap();
+        let hook = config.hooks.remove("pre-commit").unwrap();
+        assert_eq!(
+            hook,
+            HookConfig::from(vec![vec![TaskConfig::from("cargo test")]])
+        );
+    }
+
+    #[test]
+    fn parses_fail_fast() {
+        let contents = r#"[hooks.pre-commit]
+            fail_fast = false
+            tasks = [["cargo clippy"], ["cargo test"]]
+        "#;
+        let mut config = ConfigFile::try_from_str(contents).unwrap();
+        assert!(!config.hooks.remove("pre-commit").unwrap().fail_fast);
+    }
+
+    #[test]
+    fn defaults_to_fail_fast() {
+        let contents = r#"[hooks]
+            pre-commit = "cargo test"
+            pre-push = { tasks = "cargo test" }
+        "#;
+        let mut config = ConfigFile::try_from_str(contents).unwrap();
+        assert!(config.hooks.remove("pre-commit").unwrap().fail_fast);
+        assert!(config.hooks.remove("pre-push").unwrap().fail_fast);
+    }
+
+    #[test]
+    fn parses_stash_unstaged() {
+        let contents = r#"[hooks]
+            pre-commit = { tasks = "cargo test", stash_unstaged = true }
+            pre-push = "cargo test"
+        "#;
+        let mut config = ConfigFile::try_from_str(contents).unwrap();
+        assert!(config.hooks.remove("pre-commit").unwrap().stash_unstaged);
+        assert!(!config.hooks.remove("pre-push").unwrap().stash_unstaged);
+    }
+
+    #[test]
+    fn defaults_to_no_hook_timeout() {
+        let contents = r#"[hooks]
+            pre-commit = "cargo test"
+        "#;
+        let mut config = ConfigFile::try_from_str(contents).unwrap();
+        assert_eq!(config.hooks.remove("pre-commit").unwrap().timeout, None);
+    }
+
+    #[test]
+    fn returns_err_when_tasks_missing() {
+        let contents = "[hooks.pre-commit]
+            timeout = 600
+        ";
+        let e = ConfigFile::try_from_str(contents).unwrap_err();
+
+        insta::assert_snapshot!(e, @"
+        TOML parse error at line 1, column 1
+          |
+        1 | [hooks.pre-commit]
+          | ^^^^^^^^^^^^^^^^^^
+        missing field `tasks`
+        ");
+    }
+
+    #[test]
+    fn returns_err_on_unknown_hook_key() {
+        let contents = r#"[hooks.pre-commit]
+            tasks = "cargo test"
+            retries = 3
+        "#;
+        let e = ConfigFile::try_from_str(contents).unwrap_err();
+
+        insta::assert_snapshot!(e, @"
+        TOML parse error at line 3, column 13
+          |
+        3 |             retries = 3
+          |             ^^^^^^^
+        unknown field `retries`, expected one of `tasks`, `timeout`, `task_timeout`, `fail_fast`, `stash_unstaged`, `shell`
+        ");
+    }
+}
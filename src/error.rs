@@ -0,0 +1,135 @@
+use crate::{
+    config::NO_CONFIG_FILE_FOUND,
+    hooks::{NO_CONFIG_FILE_FOUND_ERROR_CODE, TIMEOUT_ERROR_CODE},
+};
+use std::{fmt, ops::Range};
+
+/// Why an operation of the library failed.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The current directory is not inside a git repository.
+    NotAGitRepo,
+    /// The repository has no config file.
+    ConfigNotFound,
+    /// The config file could not be read.
+    ConfigRead { path: String },
+    /// The config file is not valid, with the byte range of the offending
+    /// part, if known.
+    ConfigParse {
+        path: String,
+        span: Option<Range<usize>>,
+        source: Box<toml::de::Error>,
+    },
+    /// A hook, script or config file could not be written by `init`.
+    HookWrite {
+        path: String,
+        source: std::io::Error,
+    },
+    /// The command of a task exited unsuccessfully. With batches of files,
+    /// the exit code and stderr are those of the failed batches.
+    TaskFailed {
+        name: String,
+        command: String,
+        exit_code: Option<i32>,
+        stderr: String,
+        failed_batches: usize,
+        batches: usize,
+    },
+    /// The command of a task could not be spawned or waited for.
+    TaskSpawn {
+        name: String,
+        source: std::io::Error,
+    },
+    /// A task or a whole hook ran for longer than its timeout.
+    Timeout { name: String, secs: u64 },
+    /// Several tasks of a hook failed, with the name of each.
+    TasksFailed(Vec<(String, Error)>),
+    /// The hook was interrupted with Ctrl-C.
+    Interrupted,
+    /// Any other failure, such as a git command failing.
+    Other(String),
+}
+
+impl Error {
+    /// The exit code the `crusty-hooks` binary reports the error with.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::ConfigNotFound => NO_CONFIG_FILE_FOUND_ERROR_CODE,
+            Error::Timeout { .. } => TIMEOUT_ERROR_CODE,
+            Error::TasksFailed(failures)
+                if failures
+                    .iter()
+                    .any(|(_, x)| matches!(x, Error::Timeout { .. })) =>
+            {
+                TIMEOUT_ERROR_CODE
+            }
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotAGitRepo => write!(f, "Failure determining git repo root directory"),
+            Error::ConfigNotFound => write!(f, "{NO_CONFIG_FILE_FOUND}"),
+            Error::ConfigRead { path } => write!(f, "Failed to read {path}"),
+            Error::ConfigParse { path, source, .. } => {
+                write!(
+                    f,
+                    "Invalid config file {path}: {}",
+                    source.to_string().trim_end()
+                )
+            }
+            Error::HookWrite { path, source } => write!(f, "Unable to write {path}: {source}"),
+            Error::TaskFailed {
+                command,
+                exit_code,
+                failed_batches,
+                batches,
+                ..
+            } => {
+                write!(f, "Command `{command}` failed with exit code {exit_code:?}")?;
+
+                if *batches > 1 {
+                    write!(f, " in {failed_batches} of {batches} batches")?;
+                }
+
+                Ok(())
+            }
+            Error::TaskSpawn { source, .. } => write!(f, "{source}"),
+            Error::Timeout { name, secs } => write!(f, "`{name}` timed out after {secs}s"),
+            Error::TasksFailed(failures) => {
+                write!(f, "{} tasks failed:", failures.len())?;
+
+                for (name, error) in failures {
+                    write!(f, "\n  - {name}: {error}")?;
+                }
+
+                Ok(())
+            }
+            Error::Interrupted => write!(f, "Interrupted"),
+            Error::Other(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::ConfigParse { source, .. } => Some(source.as_ref()),
+            Error::HookWrite { source, .. } | Error::TaskSpawn { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Other(message)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,78 @@
+use super::*;
+use std::error::Error as _;
+
+#[cfg(test)]
+mod exit_code_tests {
+    use super::*;
+
+    fn timeout(name: &str) -> Error {
+        Error::Timeout {
+            name: name.to_string(),
+            secs: 5,
+        }
+    }
+
+    #[test]
+    fn maps_missing_config_and_timeouts() {
+        assert_eq!(
+            Error::ConfigNotFound.exit_code(),
+            NO_CONFIG_FILE_FOUND_ERROR_CODE
+        );
+        assert_eq!(timeout("test").exit_code(), TIMEOUT_ERROR_CODE);
+        assert_eq!(Error::NotAGitRepo.exit_code(), 1);
+    }
+
+    #[test]
+    fn reports_timeout_when_any_task_timed_out() {
+        let failures = |error: Error| {
+            Error::TasksFailed(vec![
+                (String::from("clippy"), Error::Other(String::from("broke"))),
+                (String::from("test"), error),
+            ])
+        };
+
+        assert_eq!(failures(timeout("test")).exit_code(), TIMEOUT_ERROR_CODE);
+        assert_eq!(failures(Error::Interrupted).exit_code(), 1);
+    }
+}
+
+#[cfg(test)]
+mod display_tests {
+    use super::*;
+
+    #[test]
+    fn counts_failed_batches() {
+        let failed = |batches: usize| Error::TaskFailed {
+            name: String::from("clippy"),
+            command: String::from("cargo clippy"),
+            exit_code: Some(101),
+            stderr: String::from("error: unused variable"),
+            failed_batches: 1,
+            batches,
+        };
+
+        assert_eq!(
+            failed(1).to_string(),
+            "Command `cargo clippy` failed with exit code Some(101)"
+        );
+        assert_eq!(
+            failed(3).to_string(),
+            "Command `cargo clippy` failed with exit code Some(101) in 1 of 3 batches"
+        );
+    }
+
+    #[test]
+    fn exposes_sources() {
+        let error = Error::HookWrite {
+            path: String::from("/repo/.git/hooks/pre-commit"),
+            source: std::io::Error::other("Permission denied"),
+        };
+
+        assert_eq!(
+            error.to_string(),
+            "Unable to write /repo/.git/hooks/pre-commit: Permission denied"
+        );
+        assert_eq!(error.source().unwrap().to_string(), "Permission denied");
+        assert!(Error::NotAGitRepo.source().is_none());
+    }
+}
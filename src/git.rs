@@ -1,5 +1,10 @@
 use super::hooks;
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+    process::Stdio,
+};
 
 pub(crate) fn get_root_directory_path<F>(
     run_command: F,
@@ -21,7 +26,7 @@ where
     )
 }
 
-fn get_hooks_directory<F>(
+pub(crate) fn get_hooks_directory<F>(
     run_command: F,
     root_directory: &str,
 ) -> Result<Option<String>, Option<String>>
@@ -41,11 +46,526 @@ where
     )
 }
 
-pub(super) fn setup_hooks<F, G>(
+/// Runs a git command listing NUL separated paths.
+fn list_files<F>(
     run_command: F,
-    write_file: G,
+    root_directory: &str,
+    cmd: &str,
+    error: &str,
+) -> Result<Vec<String>, String>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+{
+    match run_command(cmd, Some(root_directory), false, None) {
+        Ok(output) => Ok(output
+            .unwrap_or_default()
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(String::from)
+            .collect()),
+        Err(_) => Err(String::from(error)),
+    }
+}
+
+/// Paths, relative to the repository root, of the files added, copied,
+/// modified or renamed in the index.
+pub(crate) fn get_staged_files<F>(
+    run_command: F,
+    root_directory: &str,
+) -> Result<Vec<String>, String>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+{
+    // NUL separated, so git does not quote unusual paths.
+    list_files(
+        run_command,
+        root_directory,
+        "git diff --cached --name-only --diff-filter=ACMR -z",
+        "Failed to list staged files",
+    )
+}
+
+/// Paths, relative to the repository root, of the tracked files whose working
+/// tree differs from the index.
+pub(crate) fn get_unstaged_files<F>(
+    run_command: F,
+    root_directory: &str,
+) -> Result<Vec<String>, String>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+{
+    list_files(
+        run_command,
+        root_directory,
+        "git diff --name-only -z",
+        "Failed to list unstaged files",
+    )
+}
+
+/// Paths, relative to the repository root, of every file in the index.
+pub(crate) fn get_tracked_files<F>(
+    run_command: F,
+    root_directory: &str,
+) -> Result<Vec<String>, String>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+{
+    list_files(
+        run_command,
+        root_directory,
+        "git ls-files -z",
+        "Failed to list tracked files",
+    )
+}
+
+/// Paths, relative to the repository root, of the files added, copied,
+/// modified or renamed on `to_ref` since it forked from `from_ref`.
+pub(crate) fn get_changed_files<F>(
+    run_command: F,
+    root_directory: &str,
+    from_ref: &str,
+    to_ref: &str,
+) -> Result<Vec<String>, String>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+{
+    let range = shlex::try_quote(&format!("{from_ref}...{to_ref}"))
+        .map_err(|_| format!("Invalid ref range {from_ref}...{to_ref}"))?
+        .into_owned();
+
+    list_files(
+        run_command,
+        root_directory,
+        &format!("git diff --name-only --diff-filter=ACMR -z {range}"),
+        &format!("Failed to list the files changed between {from_ref} and {to_ref}"),
+    )
+}
+
+/// Absolute path of the directory holding the cached task results, which
+/// lives in the git directory so that it is never committed.
+pub(crate) fn get_cache_directory<F>(
+    run_command: F,
+    root_directory: &str,
+) -> Result<PathBuf, String>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+{
+    match run_command(
+        "git rev-parse --git-path crusty-hooks/cache",
+        Some(root_directory),
+        false,
+        None,
+    ) {
+        // Relative to the repository root, unless the git directory is
+        // elsewhere.
+        Ok(Some(path)) if !path.is_empty() => Ok(Path::new(root_directory).join(path)),
+        _ => Err(String::from("Failed to determine the cache directory")),
+    }
+}
+
+/// Id of a tree object holding the working tree, including the untracked
+/// files that are not ignored.
+///
+/// The tree is written from a copy of the index, which leaves the index alone
+/// and only rehashes the files that changed.
+pub(crate) fn get_working_tree_id<F>(run_git: F, root_directory: &str) -> Result<String, String>
+where
+    F: Fn(&str, &[&str], &[(&str, &str)], Option<&[u8]>) -> Result<Vec<u8>, String>,
+{
+    let error = || String::from("Failed to hash the working tree");
+    let git_path = |path: &str| {
+        run_git(
+            root_directory,
+            &["rev-parse", "--git-path", path],
+            &[],
+            None,
+        )
+        .map(|x| Path::new(root_directory).join(String::from_utf8_lossy(&x).trim_end()))
+        .map_err(|_| error())
+    };
+
+    // Git sets GIT_INDEX_FILE for hooks of commits that use a temporary
+    // index, such as `git commit -a`, which `--git-path` resolves to.
+    let index = git_path("index")?;
+    let copy = git_path(&format!("crusty-hooks/index.{}", std::process::id()))?;
+
+    if let Some(parent) = copy.parent() {
+        std::fs::create_dir_all(parent).map_err(|_| error())?;
+    }
+
+    // A repository without commits may not have an index yet.
+    let _ = std::fs::copy(&index, &copy);
+
+    let envs = [("GIT_INDEX_FILE", &*copy.to_string_lossy())];
+    let tree = run_git(root_directory, &["add", "--all"], &envs, None)
+        .and_then(|_| run_git(root_directory, &["write-tree"], &envs, None));
+
+    let _ = std::fs::remove_file(&copy);
+
+    match tree {
+        Ok(tree) if !tree.trim_ascii().is_empty() => {
+            Ok(String::from_utf8_lossy(tree.trim_ascii()).into_owned())
+        }
+        _ => Err(error()),
+    }
+}
+
+/// Paths and object ids of the files in `tree`.
+pub(crate) fn list_tree<F>(
+    run_command: F,
+    root_directory: &str,
+    tree: &str,
+) -> Result<Vec<(String, String)>, String>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+{
+    // Each entry is `<mode> <type> <object>\t<path>`.
+    Ok(list_files(
+        run_command,
+        root_directory,
+        &format!("git ls-tree -r -z {tree}"),
+        "Failed to list the working tree",
+    )?
+    .into_iter()
+    .filter_map(|entry| {
+        let (meta, path) = entry.split_once('\t')?;
+        let object = meta.split(' ').nth(2)?;
+
+        Some((path.to_string(), object.to_string()))
+    })
+    .collect())
+}
+
+/// Short name of the branch checked out, or `None` when `HEAD` is detached.
+pub(crate) fn get_current_branch<F>(run_command: F, root_directory: &str) -> Option<String>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+{
+    // Unlike `rev-parse`, also names the branch of a repository without
+    // commits, and exits quietly with a non-zero code on a detached `HEAD`.
+    match run_command(
+        "git symbolic-ref --short -q HEAD",
+        Some(root_directory),
+        false,
+        None,
+    ) {
+        Ok(branch) => branch.filter(|x| !x.is_empty()),
+        Err(_) => None,
+    }
+}
+
+/// Paths of the files changed by the commits of `local_sha` that are not
+/// `remote_sha`, or on any remote when the remote ref is created.
+pub(crate) fn get_pushed_files<F>(
+    run_command: F,
+    root_directory: &str,
+    local_sha: &str,
+    remote_sha: &str,
+) -> Result<Vec<String>, String>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+{
+    let range = if is_null_sha(remote_sha) {
+        format!("{local_sha} --not --remotes")
+    } else {
+        format!("{remote_sha}..{local_sha}")
+    };
+
+    list_files(
+        run_command,
+        root_directory,
+        &format!("git log -z --name-only --format= {range}"),
+        "Failed to list the pushed files",
+    )
+}
+
+/// Whether `sha` is the object id git uses for refs that do not exist.
+pub(crate) fn is_null_sha(sha: &str) -> bool {
+    sha.chars().all(|x| x == '0')
+}
+
+/// Adds `files`, relative to the repository root, to the index.
+pub(crate) fn stage_files<F>(
+    run_command: F,
+    root_directory: &str,
+    files: &[String],
+) -> Result<(), String>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+{
+    let error = || format!("Failed to stage {}", files.join(", "));
+
+    let Ok(quoted) = shlex::try_join(files.iter().map(String::as_str)) else {
+        return Err(error());
+    };
+
+    match run_command(
+        &format!("git add -- {quoted}"),
+        Some(root_directory),
+        false,
+        None,
+    ) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(error()),
+    }
+}
+
+/// Runs git with `args` in `dir` without going through a shell, with the
+/// `envs` variables set and `stdin` written to its standard input.
+///
+/// Returns what git printed to its standard output, or to its standard error
+/// when it failed.
+pub(crate) fn run_git(
+    dir: &str,
+    args: &[&str],
+    envs: &[(&str, &str)],
+    stdin: Option<&[u8]>,
+) -> Result<Vec<u8>, String> {
+    let mut command = std::process::Command::new("git");
+
+    command
+        .current_dir(dir)
+        .args(args)
+        .envs(envs.iter().copied())
+        .stdin(match stdin {
+            Some(_) => Stdio::piped(),
+            None => Stdio::null(),
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut child = command.spawn().map_err(|e| e.to_string())?;
+
+    // Written from another thread, so that git never blocks on a full
+    // stdout pipe while the input is still being written.
+    let output = std::thread::scope(|scope| {
+        if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            scope.spawn(move || pipe.write_all(input));
+        }
+
+        child.wait_with_output()
+    })
+    .map_err(|e| e.to_string())?;
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).into_owned())
+    }
+}
+
+const STASH_MESSAGE: &str = "crusty-hooks: unstaged changes";
+
+/// Stashes the unstaged changes of tracked files and the untracked files,
+/// leaving the index checked out in the working tree.
+///
+/// Returns the commit id of the stash, or `None` when there was nothing to
+/// stash.
+pub(crate) fn stash_unstaged_changes<F>(
+    run_git: F,
+    root_directory: &str,
+) -> Result<Option<String>, String>
+where
+    F: Fn(&str, &[&str], &[(&str, &str)], Option<&[u8]>) -> Result<Vec<u8>, String>,
+{
+    let run = |args: &[&str]| run_git(root_directory, args, &[], None);
+
+    // There is nothing to stash against before the first commit.
+    if run(&["rev-parse", "--verify", "--quiet", "HEAD"]).is_err() {
+        return Ok(None);
+    }
+
+    // `git diff --quiet` exits with an error when there are unstaged changes.
+    let has_unstaged = run(&["diff", "--quiet"]).is_err();
+    let has_untracked = match run(&["ls-files", "--others", "--exclude-standard"]) {
+        Ok(output) => !output.trim_ascii().is_empty(),
+        Err(_) => return Err(String::from("Failed to list untracked files")),
+    };
+
+    if !has_unstaged && !has_untracked {
+        return Ok(None);
+    }
+
+    if run(&[
+        "stash",
+        "push",
+        "--keep-index",
+        "--include-untracked",
+        "--quiet",
+        "--message",
+        STASH_MESSAGE,
+    ])
+    .is_err()
+    {
+        return Err(String::from("Failed to stash unstaged changes"));
+    }
+
+    match run(&["rev-parse", "--verify", "refs/stash"]) {
+        Ok(stash) if !stash.trim_ascii().is_empty() => Ok(Some(
+            String::from_utf8_lossy(stash.trim_ascii()).into_owned(),
+        )),
+        _ => Err(String::from("Failed to stash unstaged changes")),
+    }
+}
+
+/// Puts the changes stashed by [`stash_unstaged_changes`] back, discarding
+/// whatever the tasks left unstaged in the working tree.
+///
+/// On failure, the error names the command that recovers the changes.
+pub(crate) fn restore_unstaged_changes<F>(
+    run_git: F,
+    root_directory: &str,
+    stash: &str,
+) -> Result<(), String>
+where
+    F: Fn(&str, &[&str], &[(&str, &str)], Option<&[u8]>) -> Result<Vec<u8>, String>,
+{
+    let run = |args: &[&str], stdin: Option<&[u8]>| run_git(root_directory, args, &[], stdin);
+    let succeeds = |args: &[&str]| run(args, None).is_ok();
+    let lists_files = |args: &[&str]| run(args, None).is_ok_and(|x| !x.trim_ascii().is_empty());
+    // The patch printed by `args`, fed to `git apply`.
+    let applies = |args: &[&str]| run(args, None).is_ok_and(|x| run(&["apply"], Some(&x)).is_ok());
+
+    let (unstaged, untracked) = (format!("{stash}^2"), format!("{stash}^3"));
+
+    // Popping the stash would merge it against HEAD and conflict wherever the
+    // staged and unstaged changes overlap, so the unstaged diff (index to
+    // working tree) and the untracked files (a parentless commit, which exists
+    // even when there were none) are applied as patches on top of the index
+    // instead.
+    //
+    // The stash is only dropped when it is still on top, so that no other
+    // stash is dropped by mistake.
+    let restored = matches!(
+        run(&["rev-parse", "--verify", "refs/stash"], None),
+        Ok(top) if top.trim_ascii() == stash.as_bytes()
+    ) && succeeds(&["checkout", "--", "."])
+        && (succeeds(&["diff", "--quiet", &unstaged, stash])
+            || applies(&["diff", "--binary", &unstaged, stash]))
+        && (!lists_files(&["ls-tree", "-r", "--name-only", &untracked])
+            || applies(&["show", "--binary", "--format=", &untracked]))
+        && succeeds(&["stash", "drop", "--quiet"]);
+
+    if restored {
+        Ok(())
+    } else {
+        Err(format!(
+            "Failed to restore unstaged changes. Run `git stash apply {stash}` to recover them"
+        ))
+    }
+}
+
+/// The configured `core.hooksPath`, if any.
+pub(crate) fn get_hooks_path_config<F>(run_command: F, root_directory: &str) -> Option<String>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+{
+    // `git config --get` exits with an error when the key is unset.
+    match run_command(
+        "git config --get core.hooksPath",
+        Some(root_directory),
+        false,
+        None,
+    ) {
+        Ok(Some(path)) if !path.is_empty() => Some(path),
+        _ => None,
+    }
+}
+
+/// Creates `hooks_path` and points `core.hooksPath` at it.
+fn set_hooks_path_config<F>(
+    run_command: F,
+    root_directory: &str,
+    hooks_path: &str,
+) -> Result<(), String>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+{
+    let Ok(quoted) = shlex::try_quote(hooks_path) else {
+        return Err(format!("Invalid hooks path {hooks_path:?}"));
+    };
+
+    // Git resolves a relative `core.hooksPath` against the repository root.
+    let created = std::fs::create_dir_all(Path::new(root_directory).join(hooks_path));
+    let cmd = format!("git config core.hooksPath {quoted}");
+
+    if created.is_err() || run_command(&cmd, Some(root_directory), false, None).is_err() {
+        return Err(format!("Failed to set core.hooksPath to {hooks_path}"));
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn setup_hooks<F, G, H, I>(
+    run_command: F,
+    read_file: G,
+    write_file: H,
+    file_exists: I,
     root_directory_path: &str,
+    hooks_path: Option<&str>,
     hook_file_skip_list: &[&str],
+    opt_in_hooks: &[&str],
 ) -> Result<(), String>
 where
     F: Fn(
@@ -54,19 +574,107 @@ where
         bool,
         Option<&HashMap<String, String>>,
     ) -> Result<Option<String>, Option<String>>,
-    G: Fn(&str, &str, bool) -> Result<(), String>,
+    G: Fn(&str) -> Result<String, ()>,
+    H: Fn(&str, &str, bool) -> Result<(), String>,
+    I: Fn(&str) -> Result<bool, ()>,
 {
-    let hooks_directory = match get_hooks_directory(&run_command, root_directory_path) {
-        Ok(Some(path)) => path,
-        _ => return Err(String::from("Failure determining git hooks directory")),
+    let hooks_directory = match hooks_path {
+        Some(path) => {
+            set_hooks_path_config(&run_command, root_directory_path, path)?;
+            String::from(path)
+        }
+        None => {
+            let hooks_directory = match get_hooks_directory(&run_command, root_directory_path) {
+                Ok(Some(path)) => path,
+                _ => return Err(String::from("Failure determining git hooks directory")),
+            };
+
+            // Another hook manager (husky, lefthook, ...) pointed git at its own
+            // directory; installing there would break it.
+            if let Some(configured) = get_hooks_path_config(&run_command, root_directory_path)
+                && hooks::has_foreign_hooks(
+                    &read_file,
+                    &file_exists,
+                    root_directory_path,
+                    &hooks_directory,
+                )?
+            {
+                return Err(format!(
+                    "core.hooksPath is set to {configured}, which holds hooks of another tool. \
+                     Unset it or use `crusty-hooks init --hooks-path <dir>` to replace it"
+                ));
+            }
+
+            hooks_directory
+        }
     };
     hooks::create_hook_files(
+        read_file,
         write_file,
+        file_exists,
         root_directory_path,
         &hooks_directory,
         hook_file_skip_list,
+        opt_in_hooks,
     )
 }
 
+pub(super) fn remove_hooks<F, G, H, I, J>(
+    run_command: F,
+    read_file: G,
+    write_file: H,
+    file_exists: I,
+    remove_file: J,
+    root_directory_path: &str,
+) -> Result<hooks::UninstallSummary, String>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+    G: Fn(&str) -> Result<String, ()>,
+    H: Fn(&str, &str, bool) -> Result<(), String>,
+    I: Fn(&str) -> Result<bool, ()>,
+    J: Fn(&str) -> Result<(), String>,
+{
+    let hooks_directory = match get_hooks_directory(&run_command, root_directory_path) {
+        Ok(Some(path)) => path,
+        _ => return Err(String::from("Failure determining git hooks directory")),
+    };
+    let mut summary = hooks::remove_hook_files(
+        read_file,
+        write_file,
+        file_exists,
+        remove_file,
+        root_directory_path,
+        &hooks_directory,
+    )?;
+
+    // Left set, it would keep git from running the hooks in `.git/hooks`.
+    let root = Path::new(root_directory_path);
+
+    if !summary.removed.is_empty()
+        && let Some(hooks_path) = get_hooks_path_config(&run_command, root_directory_path)
+        && root.join(&hooks_path) == root.join(&hooks_directory)
+    {
+        if run_command(
+            "git config --unset core.hooksPath",
+            Some(root_directory_path),
+            false,
+            None,
+        )
+        .is_err()
+        {
+            return Err(format!("Failed to unset core.hooksPath {hooks_path}"));
+        }
+
+        summary.unset_hooks_path = Some(hooks_path);
+    }
+
+    Ok(summary)
+}
+
 #[cfg(test)]
 mod tests;
@@ -1,6 +1,6 @@
 use super::*;
 
-use crate::tests::utils::{GIT_REV_PARSE_CMD, build_simple_command_runner};
+use crate::tests::utils::{GIT_REV_PARSE_CMD, build_simple_command_runner, temp_path};
 
 mod get_root_directory_path_tests {
     use super::*;
@@ -62,6 +62,481 @@ mod get_hooks_directory_tests {
     }
 }
 
+mod get_staged_files_tests {
+    use super::*;
+
+    #[test]
+    fn splits_nul_separated_paths() {
+        let run_command = |cmd: &str,
+                           dir: Option<&str>,
+                           _stream_io: bool,
+                           _env: Option<&HashMap<String, String>>| {
+            assert_eq!(cmd, "git diff --cached --name-only --diff-filter=ACMR -z");
+            assert_eq!(dir, Some("/usr/repos/foo"));
+            Ok(Some(String::from("src/a b.rs\0README.md\0")))
+        };
+        let result = get_staged_files(run_command, "/usr/repos/foo");
+        assert_eq!(
+            result,
+            Ok(vec![String::from("src/a b.rs"), String::from("README.md")])
+        );
+    }
+
+    #[test]
+    fn returns_empty_without_staged_files() {
+        let run_command = build_simple_command_runner(Ok(Some(String::new())));
+        assert_eq!(get_staged_files(run_command, ""), Ok(vec![]));
+    }
+
+    #[test]
+    fn returns_error_on_command_error() {
+        let run_command = build_simple_command_runner(Err(None));
+        assert_eq!(
+            get_staged_files(run_command, ""),
+            Err(String::from("Failed to list staged files"))
+        );
+    }
+}
+
+mod get_unstaged_files_tests {
+    use super::*;
+
+    #[test]
+    fn splits_nul_separated_paths() {
+        let run_command = |cmd: &str,
+                           _dir: Option<&str>,
+                           _stream_io: bool,
+                           _env: Option<&HashMap<String, String>>| {
+            assert_eq!(cmd, "git diff --name-only -z");
+            Ok(Some(String::from("src/lib.rs\0")))
+        };
+        let result = get_unstaged_files(run_command, "/usr/repos/foo");
+        assert_eq!(result, Ok(vec![String::from("src/lib.rs")]));
+    }
+
+    #[test]
+    fn returns_error_on_command_error() {
+        let run_command = build_simple_command_runner(Err(None));
+        assert_eq!(
+            get_unstaged_files(run_command, ""),
+            Err(String::from("Failed to list unstaged files"))
+        );
+    }
+}
+
+mod get_tracked_files_tests {
+    use super::*;
+
+    #[test]
+    fn splits_nul_separated_paths() {
+        let run_command = |cmd: &str,
+                           dir: Option<&str>,
+                           _stream_io: bool,
+                           _env: Option<&HashMap<String, String>>| {
+            assert_eq!(cmd, "git ls-files -z");
+            assert_eq!(dir, Some("/usr/repos/foo"));
+            Ok(Some(String::from("Cargo.toml\0src/lib.rs\0")))
+        };
+        let result = get_tracked_files(run_command, "/usr/repos/foo");
+        assert_eq!(
+            result,
+            Ok(vec![String::from("Cargo.toml"), String::from("src/lib.rs")])
+        );
+    }
+
+    #[test]
+    fn returns_error_on_command_error() {
+        let run_command = build_simple_command_runner(Err(None));
+        assert_eq!(
+            get_tracked_files(run_command, ""),
+            Err(String::from("Failed to list tracked files"))
+        );
+    }
+}
+
+mod get_changed_files_tests {
+    use super::*;
+
+    #[test]
+    fn lists_files_changed_since_merge_base() {
+        let run_command = |cmd: &str,
+                           _dir: Option<&str>,
+                           _stream_io: bool,
+                           _env: Option<&HashMap<String, String>>| {
+            assert_eq!(
+                cmd,
+                "git diff --name-only --diff-filter=ACMR -z origin/main...HEAD"
+            );
+            Ok(Some(String::from("src/lib.rs\0")))
+        };
+        let result = get_changed_files(run_command, "/usr/repos/foo", "origin/main", "HEAD");
+        assert_eq!(result, Ok(vec![String::from("src/lib.rs")]));
+    }
+
+    #[test]
+    fn quotes_refs() {
+        let run_command = |cmd: &str,
+                           _dir: Option<&str>,
+                           _stream_io: bool,
+                           _env: Option<&HashMap<String, String>>| {
+            assert_eq!(
+                cmd,
+                "git diff --name-only --diff-filter=ACMR -z 'main...HEAD; rm -rf .'"
+            );
+            Ok(None)
+        };
+        let result = get_changed_files(run_command, "", "main", "HEAD; rm -rf .");
+        assert_eq!(result, Ok(vec![]));
+    }
+
+    #[test]
+    fn returns_error_on_command_error() {
+        let run_command = build_simple_command_runner(Err(None));
+        assert_eq!(
+            get_changed_files(run_command, "", "main", "HEAD"),
+            Err(String::from(
+                "Failed to list the files changed between main and HEAD"
+            ))
+        );
+    }
+}
+
+mod get_cache_directory_tests {
+    use super::*;
+
+    #[test]
+    fn joins_git_path_to_root() {
+        let run_command = |cmd: &str,
+                           _dir: Option<&str>,
+                           _stream_io: bool,
+                           _env: Option<&HashMap<String, String>>| {
+            assert_eq!(cmd, "git rev-parse --git-path crusty-hooks/cache");
+            Ok(Some(String::from(".git/crusty-hooks/cache")))
+        };
+        let result = get_cache_directory(run_command, "/usr/repos/foo");
+        assert_eq!(
+            result,
+            Ok(PathBuf::from("/usr/repos/foo/.git/crusty-hooks/cache"))
+        );
+    }
+
+    #[test]
+    fn keeps_absolute_git_path() {
+        let run_command = build_simple_command_runner(Ok(Some(String::from(
+            "/usr/repos/foo.git/crusty-hooks/cache",
+        ))));
+        let result = get_cache_directory(run_command, "/usr/repos/foo");
+        assert_eq!(
+            result,
+            Ok(PathBuf::from("/usr/repos/foo.git/crusty-hooks/cache"))
+        );
+    }
+
+    #[test]
+    fn returns_error_on_command_error() {
+        let run_command = build_simple_command_runner(Err(None));
+        assert_eq!(
+            get_cache_directory(run_command, ""),
+            Err(String::from("Failed to determine the cache directory"))
+        );
+    }
+}
+
+mod get_working_tree_id_tests {
+    use super::*;
+
+    #[test]
+    fn writes_tree_from_copy_of_index() {
+        let root = temp_path("tree");
+        let copy = root.join(format!(".git/crusty-hooks/index.{}", std::process::id()));
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join(".git/index"), "index").unwrap();
+        let run_git =
+            |_dir: &str, args: &[&str], envs: &[(&str, &str)], _stdin: Option<&[u8]>| match args {
+                ["rev-parse", "--git-path", path] => Ok(format!(".git/{path}\n").into_bytes()),
+                ["add", "--all"] | ["write-tree"] => {
+                    assert_eq!(envs, [("GIT_INDEX_FILE", &*copy.to_string_lossy())]);
+                    assert_eq!(std::fs::read_to_string(&copy).unwrap(), "index");
+                    Ok(b"1234\n".to_vec())
+                }
+                _ => panic!("{args:?}"),
+            };
+
+        let result = get_working_tree_id(run_git, &root.to_string_lossy());
+
+        assert_eq!(result, Ok(String::from("1234")));
+        assert!(!copy.exists());
+        assert_eq!(
+            std::fs::read_to_string(root.join(".git/index")).unwrap(),
+            "index"
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn returns_error_on_command_error() {
+        let run_git = |_dir: &str,
+                       _args: &[&str],
+                       _envs: &[(&str, &str)],
+                       _stdin: Option<&[u8]>| { Err(String::new()) };
+        assert_eq!(
+            get_working_tree_id(run_git, ""),
+            Err(String::from("Failed to hash the working tree"))
+        );
+    }
+}
+
+mod list_tree_tests {
+    use super::*;
+
+    #[test]
+    fn splits_entries_into_paths_and_objects() {
+        let run_command = |cmd: &str,
+                           _dir: Option<&str>,
+                           _stream_io: bool,
+                           _env: Option<&HashMap<String, String>>| {
+            assert_eq!(cmd, "git ls-tree -r -z 1234");
+            Ok(Some(String::from(
+                "100644 blob abcd\tsrc/a b.rs\x00100755 blob ef01\tbuild.sh\0",
+            )))
+        };
+        let result = list_tree(run_command, "/usr/repos/foo", "1234");
+        assert_eq!(
+            result,
+            Ok(vec![
+                (String::from("src/a b.rs"), String::from("abcd")),
+                (String::from("build.sh"), String::from("ef01")),
+            ])
+        );
+    }
+
+    #[test]
+    fn returns_error_on_command_error() {
+        let run_command = build_simple_command_runner(Err(None));
+        assert_eq!(
+            list_tree(run_command, "", "1234"),
+            Err(String::from("Failed to list the working tree"))
+        );
+    }
+}
+
+mod get_current_branch_tests {
+    use super::*;
+
+    #[test]
+    fn returns_branch_name() {
+        let run_command = build_simple_command_runner(Ok(Some(String::from("release/1.0"))));
+        assert_eq!(
+            get_current_branch(run_command, ""),
+            Some(String::from("release/1.0"))
+        );
+    }
+
+    #[test]
+    fn returns_none_when_detached() {
+        let run_command = build_simple_command_runner(Ok(Some(String::new())));
+        assert_eq!(get_current_branch(run_command, ""), None);
+    }
+
+    #[test]
+    fn returns_none_when_head_is_not_a_branch() {
+        let run_command = build_simple_command_runner(Err(None));
+        assert_eq!(get_current_branch(run_command, ""), None);
+    }
+}
+
+mod get_pushed_files_tests {
+    use super::*;
+
+    #[test]
+    fn lists_files_of_pushed_commits() {
+        let run_command = |cmd: &str,
+                           _dir: Option<&str>,
+                           _stream_io: bool,
+                           _env: Option<&HashMap<String, String>>| {
+            assert_eq!(cmd, "git log -z --name-only --format= 2222..1111");
+            Ok(Some(String::from("src/lib.rs\0Cargo.lock\0")))
+        };
+        let result = get_pushed_files(run_command, "", "1111", "2222");
+        assert_eq!(
+            result,
+            Ok(vec![String::from("src/lib.rs"), String::from("Cargo.lock")])
+        );
+    }
+
+    #[test]
+    fn excludes_commits_on_remotes_for_new_refs() {
+        let run_command = |cmd: &str,
+                           _dir: Option<&str>,
+                           _stream_io: bool,
+                           _env: Option<&HashMap<String, String>>| {
+            assert_eq!(cmd, "git log -z --name-only --format= 1111 --not --remotes");
+            Ok(None)
+        };
+        let null_sha = "0".repeat(40);
+        assert_eq!(
+            get_pushed_files(run_command, "", "1111", &null_sha),
+            Ok(vec![])
+        );
+    }
+}
+
+mod stage_files_tests {
+    use super::*;
+
+    #[test]
+    fn quotes_paths() {
+        let run_command = |cmd: &str,
+                           dir: Option<&str>,
+                           _stream_io: bool,
+                           _env: Option<&HashMap<String, String>>| {
+            assert_eq!(cmd, "git add -- 'src/a b.rs' README.md");
+            assert_eq!(dir, Some("/usr/repos/foo"));
+            Ok(None)
+        };
+        let files = [String::from("src/a b.rs"), String::from("README.md")];
+        assert_eq!(stage_files(run_command, "/usr/repos/foo", &files), Ok(()));
+    }
+
+    #[test]
+    fn returns_error_on_command_error() {
+        let run_command = build_simple_command_runner(Err(None));
+        let files = [String::from("src/lib.rs"), String::from("README.md")];
+        assert_eq!(
+            stage_files(run_command, "", &files),
+            Err(String::from("Failed to stage src/lib.rs, README.md"))
+        );
+    }
+}
+
+mod stash_unstaged_changes_tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    const STASH: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+    /// Runs `git diff --quiet` and `git ls-files` with the given outcomes and
+    /// records every command.
+    fn stash(
+        has_head: bool,
+        has_unstaged: bool,
+        untracked: &str,
+    ) -> (Result<Option<String>, String>, Vec<String>) {
+        let commands = RefCell::new(vec![]);
+        let run_git = |dir: &str, args: &[&str], _envs: &[(&str, &str)], _stdin: Option<&[u8]>| {
+            assert_eq!(dir, "/usr/repos/foo");
+            let cmd = format!("git {}", args.join(" "));
+            commands.borrow_mut().push(cmd.clone());
+            match cmd.as_str() {
+                "git rev-parse --verify --quiet HEAD" if !has_head => Err(String::new()),
+                "git diff --quiet" if has_unstaged => Err(String::new()),
+                "git ls-files --others --exclude-standard" => Ok(untracked.as_bytes().to_vec()),
+                "git rev-parse --verify refs/stash" => Ok(format!("{STASH}\n").into_bytes()),
+                _ => Ok(vec![]),
+            }
+        };
+        let result = stash_unstaged_changes(run_git, "/usr/repos/foo");
+        (result, commands.into_inner())
+    }
+
+    #[test]
+    fn does_nothing_without_changes() {
+        let (result, commands) = stash(true, false, "");
+        assert_eq!(result, Ok(None));
+        assert!(!commands.iter().any(|x| x.starts_with("git stash")));
+    }
+
+    #[test]
+    fn does_nothing_before_first_commit() {
+        let (result, commands) = stash(false, true, "new.rs");
+        assert_eq!(result, Ok(None));
+        assert_eq!(commands, ["git rev-parse --verify --quiet HEAD"]);
+    }
+
+    #[test]
+    fn stashes_unstaged_changes() {
+        let (result, commands) = stash(true, true, "");
+        assert_eq!(result, Ok(Some(String::from(STASH))));
+        insta::assert_debug_snapshot!(commands, @r#"
+        [
+            "git rev-parse --verify --quiet HEAD",
+            "git diff --quiet",
+            "git ls-files --others --exclude-standard",
+            "git stash push --keep-index --include-untracked --quiet --message crusty-hooks: unstaged changes",
+            "git rev-parse --verify refs/stash",
+        ]
+        "#);
+    }
+
+    #[test]
+    fn stashes_untracked_files() {
+        let (result, _) = stash(true, false, "new.rs");
+        assert_eq!(result, Ok(Some(String::from(STASH))));
+    }
+
+    #[test]
+    fn applies_stash_on_top_of_index() {
+        let commands = RefCell::new(vec![]);
+        let run_git = |_dir: &str, args: &[&str], _envs: &[(&str, &str)], stdin: Option<&[u8]>| {
+            let cmd = format!("git {}", args.join(" "));
+            commands.borrow_mut().push(cmd.clone());
+            // Unstaged changes to tracked files but no untracked files.
+            match cmd.as_str() {
+                x if x.starts_with("git diff --quiet") || x.ends_with("^3") => Err(String::new()),
+                x if x.starts_with("git diff --binary") => Ok(b"patch".to_vec()),
+                "git apply" => {
+                    assert_eq!(stdin, Some(&b"patch"[..]));
+                    Ok(vec![])
+                }
+                _ => Ok(STASH.as_bytes().to_vec()),
+            }
+        };
+        let result = restore_unstaged_changes(run_git, "/usr/repos/foo", STASH);
+        assert_eq!(result, Ok(()));
+        insta::assert_debug_snapshot!(commands.into_inner(), @r#"
+        [
+            "git rev-parse --verify refs/stash",
+            "git checkout -- .",
+            "git diff --quiet 4b825dc642cb6eb9a060e54bf8d69288fbee4904^2 4b825dc642cb6eb9a060e54bf8d69288fbee4904",
+            "git diff --binary 4b825dc642cb6eb9a060e54bf8d69288fbee4904^2 4b825dc642cb6eb9a060e54bf8d69288fbee4904",
+            "git apply",
+            "git ls-tree -r --name-only 4b825dc642cb6eb9a060e54bf8d69288fbee4904^3",
+            "git stash drop --quiet",
+        ]
+        "#);
+    }
+
+    #[test]
+    fn names_recovery_command_when_stash_not_on_top() {
+        let run_git = |_dir: &str,
+                       _args: &[&str],
+                       _envs: &[(&str, &str)],
+                       _stdin: Option<&[u8]>| Ok(b"0123abcd".to_vec());
+        let result = restore_unstaged_changes(run_git, "/usr/repos/foo", STASH);
+        insta::assert_debug_snapshot!(result, @r#"
+        Err(
+            "Failed to restore unstaged changes. Run `git stash apply 4b825dc642cb6eb9a060e54bf8d69288fbee4904` to recover them",
+        )
+        "#);
+    }
+
+    #[test]
+    fn names_recovery_command_when_apply_fails() {
+        let run_git =
+            |_dir: &str, args: &[&str], _envs: &[(&str, &str)], _stdin: Option<&[u8]>| match args {
+                ["apply"] => Err(String::from("patch does not apply")),
+                _ => Ok(STASH.as_bytes().to_vec()),
+            };
+        let result = restore_unstaged_changes(run_git, "/usr/repos/foo", STASH);
+        assert_eq!(
+            result,
+            Err(format!(
+                "Failed to restore unstaged changes. Run `git stash apply {STASH}` to recover them"
+            ))
+        );
+    }
+}
+
 mod setup_hooks_tests {
     use super::*;
 
@@ -69,8 +544,19 @@ mod setup_hooks_tests {
     fn errors_when_hooks_directory_unknown() {
         let exp_err = "Failure determining git hooks directory";
         let run_command = build_simple_command_runner(Err(None));
+        let read_file = |_path: &str| panic!("Should not get here");
         let write_file = |_path: &str, _contents: &str, _x: bool| Ok(());
-        let result = setup_hooks(run_command, write_file, "", &[]);
+        let file_exists = |_path: &str| Ok(false);
+        let result = setup_hooks(
+            run_command,
+            read_file,
+            write_file,
+            file_exists,
+            "",
+            None,
+            &[],
+            &[],
+        );
         assert_eq!(result, Err(String::from(exp_err)));
     }
 
@@ -78,8 +564,19 @@ mod setup_hooks_tests {
     fn errors_when_hook_write_fails() {
         let run_command =
             build_simple_command_runner(Ok(Some(String::from("/usr/repos/foo/.git/hooks"))));
+        let read_file = |_path: &str| panic!("Should not get here");
         let write_file = |_path: &str, _contents: &str, _x: bool| Err(String::from(""));
-        let result = setup_hooks(run_command, write_file, "", &[]);
+        let file_exists = |_path: &str| Ok(false);
+        let result = setup_hooks(
+            run_command,
+            read_file,
+            write_file,
+            file_exists,
+            "",
+            None,
+            &[],
+            &[],
+        );
         assert_eq!(result, Err(String::from(hooks::HOOK_CREATION_ERROR)));
     }
 
@@ -88,8 +585,127 @@ mod setup_hooks_tests {
         let root_dir = "/usr/repos/foo";
         let git_hooks = ".git/hooks";
         let run_command = build_simple_command_runner(Ok(Some(String::from(git_hooks))));
+        let read_file = |_path: &str| panic!("Should not get here");
         let write_file = |_p: &str, _c: &str, _x: bool| Ok(());
-        let result = setup_hooks(run_command, write_file, root_dir, &[]);
+        let file_exists = |_path: &str| Ok(false);
+        let result = setup_hooks(
+            run_command,
+            read_file,
+            write_file,
+            file_exists,
+            root_dir,
+            None,
+            &[],
+            &[],
+        );
         assert_eq!(result, Ok(()));
     }
+
+    #[test]
+    fn refuses_hooks_path_owned_by_another_tool() {
+        let run_command = build_simple_command_runner(Ok(Some(String::from(".husky/_"))));
+        let read_file = |_path: &str| Ok(String::from("#!/bin/sh\n. \"$(dirname \"$0\")/h\"\n"));
+        let write_file = |_p: &str, _c: &str, _x: bool| panic!("Should not get here");
+        let file_exists = |path: &str| Ok(path.ends_with("/pre-commit"));
+        let result = setup_hooks(
+            run_command,
+            read_file,
+            write_file,
+            file_exists,
+            "/usr/repos/foo",
+            None,
+            &[],
+            &[],
+        );
+        insta::assert_debug_snapshot!(result, @r#"
+        Err(
+            "core.hooksPath is set to .husky/_, which holds hooks of another tool. Unset it or use `crusty-hooks init --hooks-path <dir>` to replace it",
+        )
+        "#);
+    }
+
+    #[test]
+    fn installs_into_hooks_path_owned_by_crusty_hooks() {
+        let run_command = build_simple_command_runner(Ok(Some(String::from(".githooks"))));
+        let read_file =
+            |_path: &str| Ok(String::from("#!/bin/sh\n# crusty-hooks\n# version 0.1.0\n"));
+        let write_file = |p: &str, _c: &str, _x: bool| {
+            assert!(p.starts_with("/usr/repos/foo/.githooks/"));
+            Ok(())
+        };
+        let file_exists = |path: &str| Ok(path.ends_with("/pre-commit"));
+        let result = setup_hooks(
+            run_command,
+            read_file,
+            write_file,
+            file_exists,
+            "/usr/repos/foo",
+            None,
+            &[],
+            &[],
+        );
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn sets_requested_hooks_path() {
+        let root = temp_path("hooks-path");
+        let root_directory = root.to_string_lossy().into_owned();
+        let commands = std::cell::RefCell::new(vec![]);
+        let run_command = |cmd: &str,
+                           dir: Option<&str>,
+                           _stream_io: bool,
+                           _env: Option<&HashMap<String, String>>| {
+            assert_eq!(dir, Some(root_directory.as_str()));
+            commands.borrow_mut().push(cmd.to_string());
+            Ok(None)
+        };
+        let read_file = |_path: &str| panic!("Should not get here");
+        let write_file = |p: &str, _c: &str, _x: bool| {
+            assert!(p.starts_with(&format!("{root_directory}/team hooks/")));
+            Ok(())
+        };
+        let file_exists = |_path: &str| Ok(false);
+        let result = setup_hooks(
+            run_command,
+            read_file,
+            write_file,
+            file_exists,
+            &root_directory,
+            Some("team hooks"),
+            &[],
+            &[],
+        );
+        assert_eq!(result, Ok(()));
+        assert!(root.join("team hooks").is_dir());
+        insta::assert_debug_snapshot!(commands.into_inner(), @r#"
+        [
+            "git config core.hooksPath 'team hooks'",
+        ]
+        "#);
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn errors_when_hooks_path_cannot_be_set() {
+        let root = std::env::temp_dir();
+        let run_command = build_simple_command_runner(Err(None));
+        let read_file = |_path: &str| panic!("Should not get here");
+        let write_file = |_p: &str, _c: &str, _x: bool| panic!("Should not get here");
+        let file_exists = |_path: &str| panic!("Should not get here");
+        let result = setup_hooks(
+            run_command,
+            read_file,
+            write_file,
+            file_exists,
+            &root.to_string_lossy(),
+            Some("."),
+            &[],
+            &[],
+        );
+        assert_eq!(
+            result,
+            Err(String::from("Failed to set core.hooksPath to ."))
+        );
+    }
 }
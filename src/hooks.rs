@@ -1,6 +1,9 @@
 pub(crate) const HOOK_CREATION_ERROR: &str =
     "Fatal error encountered while trying to create git hook files";
+pub(crate) const HOOK_REMOVAL_ERROR: &str =
+    "Fatal error encountered while trying to remove git hook files";
 pub const NO_CONFIG_FILE_FOUND_ERROR_CODE: u8 = 3;
+pub const TIMEOUT_ERROR_CODE: u8 = 4;
 const MINIMUM_CLI_MAJOR_VERSION: i32 = 0;
 const MINIMUM_CLI_MINOR_VERSION: i32 = 1;
 const MINIMUM_CLI_PATCH_VERSION: i32 = 0;
@@ -11,7 +14,7 @@ const HOOK_FILE_TEMPLATE: &str = include_str!("hooks/files/hook_script.sh");
 const HOOK_CLI_SCRIPT_FILE_TEMPLATE: &str = include_str!("hooks/files/cli.sh");
 const HOOK_SEMVER_SCRIPT_FILE_TEMPLATE: &str = include_str!("hooks/files/semver.sh");
 
-pub const HOOK_NAMES: [&str; 19] = [
+pub const HOOK_NAMES: [&str; 20] = [
     "applypatch-msg",
     "pre-applypatch",
     "post-applypatch",
@@ -31,16 +34,72 @@ pub const HOOK_NAMES: [&str; 19] = [
     "pre-auto-gc",
     "post-rewrite",
     "sendemail-validate",
+    "reference-transaction",
 ];
+/// Hooks that are only installed when requested, as git runs them so often
+/// that spawning crusty-hooks for each run would slow every user down.
+pub const OPT_IN_HOOK_NAMES: [&str; 1] = ["reference-transaction"];
 const CLI_SCRIPT_NAME: &str = "cli.sh";
 const SEMVER_SCRIPT_NAME: &str = "semver.sh";
+/// Suffix of the backups of hooks that existed before crusty-hooks was installed.
+pub const HOOK_BACKUP_SUFFIX: &str = ".crusty-backup";
+/// Second line of every file written by crusty-hooks, including older releases.
+const HOOK_FILE_MARKERS: [&str; 2] = ["# crusty-hooks", "# rusty-hooks"];
 
-fn get_hook_file_contents() -> String {
+/// The files changed while removing the git hooks of crusty-hooks.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct UninstallSummary {
+    /// Files written by crusty-hooks that were deleted.
+    pub removed: Vec<String>,
+    /// Pre-existing hooks that were restored from their backups.
+    pub restored: Vec<String>,
+    /// The `core.hooksPath` that was unset, as it pointed at the removed
+    /// hooks.
+    pub unset_hooks_path: Option<String>,
+}
+
+/// How a file in the hooks directory compares to the one crusty-hooks writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HookFileState {
+    Missing,
+    /// Written by this version of crusty-hooks.
+    Installed,
+    /// Written by crusty-hooks, but by another version or modified since.
+    Outdated,
+    /// Written by another tool or by hand.
+    Foreign,
+}
+
+impl std::fmt::Display for HookFileState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            HookFileState::Missing => "missing",
+            HookFileState::Installed => "installed",
+            HookFileState::Outdated => "outdated",
+            HookFileState::Foreign => "foreign",
+        })
+    }
+}
+
+/// A file crusty-hooks writes into the hooks directory.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct HookFileStatus {
+    pub name: String,
+    pub path: String,
+    pub state: HookFileState,
+    /// Version in the header of a file written by crusty-hooks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+pub(super) fn get_hook_file_contents() -> String {
     String::from(HOOK_FILE_TEMPLATE).replace("{{VERSION}}", VERSION)
 }
 
-fn get_cli_script_file_contents() -> String {
+pub(super) fn get_cli_script_file_contents() -> String {
     let exit_code = &NO_CONFIG_FILE_FOUND_ERROR_CODE.to_string();
+    let timeout_exit_code = &TIMEOUT_ERROR_CODE.to_string();
     let minimum_major = &MINIMUM_CLI_MAJOR_VERSION.to_string();
     let minimum_minor = &MINIMUM_CLI_MINOR_VERSION.to_string();
     let minimum_patch = &MINIMUM_CLI_PATCH_VERSION.to_string();
@@ -49,35 +108,212 @@ fn get_cli_script_file_contents() -> String {
         .replace("{{VERSION}}", VERSION)
         .replace("\n# shellcheck disable=SC2170,SC1083", "")
         .replace("{{NO_CONFIG_FILE_EXIT_CODE}}", exit_code)
+        .replace("{{TIMEOUT_EXIT_CODE}}", timeout_exit_code)
         .replace("{{MINIMUM_MAJOR}}", minimum_major)
         .replace("{{MINIMUM_MINOR}}", minimum_minor)
         .replace("{{MINIMUM_PATCH}}", minimum_patch)
         .replace("{{MINIMUM_ALLOW_PRE}}", minimum_allow_pre)
 }
 
-fn get_semver_script_file_contents() -> String {
+pub(super) fn get_semver_script_file_contents() -> String {
     String::from(HOOK_SEMVER_SCRIPT_FILE_TEMPLATE).replace("{{VERSION}}", VERSION)
 }
 
+/// Joins `file` to the hooks directory, which git reports relative to the
+/// root of the repository unless `core.hooksPath` is absolute.
 fn get_file_path(root_directory_path: &str, hooks_directory: &str, file: &str) -> String {
-    format!("{root_directory_path}/{hooks_directory}/{file}")
+    if std::path::Path::new(hooks_directory).is_absolute() {
+        format!("{hooks_directory}/{file}")
+    } else {
+        format!("{root_directory_path}/{hooks_directory}/{file}")
+    }
+}
+
+/// Whether `contents` were written by crusty-hooks, judged by the marker and
+/// version header following the shebang.
+pub(crate) fn is_crusty_hooks_file(contents: &str) -> bool {
+    let mut lines = contents.lines().skip(1);
+
+    matches!(
+        (lines.next(), lines.next()),
+        (Some(marker), Some(version))
+            if HOOK_FILE_MARKERS.contains(&marker) && version.starts_with("# version ")
+    )
+}
+
+/// Version in the header of a file written by crusty-hooks.
+fn get_file_version(contents: &str) -> Option<String> {
+    let version = contents.lines().nth(2)?.strip_prefix("# version ")?;
+
+    Some(version.trim().to_string())
+}
+
+/// Compares the hooks and scripts in the hooks directory to the ones this
+/// version of crusty-hooks writes.
+pub(super) fn inspect_hook_files<F, G>(
+    read_file: F,
+    file_exists: G,
+    root_directory_path: &str,
+    hooks_directory: &str,
+) -> Result<Vec<HookFileStatus>, String>
+where
+    F: Fn(&str) -> Result<String, ()>,
+    G: Fn(&str) -> Result<bool, ()>,
+{
+    let hook_file_contents = get_hook_file_contents();
+    let files = HOOK_NAMES
+        .iter()
+        .map(|x| (*x, hook_file_contents.clone()))
+        .chain([
+            (CLI_SCRIPT_NAME, get_cli_script_file_contents()),
+            (SEMVER_SCRIPT_NAME, get_semver_script_file_contents()),
+        ]);
+
+    let mut statuses = vec![];
+
+    for (name, expected) in files {
+        let path = get_file_path(root_directory_path, hooks_directory, name);
+        let error = || format!("Failed to read {path}");
+
+        let (state, version) = match file_exists(&path) {
+            Err(_) => return Err(error()),
+            Ok(false) => (HookFileState::Missing, None),
+            Ok(true) => match read_file(&path) {
+                Err(_) => return Err(error()),
+                Ok(contents) if contents == expected => {
+                    (HookFileState::Installed, get_file_version(&contents))
+                }
+                Ok(contents) if is_crusty_hooks_file(&contents) => {
+                    (HookFileState::Outdated, get_file_version(&contents))
+                }
+                Ok(_) => (HookFileState::Foreign, None),
+            },
+        };
+
+        statuses.push(HookFileStatus {
+            name: name.to_string(),
+            path,
+            state,
+            version,
+        });
+    }
+
+    Ok(statuses)
+}
+
+/// Copies a hook that was not written by crusty-hooks to its backup path, so
+/// that it can be chained from the generated hook and restored on uninstall.
+fn back_up_foreign_hook<F, G, H>(
+    read_file: F,
+    write_file: G,
+    file_exists: H,
+    path: &str,
+) -> Result<(), String>
+where
+    F: Fn(&str) -> Result<String, ()>,
+    G: Fn(&str, &str, bool) -> Result<(), String>,
+    H: Fn(&str) -> Result<bool, ()>,
+{
+    match file_exists(path) {
+        Err(_) => return Err(String::from(HOOK_CREATION_ERROR)),
+        Ok(false) => return Ok(()),
+        Ok(true) => {}
+    }
+
+    let Ok(contents) = read_file(path) else {
+        return Err(String::from(HOOK_CREATION_ERROR));
+    };
+
+    if is_crusty_hooks_file(&contents) {
+        return Ok(());
+    }
+
+    let backup_path = format!("{path}{HOOK_BACKUP_SUFFIX}");
+
+    match file_exists(&backup_path) {
+        Err(_) => return Err(String::from(HOOK_CREATION_ERROR)),
+        Ok(true) => {
+            return Err(format!(
+                "{path} was not installed by crusty-hooks and {backup_path} already exists"
+            ));
+        }
+        Ok(false) => {}
+    }
+
+    // A hook disabled with `chmod -x` must not be chained once backed up.
+    if write_file(&backup_path, &contents, is_executable(path)).is_err() {
+        return Err(String::from(HOOK_CREATION_ERROR));
+    }
+
+    Ok(())
+}
+
+/// Whether git runs the hook at `path`, which it does regardless of the
+/// permissions on Windows.
+fn is_executable(path: &str) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        std::fs::metadata(path).is_ok_and(|x| x.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        true
+    }
 }
 
-pub(super) fn create_hook_files<F>(
-    write_file: F,
+/// Whether the hooks directory holds hooks that crusty-hooks did not write.
+pub(super) fn has_foreign_hooks<F, G>(
+    read_file: F,
+    file_exists: G,
+    root_directory_path: &str,
+    hooks_directory: &str,
+) -> Result<bool, String>
+where
+    F: Fn(&str) -> Result<String, ()>,
+    G: Fn(&str) -> Result<bool, ()>,
+{
+    for hook in HOOK_NAMES.iter() {
+        let path = get_file_path(root_directory_path, hooks_directory, hook);
+
+        match file_exists(&path) {
+            Err(_) => return Err(String::from(HOOK_CREATION_ERROR)),
+            Ok(false) => continue,
+            Ok(true) => match read_file(&path) {
+                Err(_) => return Err(String::from(HOOK_CREATION_ERROR)),
+                Ok(contents) if !is_crusty_hooks_file(&contents) => return Ok(true),
+                Ok(_) => {}
+            },
+        }
+    }
+
+    Ok(false)
+}
+
+pub(super) fn create_hook_files<F, G, H>(
+    read_file: F,
+    write_file: G,
+    file_exists: H,
     root_directory_path: &str,
     hooks_directory: &str,
     hook_file_skip_list: &[&str],
+    opt_in_hooks: &[&str],
 ) -> Result<(), String>
 where
-    F: Fn(&str, &str, bool) -> Result<(), String>,
+    F: Fn(&str) -> Result<String, ()>,
+    G: Fn(&str, &str, bool) -> Result<(), String>,
+    H: Fn(&str) -> Result<bool, ()>,
 {
     let hook_file_contents = get_hook_file_contents();
     for hook in HOOK_NAMES
         .iter()
         .filter(|h| !hook_file_skip_list.contains(h))
+        .filter(|h| !OPT_IN_HOOK_NAMES.contains(h) || opt_in_hooks.contains(h))
     {
         let path = get_file_path(root_directory_path, hooks_directory, hook);
+        back_up_foreign_hook(&read_file, &write_file, &file_exists, &path)?;
         if write_file(&path, &hook_file_contents, true).is_err() {
             return Err(String::from(HOOK_CREATION_ERROR));
         };
@@ -98,5 +334,70 @@ where
     Ok(())
 }
 
+pub(super) fn remove_hook_files<F, G, H, I>(
+    read_file: F,
+    write_file: G,
+    file_exists: H,
+    remove_file: I,
+    root_directory_path: &str,
+    hooks_directory: &str,
+) -> Result<UninstallSummary, String>
+where
+    F: Fn(&str) -> Result<String, ()>,
+    G: Fn(&str, &str, bool) -> Result<(), String>,
+    H: Fn(&str) -> Result<bool, ()>,
+    I: Fn(&str) -> Result<(), String>,
+{
+    let mut summary = UninstallSummary::default();
+
+    for file in HOOK_NAMES
+        .iter()
+        .chain([CLI_SCRIPT_NAME, SEMVER_SCRIPT_NAME].iter())
+    {
+        let path = get_file_path(root_directory_path, hooks_directory, file);
+
+        let is_owned = match file_exists(&path) {
+            Err(_) => return Err(String::from(HOOK_REMOVAL_ERROR)),
+            Ok(false) => false,
+            Ok(true) => match read_file(&path) {
+                Err(_) => return Err(String::from(HOOK_REMOVAL_ERROR)),
+                Ok(contents) if is_crusty_hooks_file(&contents) => true,
+                // Leave hooks of other tools, and their backups, alone.
+                Ok(_) => continue,
+            },
+        };
+
+        if is_owned {
+            if remove_file(&path).is_err() {
+                return Err(String::from(HOOK_REMOVAL_ERROR));
+            }
+
+            summary.removed.push(path.clone());
+        }
+
+        let backup_path = format!("{path}{HOOK_BACKUP_SUFFIX}");
+
+        match file_exists(&backup_path) {
+            Err(_) => return Err(String::from(HOOK_REMOVAL_ERROR)),
+            Ok(false) => {}
+            Ok(true) => {
+                let Ok(contents) = read_file(&backup_path) else {
+                    return Err(String::from(HOOK_REMOVAL_ERROR));
+                };
+
+                if write_file(&path, &contents, is_executable(&backup_path)).is_err()
+                    || remove_file(&backup_path).is_err()
+                {
+                    return Err(String::from(HOOK_REMOVAL_ERROR));
+                }
+
+                summary.restored.push(path);
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
 #[cfg(test)]
 mod tests;
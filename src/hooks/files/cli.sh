@@ -12,6 +12,8 @@ minimumPatchCliVersion={{MINIMUM_PATCH}}
 allowPrereleaseCliVersion={{MINIMUM_ALLOW_PRE}}
 # shellcheck disable=SC2170,SC1083
 noConfigFileExitCode={{NO_CONFIG_FILE_EXIT_CODE}}
+# shellcheck disable=SC2170,SC1083
+timeoutExitCode={{TIMEOUT_EXIT_CODE}}
 
 upgradeRustyHooksCli() {
   echo "[crusty-hooks] Upgrading crusty-hook cli..."
@@ -50,11 +52,17 @@ handleRustyHooksCliResult() {
       echo "[crusty-hooks] In order to use crusty-hooks, your project must have a config file"
       echo "[crusty-hooks] See https://github.com/whamcloud/crusty-hooks#configure for more information about configuring crusty-hooks"
       echo
-      echo "[crusty-hooks] If you were trying to remove crusty-hooks, then you should also delete the git hook files to remove this warning"
+      echo "[crusty-hooks] If you were trying to remove crusty-hooks, then run 'crusty-hooks uninstall' to remove the git hook files and this warning"
       echo "[crusty-hooks] See https://github.com/whamcloud/crusty-hooks#removing-crusty-hooks for more information about removing rusty-hook from your project"
       echo
     fi
     exit 0
+  # shellcheck disable=SC2086
+  elif [ ${rustyHooksExitCode} -eq ${timeoutExitCode} ]; then
+    echo "[crusty-hooks] Configured hook command timed out"
+    echo "[crusty-hooks] ${hookName} hook rejected"
+    # shellcheck disable=SC2086
+    exit ${rustyHooksExitCode}
   else
     echo "[crusty-hooks] Configured hook command failed"
     echo "[crusty-hooks] ${hookName} hook rejected"
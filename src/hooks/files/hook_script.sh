@@ -1,9 +1,8 @@
 #!/bin/sh
-# rusty-hooks
+# crusty-hooks
 # version {{VERSION}}
 
 hookName=$(basename "$0")
-gitParams="$*"
 
 # shellcheck source=src/hook_files/cli.sh
 . "$(dirname "$0")"/cli.sh
@@ -21,6 +20,11 @@ else
   ensureMinimumRustyHooksCliVersion || true
 fi
 
-# shellcheck disable=SC2046
-crusty-hooks run --hook "${hookName}"
+legacyHook="$(dirname "$0")/${hookName}.crusty-backup"
+
+if [ -x "${legacyHook}" ]; then
+  crusty-hooks run --hook "${hookName}" --legacy-hook "${legacyHook}" -- "$@"
+else
+  crusty-hooks run --hook "${hookName}" -- "$@"
+fi
 handleRustyHooksCliResult $? "${hookName}"
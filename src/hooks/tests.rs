@@ -1,12 +1,13 @@
 use super::*;
 
 const EXP_NO_CONFIG_FILE_FOUND_ERROR_CODE: i32 = 3;
+const EXP_TIMEOUT_ERROR_CODE: i32 = 4;
 const EXP_VERSION: &str = env!("CARGO_PKG_VERSION");
 const EXP_HOOK_FILE_TEMPLATE: &str = include_str!("files/hook_script.sh");
 const EXP_HOOK_CLI_SCRIPT_FILE_TEMPLATE: &str = include_str!("files/cli.sh");
 const EXP_HOOK_SEMVER_SCRIPT_FILE_TEMPLATE: &str = include_str!("files/semver.sh");
 
-const EXP_HOOK_NAMES: [&str; 19] = [
+const EXP_HOOK_NAMES: [&str; 20] = [
     "applypatch-msg",
     "pre-applypatch",
     "post-applypatch",
@@ -26,6 +27,7 @@ const EXP_HOOK_NAMES: [&str; 19] = [
     "pre-auto-gc",
     "post-rewrite",
     "sendemail-validate",
+    "reference-transaction",
 ];
 const EXP_SKIPPED_HOOK: &str = "commit-msg";
 
@@ -46,6 +48,7 @@ fn get_expected_hook_file_contents() -> String {
 
 fn get_expected_cli_script_file_contents() -> String {
     let exit_code = &EXP_NO_CONFIG_FILE_FOUND_ERROR_CODE.to_string();
+    let timeout_exit_code = &EXP_TIMEOUT_ERROR_CODE.to_string();
     let minimum_major = &EXP_MINIMUM_CLI_MAJOR_VERSION.to_string();
     let minimum_minor = &EXP_MINIMUM_CLI_MINOR_VERSION.to_string();
     let minimum_patch = &EXP_MINIMUM_CLI_PATCH_VERSION.to_string();
@@ -54,6 +57,7 @@ fn get_expected_cli_script_file_contents() -> String {
         .replace("{{VERSION}}", VERSION)
         .replace("\n# shellcheck disable=SC2170,SC1083", "")
         .replace("{{NO_CONFIG_FILE_EXIT_CODE}}", exit_code)
+        .replace("{{TIMEOUT_EXIT_CODE}}", timeout_exit_code)
         .replace("{{MINIMUM_MAJOR}}", minimum_major)
         .replace("{{MINIMUM_MINOR}}", minimum_minor)
         .replace("{{MINIMUM_PATCH}}", minimum_patch)
@@ -76,6 +80,27 @@ mod constants_tests {
     }
 }
 
+#[cfg(test)]
+mod get_file_path_tests {
+    use super::*;
+
+    #[test]
+    fn joins_relative_hooks_directory_to_root() {
+        assert_eq!(
+            get_file_path("/usr/repos/foo", ".git/hooks", "pre-commit"),
+            "/usr/repos/foo/.git/hooks/pre-commit"
+        );
+    }
+
+    #[test]
+    fn keeps_absolute_hooks_directory() {
+        assert_eq!(
+            get_file_path("/usr/repos/foo", "/etc/git/hooks", "pre-commit"),
+            "/etc/git/hooks/pre-commit"
+        );
+    }
+}
+
 #[cfg(test)]
 mod get_hook_file_contents_tests {
     use super::*;
@@ -115,6 +140,8 @@ mod create_hook_files_tests {
 
     #[test]
     fn errors_when_hook_write_fails() {
+        let read_file = |_path: &str| panic!("Should not get here");
+        let file_exists = |_path: &str| Ok(false);
         let write_file = |path: &str, _contents: &str, _make_executable: bool| {
             let file_name = &&path[(path.rfind('/').unwrap() + 1)..];
             match *file_name {
@@ -123,12 +150,14 @@ mod create_hook_files_tests {
                 _ => Err(String::from("")),
             }
         };
-        let result = create_hook_files(write_file, "", "", &[]);
+        let result = create_hook_files(read_file, write_file, file_exists, "", "", &[], &[]);
         assert_eq!(result, Err(String::from(EXP_HOOK_CREATION_ERROR)));
     }
 
     #[test]
     fn errors_when_cli_script_write_fails() {
+        let read_file = |_path: &str| panic!("Should not get here");
+        let file_exists = |_path: &str| Ok(false);
         let write_file = |path: &str, _contents: &str, _make_executable: bool| {
             let file_name = &&path[(path.rfind('/').unwrap() + 1)..];
             match *file_name {
@@ -137,12 +166,14 @@ mod create_hook_files_tests {
                 _ => Ok(()),
             }
         };
-        let result = create_hook_files(write_file, "", "", &[]);
+        let result = create_hook_files(read_file, write_file, file_exists, "", "", &[], &[]);
         assert_eq!(result, Err(String::from(EXP_HOOK_CREATION_ERROR)));
     }
 
     #[test]
     fn errors_when_semver_script_write_fails() {
+        let read_file = |_path: &str| panic!("Should not get here");
+        let file_exists = |_path: &str| Ok(false);
         let write_file = |path: &str, _contents: &str, _make_executable: bool| {
             let file_name = &&path[(path.rfind('/').unwrap() + 1)..];
             match *file_name {
@@ -151,7 +182,7 @@ mod create_hook_files_tests {
                 _ => Ok(()),
             }
         };
-        let result = create_hook_files(write_file, "", "", &[]);
+        let result = create_hook_files(read_file, write_file, file_exists, "", "", &[], &[]);
         assert_eq!(result, Err(String::from(EXP_HOOK_CREATION_ERROR)));
     }
 
@@ -164,6 +195,8 @@ mod create_hook_files_tests {
         let exp_cli_path = &format!("{}/{}/{}", root_dir, git_hooks, EXP_CLI_SCRIPT_NAME);
         let exp_semver_path = &format!("{}/{}/{}", root_dir, git_hooks, EXP_SEMVER_SCRIPT_NAME);
         let exp_semver_contents = get_expected_semver_script_file_contents();
+        let read_file = |_path: &str| panic!("Should not get here");
+        let file_exists = |_path: &str| Ok(false);
         let write_file = |path: &str, contents: &str, make_executable: bool| {
             let file_name = &&path[(path.rfind('/').unwrap() + 1)..];
             match *file_name {
@@ -175,6 +208,7 @@ mod create_hook_files_tests {
                     assert_eq!(exp_semver_path, path);
                     assert_eq!(exp_semver_contents, contents);
                 }
+                "reference-transaction" => panic!("Should not create opt-in hook"),
                 _ => {
                     let exp_hook = EXP_HOOK_NAMES.iter().find(|&n| n == file_name).unwrap();
                     let exp_path = &format!("{}/{}/{}", root_dir, git_hooks, exp_hook);
@@ -185,8 +219,40 @@ mod create_hook_files_tests {
             assert!(make_executable);
             Ok(())
         };
-        let result = create_hook_files(write_file, root_dir, git_hooks, &[]);
+        let result = create_hook_files(
+            read_file,
+            write_file,
+            file_exists,
+            root_dir,
+            git_hooks,
+            &[],
+            &[],
+        );
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn creates_opt_in_hook_on_request() {
+        let written = std::cell::RefCell::new(vec![]);
+        let read_file = |_path: &str| panic!("Should not get here");
+        let file_exists = |_path: &str| Ok(false);
+        let write_file = |path: &str, _contents: &str, _make_executable: bool| {
+            written.borrow_mut().push(path.to_string());
+            Ok(())
+        };
+        let result = create_hook_files(
+            read_file,
+            write_file,
+            file_exists,
+            "/usr/repos/foo",
+            ".git/hooks",
+            &[],
+            &["reference-transaction"],
+        );
         assert_eq!(result, Ok(()));
+        assert!(written.into_inner().contains(&String::from(
+            "/usr/repos/foo/.git/hooks/reference-transaction"
+        )));
     }
 
     #[test]
@@ -198,6 +264,8 @@ mod create_hook_files_tests {
         let exp_cli_path = &format!("{}/{}/{}", root_dir, git_hooks, EXP_CLI_SCRIPT_NAME);
         let exp_semver_path = &format!("{}/{}/{}", root_dir, git_hooks, EXP_SEMVER_SCRIPT_NAME);
         let exp_semver_contents = get_expected_semver_script_file_contents();
+        let read_file = |_path: &str| panic!("Should not get here");
+        let file_exists = |_path: &str| Ok(false);
         let write_file = |path: &str, contents: &str, make_executable: bool| {
             let file_name = &&path[(path.rfind('/').unwrap() + 1)..];
             match *file_name {
@@ -220,7 +288,393 @@ mod create_hook_files_tests {
             assert!(make_executable);
             Ok(())
         };
-        let result = create_hook_files(write_file, root_dir, git_hooks, &[EXP_SKIPPED_HOOK]);
+        let result = create_hook_files(
+            read_file,
+            write_file,
+            file_exists,
+            root_dir,
+            git_hooks,
+            &[EXP_SKIPPED_HOOK],
+            &[],
+        );
         assert_eq!(result, Ok(()));
     }
 }
+
+#[cfg(test)]
+mod is_crusty_hooks_file_tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_installed_files() {
+        assert!(is_crusty_hooks_file(&get_expected_hook_file_contents()));
+        assert!(is_crusty_hooks_file(
+            &get_expected_cli_script_file_contents()
+        ));
+        assert!(is_crusty_hooks_file(
+            &get_expected_semver_script_file_contents()
+        ));
+    }
+
+    #[test]
+    fn recognizes_files_of_older_releases() {
+        assert!(is_crusty_hooks_file(
+            "#!/bin/sh\n# rusty-hooks\n# version 0.1.0\n"
+        ));
+    }
+
+    #[test]
+    fn rejects_foreign_files() {
+        assert!(!is_crusty_hooks_file("#!/bin/sh\ncargo fmt --check\n"));
+        assert!(!is_crusty_hooks_file("#!/bin/sh\n# crusty-hooks\n"));
+        assert!(!is_crusty_hooks_file(""));
+    }
+}
+
+#[cfg(test)]
+mod remove_hook_files_tests {
+    use super::*;
+    use std::{cell::RefCell, collections::HashMap};
+
+    const ROOT_DIR: &str = "/usr/repos/foo";
+    const GIT_HOOKS: &str = ".git/hooks";
+
+    fn path(file: &str) -> String {
+        format!("{ROOT_DIR}/{GIT_HOOKS}/{file}")
+    }
+
+    fn remove(files: &RefCell<HashMap<String, String>>) -> Result<UninstallSummary, String> {
+        remove_hook_files(
+            |p: &str| files.borrow().get(p).cloned().ok_or(()),
+            // Restored hooks keep the mode of their backup, see
+            // `keeps_disabled_foreign_hook_disabled`.
+            |p: &str, contents: &str, _make_executable: bool| {
+                files
+                    .borrow_mut()
+                    .insert(p.to_string(), contents.to_string());
+                Ok(())
+            },
+            |p: &str| Ok(files.borrow().contains_key(p)),
+            |p: &str| {
+                files.borrow_mut().remove(p);
+                Ok(())
+            },
+            ROOT_DIR,
+            GIT_HOOKS,
+        )
+    }
+
+    #[test]
+    fn removes_only_owned_files() {
+        let foreign = String::from("#!/bin/sh\ncargo fmt --check\n");
+        let files = RefCell::new(HashMap::from([
+            (path("pre-commit"), get_expected_hook_file_contents()),
+            (path("pre-push"), foreign.clone()),
+            (
+                path(EXP_CLI_SCRIPT_NAME),
+                get_expected_cli_script_file_contents(),
+            ),
+            (
+                path(EXP_SEMVER_SCRIPT_NAME),
+                get_expected_semver_script_file_contents(),
+            ),
+        ]));
+
+        let summary = remove(&files).unwrap();
+
+        assert_eq!(
+            summary.removed,
+            vec![
+                path("pre-commit"),
+                path(EXP_CLI_SCRIPT_NAME),
+                path(EXP_SEMVER_SCRIPT_NAME)
+            ]
+        );
+        assert!(summary.restored.is_empty());
+        assert_eq!(
+            files.into_inner(),
+            HashMap::from([(path("pre-push"), foreign)])
+        );
+    }
+
+    #[test]
+    fn restores_backed_up_hooks() {
+        let legacy = String::from("#!/bin/sh\nmake lint\n");
+        let files = RefCell::new(HashMap::from([
+            (path("pre-commit"), get_expected_hook_file_contents()),
+            (
+                format!("{}{HOOK_BACKUP_SUFFIX}", path("pre-commit")),
+                legacy.clone(),
+            ),
+        ]));
+
+        let summary = remove(&files).unwrap();
+
+        assert_eq!(summary.removed, vec![path("pre-commit")]);
+        assert_eq!(summary.restored, vec![path("pre-commit")]);
+        assert_eq!(
+            files.into_inner(),
+            HashMap::from([(path("pre-commit"), legacy)])
+        );
+    }
+
+    #[test]
+    fn keeps_backup_when_hook_was_replaced_by_another_tool() {
+        let foreign = String::from("#!/bin/sh\nnpx lint-staged\n");
+        let backup = format!("{}{HOOK_BACKUP_SUFFIX}", path("pre-commit"));
+        let files = RefCell::new(HashMap::from([
+            (path("pre-commit"), foreign.clone()),
+            (backup.clone(), String::from("#!/bin/sh\nmake lint\n")),
+        ]));
+
+        let summary = remove(&files).unwrap();
+
+        assert_eq!(summary, UninstallSummary::default());
+        assert_eq!(files.borrow().get(&path("pre-commit")), Some(&foreign));
+        assert!(files.borrow().contains_key(&backup));
+    }
+
+    #[test]
+    fn errors_when_remove_fails() {
+        let result = remove_hook_files(
+            |_: &str| Ok(get_expected_hook_file_contents()),
+            |_: &str, _: &str, _: bool| Ok(()),
+            |p: &str| Ok(!p.ends_with(HOOK_BACKUP_SUFFIX)),
+            |_: &str| Err(String::from("")),
+            ROOT_DIR,
+            GIT_HOOKS,
+        );
+        assert_eq!(result, Err(String::from(HOOK_REMOVAL_ERROR)));
+    }
+
+    #[test]
+    fn errors_when_read_fails() {
+        let result = remove_hook_files(
+            |_: &str| Err(()),
+            |_: &str, _: &str, _: bool| Ok(()),
+            |_: &str| Ok(true),
+            |_: &str| Ok(()),
+            ROOT_DIR,
+            GIT_HOOKS,
+        );
+        assert_eq!(result, Err(String::from(HOOK_REMOVAL_ERROR)));
+    }
+}
+
+#[cfg(test)]
+mod back_up_foreign_hook_tests {
+    use super::*;
+    use std::{cell::RefCell, collections::HashMap};
+
+    const ROOT_DIR: &str = "/usr/repos/foo";
+    const GIT_HOOKS: &str = ".git/hooks";
+
+    fn path(file: &str) -> String {
+        format!("{ROOT_DIR}/{GIT_HOOKS}/{file}")
+    }
+
+    fn create(files: &RefCell<HashMap<String, String>>) -> Result<(), String> {
+        create_hook_files(
+            |p: &str| files.borrow().get(p).cloned().ok_or(()),
+            |p: &str, contents: &str, _make_executable: bool| {
+                files
+                    .borrow_mut()
+                    .insert(p.to_string(), contents.to_string());
+                Ok(())
+            },
+            |p: &str| Ok(files.borrow().contains_key(p)),
+            ROOT_DIR,
+            GIT_HOOKS,
+            &[],
+            &[],
+        )
+    }
+
+    #[test]
+    fn backs_up_foreign_hook() {
+        let foreign = String::from("#!/bin/sh\nmake lint\n");
+        let files = RefCell::new(HashMap::from([(path("pre-commit"), foreign.clone())]));
+
+        assert_eq!(create(&files), Ok(()));
+
+        let files = files.into_inner();
+        assert_eq!(
+            files.get(&format!("{}{HOOK_BACKUP_SUFFIX}", path("pre-commit"))),
+            Some(&foreign)
+        );
+        assert_eq!(
+            files.get(&path("pre-commit")),
+            Some(&get_expected_hook_file_contents())
+        );
+    }
+
+    #[test]
+    fn overwrites_own_hook_without_backup() {
+        let files = RefCell::new(HashMap::from([(
+            path("pre-commit"),
+            String::from("#!/bin/sh\n# crusty-hooks\n# version 0.0.1\n"),
+        )]));
+
+        assert_eq!(create(&files), Ok(()));
+
+        assert!(
+            !files
+                .borrow()
+                .keys()
+                .any(|p| p.ends_with(HOOK_BACKUP_SUFFIX))
+        );
+    }
+
+    #[test]
+    fn errors_instead_of_overwriting_existing_backup() {
+        let foreign = String::from("#!/bin/sh\nnpx lint-staged\n");
+        let backup = format!("{}{HOOK_BACKUP_SUFFIX}", path("pre-commit"));
+        let files = RefCell::new(HashMap::from([
+            (path("pre-commit"), foreign.clone()),
+            (backup.clone(), String::from("#!/bin/sh\nmake lint\n")),
+        ]));
+
+        insta::assert_debug_snapshot!(create(&files), @r#"
+        Err(
+            "/usr/repos/foo/.git/hooks/pre-commit was not installed by crusty-hooks and /usr/repos/foo/.git/hooks/pre-commit.crusty-backup already exists",
+        )
+        "#);
+        assert_eq!(files.borrow().get(&path("pre-commit")), Some(&foreign));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_disabled_foreign_hook_disabled() {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        let root = crate::tests::utils::temp_path("disabled-hook");
+        let hooks = root.join(GIT_HOOKS);
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&hooks).unwrap();
+        std::fs::write(hooks.join("pre-commit"), "#!/bin/sh\nexit 1\n").unwrap();
+        let root_directory = root.to_string_lossy();
+        let is_executable = |file: &str| {
+            std::fs::metadata(hooks.join(file))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o111
+                != 0
+        };
+
+        let result = create_hook_files(
+            nias::get_file_reader(),
+            nias::get_file_writer(),
+            nias::get_file_existence_checker(),
+            &root_directory,
+            GIT_HOOKS,
+            &[],
+            &[],
+        );
+
+        // The generated hook only chains an executable backup.
+        assert_eq!(result, Ok(()));
+        assert!(is_executable("pre-commit"));
+        assert!(!is_executable(&format!("pre-commit{HOOK_BACKUP_SUFFIX}")));
+
+        let result = remove_hook_files(
+            nias::get_file_reader(),
+            nias::get_file_writer(),
+            nias::get_file_existence_checker(),
+            nias::get_file_remover(),
+            &root_directory,
+            GIT_HOOKS,
+        );
+
+        assert!(result.is_ok(), "{result:?}");
+        assert!(!is_executable("pre-commit"));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod has_foreign_hooks_tests {
+    use super::*;
+
+    #[test]
+    fn detects_foreign_hook() {
+        let read_file = |path: &str| {
+            if path.ends_with("/pre-push") {
+                Ok(String::from("#!/bin/sh\nnpx lint-staged\n"))
+            } else {
+                Ok(get_expected_hook_file_contents())
+            }
+        };
+        let file_exists = |_path: &str| Ok(true);
+        let result = has_foreign_hooks(read_file, file_exists, "/usr/repos/foo", ".husky");
+        assert_eq!(result, Ok(true));
+    }
+
+    #[test]
+    fn ignores_own_and_missing_hooks() {
+        let read_file = |_path: &str| Ok(get_expected_hook_file_contents());
+        let file_exists = |path: &str| Ok(path.ends_with("/pre-commit"));
+        let result = has_foreign_hooks(read_file, file_exists, "/usr/repos/foo", ".githooks");
+        assert_eq!(result, Ok(false));
+    }
+}
+
+#[cfg(test)]
+mod inspect_hook_files_tests {
+    use super::*;
+
+    #[test]
+    fn compares_files_to_current_version() {
+        let read_file = |path: &str| {
+            if path.ends_with("/pre-push") {
+                Ok(String::from("#!/bin/sh\nnpx lint-staged\n"))
+            } else if path.ends_with("/commit-msg") {
+                Ok(String::from("#!/bin/sh\n# rusty-hooks\n# version 0.11.2\n"))
+            } else if path.ends_with(EXP_CLI_SCRIPT_NAME) {
+                Ok(get_expected_cli_script_file_contents())
+            } else {
+                Ok(get_expected_hook_file_contents())
+            }
+        };
+        let file_exists = |path: &str| Ok(!path.ends_with(EXP_SEMVER_SCRIPT_NAME));
+        let result =
+            inspect_hook_files(read_file, file_exists, "/usr/repos/foo", ".git/hooks").unwrap();
+
+        let state = |name: &str| {
+            let file = result.iter().find(|x| x.name == name).unwrap();
+            (file.state, file.version.as_deref())
+        };
+        assert_eq!(result.len(), EXP_HOOK_NAMES.len() + 2);
+        assert_eq!(
+            state("pre-commit"),
+            (HookFileState::Installed, Some(EXP_VERSION))
+        );
+        assert_eq!(state("pre-push"), (HookFileState::Foreign, None));
+        assert_eq!(
+            state("commit-msg"),
+            (HookFileState::Outdated, Some("0.11.2"))
+        );
+        assert_eq!(
+            state(EXP_CLI_SCRIPT_NAME),
+            (HookFileState::Installed, Some(EXP_VERSION))
+        );
+        assert_eq!(
+            state(EXP_SEMVER_SCRIPT_NAME),
+            (HookFileState::Missing, None)
+        );
+        assert_eq!(result[0].path, "/usr/repos/foo/.git/hooks/applypatch-msg");
+    }
+
+    #[test]
+    fn returns_error_when_file_unreadable() {
+        let read_file = |_path: &str| Err(());
+        let file_exists = |_path: &str| Ok(true);
+        let result = inspect_hook_files(read_file, file_exists, "/usr/repos/foo", ".git/hooks");
+        assert_eq!(
+            result,
+            Err(String::from(
+                "Failed to read /usr/repos/foo/.git/hooks/applypatch-msg"
+            ))
+        );
+    }
+}
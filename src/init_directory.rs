@@ -1,13 +1,17 @@
-use crate::{config, git};
-use std::collections::HashMap;
+use crate::{config, error::Error, git};
+use std::{cell::RefCell, collections::HashMap, io};
 
-pub(crate) fn init_directory<F, G, H>(
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn init_directory<F, G, H, I>(
     run_command: F,
-    write_file: G,
-    file_exists: H,
+    read_file: G,
+    write_file: H,
+    file_exists: I,
     target_directory: Option<&str>,
+    hooks_path: Option<&str>,
     hook_file_skip_list: Vec<&str>,
-) -> Result<(), String>
+    opt_in_hooks: Vec<&str>,
+) -> Result<(), Error>
 where
     F: Fn(
         &str,
@@ -15,27 +19,43 @@ where
         bool,
         Option<&HashMap<String, String>>,
     ) -> Result<Option<String>, Option<String>>,
-    G: Fn(&str, &str, bool) -> Result<(), String>,
-    H: Fn(&str) -> Result<bool, ()>,
+    G: Fn(&str) -> Result<String, ()>,
+    H: Fn(&str, &str, bool) -> Result<(), String>,
+    I: Fn(&str) -> Result<bool, ()>,
 {
     let root_directory_path = match git::get_root_directory_path(&run_command, target_directory) {
         Ok(Some(path)) => path,
-        _ => return Err(String::from("Failure determining git repo root directory")),
+        _ => return Err(Error::NotAGitRepo),
     };
-    if git::setup_hooks(
+
+    // The path and message of the last write that failed, if any.
+    let failed_write = RefCell::new(None);
+    let write_file = |path: &str, contents: &str, make_executable: bool| {
+        write_file(path, contents, make_executable)
+            .inspect_err(|e| *failed_write.borrow_mut() = Some((path.to_string(), e.clone())))
+    };
+    let write_error = |message: String| match failed_write.take() {
+        Some((path, e)) => Error::HookWrite {
+            path,
+            source: io::Error::other(e),
+        },
+        None => Error::Other(message),
+    };
+    if let Err(err) = git::setup_hooks(
         &run_command,
-        &write_file,
+        &read_file,
+        write_file,
+        &file_exists,
         &root_directory_path,
+        hooks_path,
         &hook_file_skip_list,
-    )
-    .is_err()
-    {
-        return Err(String::from("Unable to create git hooks"));
+        &opt_in_hooks,
+    ) {
+        return Err(write_error(format!("Unable to create git hooks: {err}")));
     };
 
-    if config::create_default_config_file(&write_file, &file_exists, &root_directory_path).is_err()
-    {
-        return Err(String::from("Unable to create config file"));
+    if config::create_default_config_file(write_file, &file_exists, &root_directory_path).is_err() {
+        return Err(write_error(String::from("Unable to create config file")));
     }
 
     Ok(())
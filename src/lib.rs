@@ -1,21 +1,93 @@
+use condition::ConditionState;
 pub use config::NO_CONFIG_FILE_FOUND;
-use std::collections::HashMap;
-use tracing::instrument;
+use std::{collections::HashMap, num::NonZeroUsize, sync::Arc};
+use task::HookContext;
 
+mod cache;
+mod condition;
 mod config;
+mod error;
 mod git;
 mod init_directory;
+mod output;
+mod plan;
+mod refs;
+mod schedule;
+mod status;
+mod task;
+mod uninstall_directory;
+mod validate;
 
 mod hooks;
-pub use config::ConfigFile;
-pub use hooks::{HOOK_NAMES, NO_CONFIG_FILE_FOUND_ERROR_CODE};
+pub use config::{
+    ConfigFile, HookConfig, LegacyHooks, LoggingConfig, Shell, TaskCondition, TaskConfig,
+};
+pub use error::Error;
+pub use hooks::{
+    HOOK_BACKUP_SUFFIX, HOOK_NAMES, HookFileState, HookFileStatus, NO_CONFIG_FILE_FOUND_ERROR_CODE,
+    OPT_IN_HOOK_NAMES, TIMEOUT_ERROR_CODE, UninstallSummary,
+};
+pub use plan::{CommandPlan, HookPlan, TaskPlan};
+pub use refs::STDIN_HOOK_NAMES;
+pub use status::StatusReport;
+pub use validate::{Validation, validate_config};
+
+/// Token that is replaced by the arguments git passed to the hook.
+pub const GIT_PARAMS_TOKEN: &str = "%rh!";
+/// Token that is replaced by the files of a task, split into as many
+/// invocations of the command as the platform's argument limit requires.
+pub const FILES_TOKEN: &str = "{files}";
+/// Alias of [`FILES_TOKEN`].
+pub const STAGED_FILES_TOKEN: &str = "{staged_files}";
+/// Environment variable holding all of the arguments git passed to the hook.
+pub const GIT_PARAMS_ENV_VAR: &str = "CRUSTY_HOOKS_GIT_PARAMS";
+/// Prefix of the environment variables holding each git argument by position.
+pub const GIT_ARG_ENV_VAR_PREFIX: &str = "CRUSTY_HOOKS_ARG_";
+/// Environment variable listing the tasks or hooks to skip, separated by
+/// commas.
+pub const SKIP_ENV_VAR: &str = "CRUSTY_HOOKS_SKIP";
+/// Environment variable that disables every hook when set to `0`.
+pub const ENABLE_ENV_VAR: &str = "CRUSTY_HOOKS";
+
+/// The files a hook run by hand checks, in place of the staged files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileSelection {
+    /// Every file tracked by git.
+    All,
+    /// These paths, relative to the repository root.
+    Paths(Vec<String>),
+    /// The files changed on `to` since it forked from `from`.
+    Refs { from: String, to: String },
+}
+
+/// Options of a single hook run that do not come from the config file.
+#[derive(Debug, Default, Clone)]
+pub struct RunOptions {
+    /// Path of the hook that crusty-hooks replaced, chained according to the
+    /// `legacy_hooks` setting.
+    pub legacy_hook: Option<String>,
+    /// Run cached tasks even if they passed on the same working tree before.
+    pub no_cache: bool,
+    /// Names of the tasks to skip, or of hooks to skip entirely.
+    pub skip: Vec<String>,
+    /// Files to run the tasks on instead of the staged files. Fixes are left
+    /// in the working tree, and unstaged changes are not stashed.
+    pub files: Option<FileSelection>,
+    /// Names of the only tasks to run, if any.
+    pub tasks: Vec<String>,
+}
 
-pub fn init<F, G, H>(
+/// Installs the git hooks, except those in `hook_file_skip_list`, and the
+/// hooks of [`OPT_IN_HOOK_NAMES`] listed in `opt_in_hooks`.
+pub fn init<F, G, H, I>(
     run_command: F,
-    write_file: G,
-    file_exists: H,
+    read_file: G,
+    write_file: H,
+    file_exists: I,
+    hooks_path: Option<&str>,
     hook_file_skip_list: Vec<&str>,
-) -> Result<(), String>
+    opt_in_hooks: Vec<&str>,
+) -> Result<(), Error>
 where
     F: Fn(
         &str,
@@ -23,24 +95,110 @@ where
         bool,
         Option<&HashMap<String, String>>,
     ) -> Result<Option<String>, Option<String>>,
-    G: Fn(&str, &str, bool) -> Result<(), String>,
-    H: Fn(&str) -> Result<bool, ()>,
+    G: Fn(&str) -> Result<String, ()>,
+    H: Fn(&str, &str, bool) -> Result<(), String>,
+    I: Fn(&str) -> Result<bool, ()>,
 {
     init_directory::init_directory(
         &run_command,
+        &read_file,
         &write_file,
         &file_exists,
         None,
+        hooks_path,
         hook_file_skip_list,
+        opt_in_hooks,
+    )
+}
+
+/// Removes the git hooks written by crusty-hooks, restoring any hooks they
+/// replaced, and optionally the config file.
+pub fn uninstall<F, G, H, I, J>(
+    run_command: F,
+    read_file: G,
+    write_file: H,
+    file_exists: I,
+    remove_file: J,
+    remove_config: bool,
+) -> Result<UninstallSummary, Error>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+    G: Fn(&str) -> Result<String, ()>,
+    H: Fn(&str, &str, bool) -> Result<(), String>,
+    I: Fn(&str) -> Result<bool, ()>,
+    J: Fn(&str) -> Result<(), String>,
+{
+    uninstall_directory::uninstall_directory(
+        &run_command,
+        &read_file,
+        &write_file,
+        &file_exists,
+        &remove_file,
+        None,
+        remove_config,
+    )
+}
+
+/// Reports how crusty-hooks is installed in the current directory, and the
+/// problems keeping the hooks from working.
+pub fn status<F, G, H>(run_command: F, read_file: G, file_exists: H) -> Result<StatusReport, Error>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+    G: Fn(&str) -> Result<String, ()>,
+    H: Fn(&str) -> Result<bool, ()>,
+{
+    status::status_directory(
+        &run_command,
+        &read_file,
+        &file_exists,
+        task::find_program,
+        None,
+        ci_info::is_ci(),
+    )
+}
+
+/// Checks the config file of the current directory, returning its path and
+/// what looks wrong about it, or why it cannot be used.
+pub fn validate<F, G, H>(run_command: F, read_file: G, file_exists: H) -> Result<Validation, Error>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+    G: Fn(&str) -> Result<String, ()>,
+    H: Fn(&str) -> Result<bool, ()>,
+{
+    validate::validate_directory(
+        &run_command,
+        &read_file,
+        &file_exists,
+        task::find_program,
+        None,
     )
 }
 
-pub async fn run<F, G, H>(
+/// Loads `hook_name` from the config file, and queries what its tasks are
+/// checked against, without changing anything.
+fn load_hook<F, G, H, I>(
     run_command: F,
     file_exists: G,
     read_file: H,
+    read_stdin: I,
     hook_name: &str,
-) -> Result<(), Box<dyn std::error::Error>>
+    options: &RunOptions,
+) -> Result<LoadedHook, Error>
 where
     F: Fn(
         &str,
@@ -50,122 +208,537 @@ where
     ) -> Result<Option<String>, Option<String>>,
     G: Fn(&str) -> Result<bool, ()>,
     H: Fn(&str) -> Result<String, ()>,
+    I: Fn() -> Result<String, ()>,
 {
     let root_directory_path = match git::get_root_directory_path(&run_command, None) {
         Ok(Some(path)) => path,
-        _ => {
-            return Err(String::from("Failure determining git repo root directory"))?;
+        _ => return Err(Error::NotAGitRepo),
+    };
+
+    let config_file =
+        match config::get_config_file_contents(read_file, file_exists, &root_directory_path) {
+            Ok((path, contents)) => Some(ConfigFile::try_from_str(&contents).map_err(
+                |source| Error::ConfigParse {
+                    path,
+                    span: source.span(),
+                    source: Box::new(source),
+                },
+            )?),
+            // The replaced hook keeps running until a config file is added.
+            Err(Error::ConfigNotFound) if options.legacy_hook.is_some() => None,
+            Err(e) => return Err(e),
+        };
+
+    let config_file_missing = config_file.is_none();
+
+    let (legacy_hooks, jobs, logging, hook) = match config_file {
+        Some(mut x) => (x.legacy_hooks, x.jobs, x.logging, x.hooks.remove(hook_name)),
+        None => (LegacyHooks::default(), None, LoggingConfig::default(), None),
+    };
+
+    let legacy_hook = match legacy_hooks {
+        LegacyHooks::Skip => None,
+        _ => options
+            .legacy_hook
+            .as_deref()
+            .map(|path| HookConfig::from(vec![vec![legacy_hook_task(hook_name, path)]])),
+    };
+
+    if let Some(name) = options.tasks.iter().find(|name| {
+        ![&hook, &legacy_hook]
+            .into_iter()
+            .flatten()
+            .flat_map(|x| x.groups.iter().flatten())
+            .any(|x| x.name() == name.as_str())
+    }) {
+        return Err(format!("{hook_name} has no task named `{name}`").into());
+    }
+
+    let hook = hook.and_then(|x| select_tasks(x, &options.tasks));
+    let legacy_hook = legacy_hook.and_then(|x| select_tasks(x, &options.tasks));
+
+    let mut loaded = LoadedHook {
+        root_directory_path,
+        legacy_hooks,
+        jobs,
+        logging,
+        hook: None,
+        legacy_hook: None,
+        skipped: false,
+        stdin: None,
+        staged_files: None,
+        unmet_conditions: HashMap::new(),
+        config_file_missing,
+    };
+
+    if hook.is_none() && legacy_hook.is_none() {
+        return Ok(loaded);
+    }
+
+    if options.skip.iter().any(|x| x == hook_name) {
+        loaded.skipped = true;
+
+        return Ok(loaded);
+    }
+
+    let root_directory_path = &loaded.root_directory_path;
+
+    // Run by hand, a hook reads nothing but whitespace, if anything, which
+    // must not be mistaken for an empty list of ref updates. The chosen files
+    // stand in for the ref updates too.
+    let stdin = if refs::reads_stdin(hook_name) && options.files.is_none() {
+        Some(read_stdin().map_err(|_| String::from("Failed to read hook stdin"))?)
+            .filter(|x| !x.trim().is_empty())
+    } else {
+        None
+    };
+
+    // The chosen files stand in for the staged files.
+    let staged_files = match (&hook, &options.files) {
+        (Some(_), Some(files)) => Some(select_files(&run_command, root_directory_path, files)?),
+        (Some(hook), None)
+            if hook
+                .groups
+                .iter()
+                .flatten()
+                .any(|x| task::file_pattern(x).is_some() || task::is_fixer(x)) =>
+        {
+            Some(git::get_staged_files(&run_command, root_directory_path)?)
         }
+        _ => None,
     };
 
-    let config_file_contents =
-        config::get_config_file_contents(read_file, file_exists, &root_directory_path).map_err(
-            |e| {
-                if e == config::NO_CONFIG_FILE_FOUND {
-                    e
-                } else {
-                    String::from("Failed to parse config file")
-                }
-            },
-        )?;
+    // Evaluated up front, before anything is spawned or stashed.
+    let unmet_conditions = match &hook {
+        Some(hook) if hook.groups.iter().flatten().any(|x| x.when.is_some()) => {
+            let state = ConditionState::new(
+                &run_command,
+                root_directory_path,
+                hook_name,
+                hook,
+                stdin.as_deref(),
+                staged_files.as_deref(),
+            )?;
+
+            hook.groups
+                .iter()
+                .flatten()
+                .filter_map(|x| {
+                    let reason = condition::unmet_condition(x.when.as_ref()?, &state, |var| {
+                        std::env::var(var).ok()
+                    })?;
+
+                    Some((x.name().to_string(), reason))
+                })
+                .collect()
+        }
+        _ => HashMap::new(),
+    };
+
+    loaded.hook = hook;
+    loaded.legacy_hook = legacy_hook;
+    loaded.stdin = stdin;
+    loaded.staged_files = staged_files;
+    loaded.unmet_conditions = unmet_conditions;
+
+    Ok(loaded)
+}
 
-    let mut config_file = ConfigFile::try_from_str(&config_file_contents)?;
+pub async fn run<F, G, H, I>(
+    run_command: F,
+    file_exists: G,
+    read_file: H,
+    read_stdin: I,
+    hook_name: &str,
+    git_params: Vec<String>,
+    options: RunOptions,
+) -> Result<(), Error>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+    G: Fn(&str) -> Result<bool, ()>,
+    H: Fn(&str) -> Result<String, ()>,
+    I: Fn() -> Result<String, ()>,
+{
+    let LoadedHook {
+        root_directory_path,
+        legacy_hooks,
+        jobs,
+        logging,
+        hook,
+        legacy_hook,
+        skipped,
+        stdin,
+        staged_files,
+        unmet_conditions,
+        config_file_missing,
+    } = load_hook(
+        &run_command,
+        file_exists,
+        read_file,
+        read_stdin,
+        hook_name,
+        &options,
+    )?;
+
+    if skipped {
+        tracing::warn!("Skipped {hook_name}, as requested by --skip or {SKIP_ENV_VAR}");
+
+        return Ok(());
+    }
 
-    let Some(hooks) = config_file.hooks.remove(hook_name) else {
+    if hook.is_none() && legacy_hook.is_none() {
         return Ok(());
+    }
+
+    // Installed before stashing and kept until the stash is restored, so that
+    // Ctrl-C never leaves the unstaged changes in the stash.
+    let mut interrupt = schedule::Interrupt::listen()?;
+
+    let stash = match &hook {
+        Some(hook) if hook.stash_unstaged && options.files.is_none() => {
+            git::stash_unstaged_changes(git::run_git, &root_directory_path)?
+        }
+        _ => None,
+    };
+
+    // Keyed on the working tree as the tasks see it, after stashing.
+    let cache = match &hook {
+        Some(hook) if hook.groups.iter().flatten().any(cache::is_cached) => {
+            match task_cache(
+                &run_command,
+                git::run_git,
+                &root_directory_path,
+                hook,
+                !options.no_cache,
+            ) {
+                Ok(x) => Some(x),
+                Err(e) => {
+                    tracing::warn!("Running cached tasks anyway: {e}");
+                    None
+                }
+            }
+        }
+        _ => None,
     };
 
-    let mut handle = tokio::task::JoinSet::new();
+    let mut context = HookContext::new(root_directory_path, hook_name, git_params, stdin, logging);
 
-    for xs in hooks {
-        let root_directory_path = root_directory_path.clone();
+    context.staged_files = staged_files;
+    context.cache = cache;
+    context.skip = options.skip;
+    context.unmet_conditions = unmet_conditions;
 
-        handle.spawn(async move {
-            for x in xs {
-                run_task(x, root_directory_path.clone()).await?;
+    if let Some(jobs) = jobs {
+        context.jobs = jobs.get();
+    }
+
+    let context = Arc::new(context);
+
+    // Fixes to files that are only partly staged cannot be staged without
+    // also staging the changes that were left out of the commit.
+    let stage_fixes = options.files.is_none();
+    let unstaged_files = match &hook {
+        Some(hook) if stage_fixes && hook.groups.iter().flatten().any(task::is_fixer) => {
+            git::get_unstaged_files(&run_command, &context.root_directory_path)?
+        }
+        _ => vec![],
+    };
+
+    let stages = order_stages(legacy_hooks, hook, legacy_hook);
+
+    // Failures, timeouts and interruptions all end up here, so the stash
+    // is restored whichever way the hook ends.
+    let result = async {
+        if interrupt.received().await {
+            return Err(Error::Interrupted);
+        }
+
+        for x in stages.into_iter().flatten() {
+            schedule::run_hook(x, Arc::clone(&context), &mut interrupt).await?;
+        }
+
+        Ok(())
+    }
+    .await;
+
+    if let Ok(skipped) = context.skipped.lock()
+        && !skipped.is_empty()
+    {
+        tracing::warn!(
+            "Skipped {}, as requested by --skip or {SKIP_ENV_VAR}",
+            skipped.join(", ")
+        );
+    }
+
+    let result = match (
+        result,
+        restage_fixes(&run_command, &context, &unstaged_files, stage_fixes),
+    ) {
+        (Ok(()), fixed) => fixed.map_err(Into::into),
+        (Err(e), fixed) => {
+            if let Err(x) = fixed {
+                tracing::error!("{x}");
             }
 
-            Ok::<_, std::io::Error>(())
-        });
+            Err(e)
+        }
+    };
+
+    if let Some(stash) = stash
+        && let Err(e) =
+            git::restore_unstaged_changes(git::run_git, &context.root_directory_path, &stash)
+    {
+        // Keep reporting why the hook failed, if it did.
+        if result.is_ok() {
+            return Err(e.into());
+        }
+
+        tracing::error!("{e}");
+    }
+
+    result?;
+
+    // Ctrl-C while the fixes were staged or the stash was restored.
+    if interrupt.received().await {
+        return Err(Error::Interrupted);
     }
 
-    while let Some(x) = handle.join_next().await {
-        x??;
+    if config_file_missing {
+        return Err(Error::ConfigNotFound);
     }
 
     Ok(())
 }
 
-#[instrument(skip(root_directory_path), err)]
-async fn run_task(x: String, root_directory_path: String) -> Result<(), std::io::Error> {
-    tracing::info!("Running {x}");
-
-    let (envs, cmd) = parse_env_and_command(&x);
+/// Resolves how [`run`] would run `hook_name`, without stashing, running or
+/// caching anything.
+pub fn plan<F, G, H, I>(
+    run_command: F,
+    file_exists: G,
+    read_file: H,
+    read_stdin: I,
+    hook_name: &str,
+    git_params: Vec<String>,
+    options: RunOptions,
+) -> Result<HookPlan, Error>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+    G: Fn(&str) -> Result<bool, ()>,
+    H: Fn(&str) -> Result<String, ()>,
+    I: Fn() -> Result<String, ()>,
+{
+    let loaded = load_hook(
+        &run_command,
+        file_exists,
+        read_file,
+        read_stdin,
+        hook_name,
+        &options,
+    )?;
+
+    Ok(plan::plan_hook(hook_name, git_params, loaded, &options)?)
+}
 
-    let mut args = cmd.into_iter();
+/// A hook loaded from the config file, with what its tasks are checked
+/// against.
+struct LoadedHook {
+    root_directory_path: String,
+    legacy_hooks: LegacyHooks,
+    jobs: Option<NonZeroUsize>,
+    logging: LoggingConfig,
+    /// The configured tasks, if any.
+    hook: Option<HookConfig>,
+    /// The hook that crusty-hooks replaced, unless `legacy_hooks` skips it.
+    legacy_hook: Option<HookConfig>,
+    /// Whether the hook is skipped with `--skip` or [`SKIP_ENV_VAR`], in
+    /// which case nothing else was queried.
+    skipped: bool,
+    stdin: Option<String>,
+    staged_files: Option<Vec<String>>,
+    unmet_conditions: HashMap<String, String>,
+    config_file_missing: bool,
+}
 
-    let cmd = args.next().unwrap();
+/// The configured hook and the hook that crusty-hooks replaced, in the
+/// order `legacy_hooks` runs them in.
+fn order_stages(
+    legacy_hooks: LegacyHooks,
+    hook: Option<HookConfig>,
+    legacy_hook: Option<HookConfig>,
+) -> [Option<HookConfig>; 2] {
+    match legacy_hooks {
+        LegacyHooks::After => [hook, legacy_hook],
+        _ => [legacy_hook, hook],
+    }
+}
 
-    let mut cmd = tokio::process::Command::new(cmd);
+/// The task running a hook that crusty-hooks replaced, with the arguments git
+/// passed to the hook.
+fn legacy_hook_task(hook_name: &str, path: &str) -> TaskConfig {
+    let path = shlex::try_quote(path).unwrap_or(path.into());
 
-    cmd.args(args).current_dir(&root_directory_path);
+    TaskConfig {
+        name: Some(format!("{hook_name} (legacy)")),
+        ..TaskConfig::from(format!("{path} {GIT_PARAMS_TOKEN}").as_str())
+    }
+}
 
-    if !envs.is_empty() {
-        cmd.envs(&envs);
+/// Keeps only the tasks of `hook` named in `names`, unless it is empty, and
+/// drops their dependencies on the others. `None` when no task is left.
+fn select_tasks(mut hook: HookConfig, names: &[String]) -> Option<HookConfig> {
+    if names.is_empty() {
+        return Some(hook);
     }
 
-    let output = cmd.output().await?;
+    for group in &mut hook.groups {
+        group.retain(|x| names.iter().any(|name| name == x.name()));
 
-    if !output.status.success() {
-        if !output.stdout.is_empty() {
-            tracing::error!("Stdout: {}", String::from_utf8_lossy(&output.stdout));
+        for task in group.iter_mut() {
+            task.depends_on.retain(|x| names.contains(x));
         }
+    }
 
-        if !output.stderr.is_empty() {
-            tracing::error!("Stderr: {}", String::from_utf8_lossy(&output.stderr));
-        }
+    hook.groups.retain(|x| !x.is_empty());
+
+    (!hook.groups.is_empty()).then_some(hook)
+}
 
-        return Err(std::io::Error::other(format!(
-            "Command `{x}` failed with exit code {:?}",
-            output.status.code()
-        )));
+/// Paths, relative to the repository root at `root`, of the files in
+/// `selection`.
+fn select_files<F>(
+    run_command: F,
+    root: &str,
+    selection: &FileSelection,
+) -> Result<Vec<String>, String>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+{
+    match selection {
+        FileSelection::All => git::get_tracked_files(run_command, root),
+        FileSelection::Paths(paths) => Ok(paths
+            .iter()
+            .map(|x| x.strip_prefix("./").unwrap_or(x).to_string())
+            .collect()),
+        FileSelection::Refs { from, to } => git::get_changed_files(run_command, root, from, to),
     }
+}
 
-    tracing::info!("Finished {x}");
+/// Sets up the cache of the tasks of `hook`, keyed on the current working
+/// tree of the repository at `root`.
+fn task_cache<F, G>(
+    run_command: F,
+    run_git: G,
+    root: &str,
+    hook: &HookConfig,
+    reuse: bool,
+) -> Result<cache::TaskCache, String>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+    G: Fn(&str, &[&str], &[(&str, &str)], Option<&[u8]>) -> Result<Vec<u8>, String>,
+{
+    let directory = git::get_cache_directory(&run_command, root)?;
+    let tree = git::get_working_tree_id(run_git, root)?;
+    let objects = if hook.groups.iter().flatten().any(|x| !x.inputs.is_empty()) {
+        Some(git::list_tree(&run_command, root, &tree)?)
+    } else {
+        None
+    };
 
-    Ok(())
+    Ok(cache::TaskCache {
+        directory,
+        tree,
+        objects,
+        reuse,
+    })
 }
 
-fn parse_env_and_command(input: &str) -> (HashMap<String, String>, Vec<String>) {
-    let mut env_vars = HashMap::new();
-    let mut command_args = Vec::new();
-
-    if let Some(parts) = shlex::split(input) {
-        let mut found_command = false;
-
-        for part in parts {
-            // Check if it's an env var assignment (KEY=VALUE pattern)
-            if !found_command
-                && part.contains('=')
-                && let Some((key, value)) = part.split_once('=')
-            {
-                // Validate that the key looks like a valid env var name
-                if key.chars().all(|c| c.is_alphanumeric() || c == '_')
-                    && !key.is_empty()
-                    && !key.chars().next().unwrap().is_numeric()
-                {
-                    env_vars.insert(key.to_string(), value.to_string());
-                    continue;
-                }
-            }
+/// Adds the staged files that fixers modified back to the index, unless
+/// `stage` is false, and reports them. Rejects the hook if one of the fixers
+/// sets `fail_on_fix`, or if a fix could not be staged.
+fn restage_fixes<F>(
+    run_command: F,
+    context: &HookContext,
+    unstaged_files: &[String],
+    stage: bool,
+) -> Result<(), String>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+{
+    let fixes = match context.fixes.lock() {
+        Ok(mut fixes) => std::mem::take(&mut *fixes),
+        Err(_) => return Err(String::from("Failed to collect the fixed files")),
+    };
+
+    if fixes.is_empty() {
+        return Ok(());
+    }
 
-            // Once we hit a non-env-var pattern, treat rest as command
-            found_command = true;
-            command_args.push(part);
+    let mut files: Vec<String> = fixes.iter().flat_map(|x| x.files.clone()).collect();
+    files.sort();
+    files.dedup();
+
+    let (partial, files): (Vec<String>, Vec<String>) = if stage {
+        files.into_iter().partition(|x| unstaged_files.contains(x))
+    } else {
+        (vec![], vec![])
+    };
+
+    let cmd = ["git", "add", "--"].map(String::from);
+    let limit = task::argument_limit(std::iter::empty());
+
+    for batch in task::batch_files(&cmd, &files, limit) {
+        git::stage_files(&run_command, &context.root_directory_path, batch)?;
+    }
+
+    for fix in &fixes {
+        tracing::warn!("{} fixed {}", fix.name, fix.files.join(", "));
+    }
+
+    if !partial.is_empty() {
+        return Err(format!(
+            "Fixes to {} were not staged, because the files have unstaged changes. \
+             Stage the fixes and commit again, or set `stash_unstaged = true`",
+            partial.join(", ")
+        ));
+    }
+
+    if let Some(fix) = fixes.iter().find(|x| x.fail_on_fix) {
+        if !stage {
+            return Err(format!("{} fixed files. Review the fixes", fix.name));
         }
+
+        return Err(format!(
+            "{} fixed staged files. Review the staged fixes and commit again",
+            fix.name
+        ));
     }
 
-    (env_vars, command_args)
+    Ok(())
 }
 
 #[cfg(test)]
@@ -1,7 +1,8 @@
 use clap::Parser;
-use crusty_hooks::HOOK_NAMES;
+use crusty_hooks::{FileSelection, HOOK_NAMES, OPT_IN_HOOK_NAMES, RunOptions};
 use std::{
     env,
+    io::{ErrorKind, IsTerminal as _, Read as _, Write as _},
     process::{ExitCode, exit},
 };
 use tracing_indicatif::IndicatifLayer;
@@ -15,7 +16,39 @@ enum RustyHookOpts {
     Init {
         #[clap(long)]
         skip_hook_list: Option<String>,
+        /// Also install these hooks, which are left out by default as git
+        /// runs them on every ref update.
+        #[clap(
+            long,
+            value_delimiter = ',',
+            value_parser = clap::builder::PossibleValuesParser::new(OPT_IN_HOOK_NAMES)
+        )]
+        with_hook_list: Vec<String>,
+        /// Install the hooks into this directory, relative to the repository
+        /// root, and point `core.hooksPath` at it.
+        #[clap(long)]
+        hooks_path: Option<String>,
+    },
+    /// Remove crusty-hooks' git hooks from the current directory, restoring
+    /// any hooks they replaced.
+    #[clap(author, version)]
+    Uninstall {
+        /// Also delete the crusty-hooks config file.
+        #[clap(long)]
+        remove_config: bool,
     },
+    /// Report how crusty-hooks is installed in the current directory, and
+    /// exit with an error if anything keeps the hooks from working.
+    #[clap(author, version, alias = "doctor")]
+    Status {
+        /// Print the report as JSON.
+        #[clap(long)]
+        json: bool,
+    },
+    /// Check the config file of the current directory for errors and likely
+    /// mistakes.
+    #[clap(author, version)]
+    Validate,
     /// Print the current version of crusty-hooks.
     #[clap(author, version, alias = "-v")]
     Version,
@@ -23,12 +56,98 @@ enum RustyHookOpts {
     /// Ran automatically by crusty-hooks' git hooks.
     #[clap(author, version)]
     Run {
-        #[clap(long, value_parser = clap::builder::PossibleValuesParser::new(HOOK_NAMES))]
-        hook: String,
+        #[clap(flatten)]
+        args: RunArgs,
+        /// Print the tasks that would run instead of running them.
+        #[clap(long)]
+        dry_run: bool,
+        /// Print the plan of --dry-run as JSON.
+        #[clap(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Print how a git hook would run, resolving every task into the
+    /// commands it spawns, without running anything.
+    #[clap(author, version)]
+    Plan {
+        #[clap(flatten)]
+        args: RunArgs,
+        /// Print the plan as JSON.
+        #[clap(long)]
+        json: bool,
     },
 }
 
-fn init(skip_hook_list: Option<String>) {
+/// The hook to run, and how.
+#[derive(clap::Args)]
+struct RunArgs {
+    #[clap(long, value_parser = clap::builder::PossibleValuesParser::new(HOOK_NAMES))]
+    hook: String,
+    /// Hook that crusty-hooks replaced, chained according to the config file.
+    #[clap(long)]
+    legacy_hook: Option<String>,
+    /// Run cached tasks even if they passed on the same working tree before.
+    #[clap(long)]
+    no_cache: bool,
+    /// Tasks to skip, or hooks to skip entirely, in addition to those
+    /// listed in CRUSTY_HOOKS_SKIP.
+    #[clap(long, value_delimiter = ',')]
+    skip: Vec<String>,
+    /// Run on every tracked file instead of the staged files.
+    #[clap(long, conflicts_with_all = ["files", "from_ref"])]
+    all_files: bool,
+    /// Run on these files, relative to the repository root, instead of
+    /// the staged files.
+    #[clap(long, num_args = 1.., conflicts_with = "from_ref")]
+    files: Vec<String>,
+    /// Run on the files changed on --to-ref since it forked from this ref.
+    #[clap(long, requires = "to_ref")]
+    from_ref: Option<String>,
+    /// Run on the files changed on this ref since it forked from --from-ref.
+    #[clap(long, requires = "from_ref")]
+    to_ref: Option<String>,
+    /// Run only these tasks of the hook.
+    #[clap(long = "task", value_delimiter = ',')]
+    tasks: Vec<String>,
+    /// Arguments git passed to the hook, forwarded to the configured commands.
+    #[clap(last = true)]
+    git_params: Vec<String>,
+}
+
+impl RunArgs {
+    /// The hook name, git arguments and options of the run, with the tasks
+    /// listed in CRUSTY_HOOKS_SKIP added to those to skip.
+    fn into_parts(self) -> (String, Vec<String>, RunOptions) {
+        let mut skip = self.skip;
+
+        if let Ok(x) = std::env::var(crusty_hooks::SKIP_ENV_VAR) {
+            skip.extend(
+                x.split(',')
+                    .map(str::trim)
+                    .filter(|x| !x.is_empty())
+                    .map(String::from),
+            );
+        }
+
+        let files = match (self.from_ref, self.to_ref) {
+            (Some(from), Some(to)) => Some(FileSelection::Refs { from, to }),
+            _ if self.all_files => Some(FileSelection::All),
+            _ if !self.files.is_empty() => Some(FileSelection::Paths(self.files)),
+            _ => None,
+        };
+
+        let options = RunOptions {
+            legacy_hook: self.legacy_hook,
+            no_cache: self.no_cache,
+            skip,
+            files,
+            tasks: self.tasks,
+        };
+
+        (self.hook, self.git_params, options)
+    }
+}
+
+fn init(skip_hook_list: Option<String>, with_hook_list: Vec<String>, hooks_path: Option<String>) {
     if ci_info::is_ci() {
         println!("[crusty-hooks] CI Environment detected. Skipping hook install");
 
@@ -41,9 +160,12 @@ fn init(skip_hook_list: Option<String>) {
 
     if let Err(err) = crusty_hooks::init(
         nias::get_command_runner(),
+        nias::get_file_reader(),
         nias::get_file_writer(),
         nias::get_file_existence_checker(),
+        hooks_path.as_deref(),
         skip_hook_list,
+        with_hook_list.iter().map(String::as_str).collect(),
     ) {
         eprintln!(
             "[crusty-hooks] Fatal error encountered during initialization. Details: {}",
@@ -53,7 +175,164 @@ fn init(skip_hook_list: Option<String>) {
     };
 }
 
-async fn run(hook: String) -> ExitCode {
+fn uninstall(remove_config: bool) {
+    match crusty_hooks::uninstall(
+        nias::get_command_runner(),
+        nias::get_file_reader(),
+        nias::get_file_writer(),
+        nias::get_file_existence_checker(),
+        nias::get_file_remover(),
+        remove_config,
+    ) {
+        Ok(summary) => {
+            for path in summary.removed {
+                println!("[crusty-hooks] Removed {path}");
+            }
+
+            for path in summary.restored {
+                println!("[crusty-hooks] Restored {path}");
+            }
+
+            if let Some(path) = summary.unset_hooks_path {
+                println!("[crusty-hooks] Unset core.hooksPath {path}");
+            }
+        }
+        Err(err) => {
+            eprintln!(
+                "[crusty-hooks] Fatal error encountered during uninstall. Details: {}",
+                err
+            );
+            exit(1);
+        }
+    };
+}
+
+fn status(json: bool) -> ExitCode {
+    let report = match crusty_hooks::status(
+        nias::get_command_runner(),
+        nias::get_file_reader(),
+        nias::get_file_existence_checker(),
+    ) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!(
+                "[crusty-hooks] Fatal error encountered while inspecting the installation. Details: {}",
+                err
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let output = if json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(x) => x,
+            Err(err) => {
+                eprintln!("[crusty-hooks] Failed to serialize the report. Details: {err}");
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        report.to_string()
+    };
+
+    if let Err(err) = print(&output) {
+        eprintln!("[crusty-hooks] Failed to print the report. Details: {err}");
+        return ExitCode::FAILURE;
+    }
+
+    if report.problems.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn validate() -> ExitCode {
+    match crusty_hooks::validate(
+        nias::get_command_runner(),
+        nias::get_file_reader(),
+        nias::get_file_existence_checker(),
+    ) {
+        Ok(validation) => {
+            for warning in &validation.warnings {
+                println!("[crusty-hooks] Warning: {warning}");
+            }
+
+            println!("[crusty-hooks] {} is valid", validation.path);
+
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("[crusty-hooks] {err}");
+
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+fn read_stdin() -> Result<String, ()> {
+    let mut stdin = std::io::stdin();
+
+    // Nothing is piped in when the hook is run by hand.
+    if stdin.is_terminal() {
+        return Ok(String::new());
+    }
+
+    let mut contents = String::new();
+
+    stdin.read_to_string(&mut contents).map_err(|_| ())?;
+
+    Ok(contents)
+}
+
+fn plan(hook: String, git_params: Vec<String>, options: RunOptions, json: bool) -> ExitCode {
+    let plan = match crusty_hooks::plan(
+        nias::get_command_runner(),
+        nias::get_file_existence_checker(),
+        nias::get_file_reader(),
+        read_stdin,
+        &hook,
+        git_params,
+        options,
+    ) {
+        Ok(plan) => plan,
+        Err(err) => {
+            eprintln!("[crusty-hooks] {err}");
+
+            return ExitCode::from(err.exit_code());
+        }
+    };
+
+    let output = if json {
+        match serde_json::to_string_pretty(&plan) {
+            Ok(x) => x,
+            Err(err) => {
+                eprintln!("[crusty-hooks] Failed to serialize the plan. Details: {err}");
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        plan.to_string()
+    };
+
+    if let Err(err) = print(&output) {
+        eprintln!("[crusty-hooks] Failed to print the plan. Details: {err}");
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Prints `output` to stdout, stopping quietly once the reader is gone, as
+/// when piped into `head`.
+fn print(output: &str) -> std::io::Result<()> {
+    match writeln!(std::io::stdout().lock(), "{output}") {
+        Err(err) if err.kind() == ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+async fn run(hook: String, git_params: Vec<String>, options: RunOptions) -> ExitCode {
     let indicatif_layer = IndicatifLayer::new();
 
     tracing_subscriber::registry()
@@ -61,24 +340,32 @@ async fn run(hook: String) -> ExitCode {
         .with(indicatif_layer)
         .init();
 
+    if std::env::var(crusty_hooks::ENABLE_ENV_VAR).is_ok_and(|x| x == "0") {
+        tracing::info!(
+            "Skipping {hook}: {}=0 disables every hook",
+            crusty_hooks::ENABLE_ENV_VAR
+        );
+
+        return ExitCode::SUCCESS;
+    }
+
     if let Err(err) = crusty_hooks::run(
         nias::get_command_runner(),
         nias::get_file_existence_checker(),
         nias::get_file_reader(),
+        read_stdin,
         &hook,
+        git_params,
+        options,
     )
     .await
     {
-        match err {
-            err if err.to_string() == crusty_hooks::NO_CONFIG_FILE_FOUND => {
-                return ExitCode::from(crusty_hooks::NO_CONFIG_FILE_FOUND_ERROR_CODE);
-            }
-            err => {
-                tracing::error!("{err}");
-
-                return ExitCode::FAILURE;
-            }
+        // A repository without a config file is reported by the hook script.
+        if !matches!(err, crusty_hooks::Error::ConfigNotFound) {
+            tracing::error!("{err}");
         }
+
+        return ExitCode::from(err.exit_code());
     }
 
     ExitCode::SUCCESS
@@ -89,9 +376,30 @@ async fn main() -> ExitCode {
     let opts = RustyHookOpts::parse();
 
     match opts {
-        RustyHookOpts::Init { skip_hook_list } => init(skip_hook_list),
+        RustyHookOpts::Init {
+            skip_hook_list,
+            with_hook_list,
+            hooks_path,
+        } => init(skip_hook_list, with_hook_list, hooks_path),
+        RustyHookOpts::Uninstall { remove_config } => uninstall(remove_config),
+        RustyHookOpts::Status { json } => return status(json),
+        RustyHookOpts::Validate => return validate(),
         RustyHookOpts::Version => println!(env!("CARGO_PKG_VERSION")),
-        RustyHookOpts::Run { hook } => return run(hook).await,
+        RustyHookOpts::Run {
+            args,
+            dry_run: true,
+            json,
+        }
+        | RustyHookOpts::Plan { args, json } => {
+            let (hook, git_params, options) = args.into_parts();
+
+            return plan(hook, git_params, options, json);
+        }
+        RustyHookOpts::Run { args, .. } => {
+            let (hook, git_params, options) = args.into_parts();
+
+            return run(hook, git_params, options).await;
+        }
     };
 
     ExitCode::SUCCESS
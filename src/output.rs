@@ -0,0 +1,118 @@
+use std::io::Write as _;
+use tokio::io::{AsyncBufReadExt as _, AsyncRead, BufReader};
+use tracing_indicatif::writer::{get_indicatif_stderr_writer, get_indicatif_stdout_writer};
+
+/// The output stream of a task a line was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stream {
+    Stdout,
+    Stderr,
+}
+
+fn format_prefixed_line(name: &str, line: &str) -> String {
+    format!("[{name}] {line}\n")
+}
+
+/// Writes `line` prefixed with the task name to the matching stream.
+///
+/// Goes through the `tracing_indicatif` writer when one is registered, so the
+/// progress bars of running tasks are not garbled by the output.
+pub(crate) fn write_prefixed_line(stream: Stream, name: &str, line: &str) {
+    let line = format_prefixed_line(name, line);
+
+    #[cfg(test)]
+    if capture::push(stream, &line) {
+        return;
+    }
+
+    // Failing to print task output must not fail the hook.
+    let _ = match stream {
+        Stream::Stdout => match get_indicatif_stdout_writer() {
+            Some(mut writer) => writer.write_all(line.as_bytes()),
+            None => std::io::stdout().write_all(line.as_bytes()),
+        },
+        Stream::Stderr => match get_indicatif_stderr_writer() {
+            Some(mut writer) => writer.write_all(line.as_bytes()),
+            None => std::io::stderr().write_all(line.as_bytes()),
+        },
+    };
+}
+
+/// Writes every line of already captured output prefixed with the task name.
+pub(crate) fn write_prefixed(stream: Stream, name: &str, output: &[u8]) {
+    for line in String::from_utf8_lossy(output).lines() {
+        write_prefixed_line(stream, name, line);
+    }
+}
+
+/// Forwards `reader` line by line as it is produced, prefixed with the task
+/// name, and returns up to the last `keep` bytes it forwarded.
+pub(crate) async fn forward_lines<R>(
+    reader: Option<R>,
+    stream: Stream,
+    name: &str,
+    keep: usize,
+) -> Result<Vec<u8>, std::io::Error>
+where
+    R: AsyncRead + Unpin,
+{
+    let Some(reader) = reader else {
+        return Ok(vec![]);
+    };
+
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+    let mut kept = Vec::new();
+
+    loop {
+        buf.clear();
+
+        if reader.read_until(b'\n', &mut buf).await? == 0 {
+            kept.drain(..kept.len().saturating_sub(keep));
+
+            return Ok(kept);
+        }
+
+        kept.extend_from_slice(&buf);
+
+        // Trimmed in bulk rather than on every line.
+        if kept.len() > 2 * keep {
+            kept.drain(..kept.len() - keep);
+        }
+
+        let line = String::from_utf8_lossy(&buf);
+
+        write_prefixed_line(stream, name, line.trim_end_matches(['\r', '\n']));
+    }
+}
+
+/// Lets tests see the lines written on their thread instead of printing
+/// them.
+#[cfg(test)]
+pub(crate) mod capture {
+    use super::Stream;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static LINES: RefCell<Option<Vec<(Stream, String)>>> = const { RefCell::new(None) };
+    }
+
+    /// Captures the lines written on this thread until [`take`] is called.
+    pub(crate) fn start() {
+        LINES.set(Some(vec![]));
+    }
+
+    /// The lines written since [`start`], in order, ending the capture.
+    pub(crate) fn take() -> Vec<(Stream, String)> {
+        LINES.take().unwrap_or_default()
+    }
+
+    pub(super) fn push(stream: Stream, line: &str) -> bool {
+        LINES
+            .with_borrow_mut(|x| x.as_mut().map(|x| x.push((stream, line.to_string()))))
+            .is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests;
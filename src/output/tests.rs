@@ -0,0 +1,44 @@
+use super::*;
+
+#[cfg(test)]
+mod format_prefixed_line_tests {
+    use super::*;
+
+    #[test]
+    fn prefixes_line_with_task_name() {
+        let result = format_prefixed_line("cargo test", "running 3 tests");
+        assert_eq!(result, "[cargo test] running 3 tests\n");
+    }
+}
+
+#[cfg(test)]
+mod forward_lines_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn succeeds_without_reader() {
+        let result = forward_lines(None::<&[u8]>, Stream::Stdout, "", 0).await;
+        assert_eq!(result.unwrap(), b"");
+    }
+
+    #[tokio::test]
+    async fn forwards_until_eof() {
+        let input: &[u8] = b"first\nsecond without newline";
+        let result = forward_lines(Some(input), Stream::Stderr, "task", 64).await;
+        assert_eq!(result.unwrap(), input);
+    }
+
+    #[tokio::test]
+    async fn keeps_end_of_output() {
+        let input: &[u8] = b"first\nsecond\nthird\n";
+        let result = forward_lines(Some(input), Stream::Stderr, "task", 8).await;
+        assert_eq!(result.unwrap(), b"d\nthird\n");
+    }
+
+    #[tokio::test]
+    async fn tolerates_invalid_utf8() {
+        let input: &[u8] = b"\xff\xfe\n";
+        let result = forward_lines(Some(input), Stream::Stdout, "task", 0).await;
+        assert!(result.is_ok());
+    }
+}
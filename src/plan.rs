@@ -0,0 +1,309 @@
+use crate::{
+    LoadedHook, RunOptions, SKIP_ENV_VAR, TaskConfig, cache, order_stages,
+    task::{self, HookContext},
+};
+use std::{collections::BTreeMap, fmt};
+
+/// How a hook would run, resolved from the config file without running
+/// anything.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct HookPlan {
+    pub hook: String,
+    pub root_directory: String,
+    /// Whether the hook is skipped with `--skip` or `CRUSTY_HOOKS_SKIP`.
+    pub skipped: bool,
+    /// Maximum number of tasks running at the same time.
+    pub jobs: usize,
+    /// Whether the first failure cancels the remaining tasks.
+    pub fail_fast: bool,
+    /// Maximum run time of the configured tasks together, in seconds.
+    pub timeout: Option<u64>,
+    /// Whether unstaged changes are stashed while the tasks run.
+    pub stash_unstaged: bool,
+    /// The hook that crusty-hooks replaced and the configured tasks, in the
+    /// order they run. A stage starts once the previous one succeeded.
+    pub stages: Vec<Vec<TaskPlan>>,
+}
+
+/// How a single task would run. The tasks of a stage are listed in the
+/// order they can start in.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct TaskPlan {
+    pub name: String,
+    /// Names of the tasks it waits for.
+    pub after: Vec<String>,
+    /// Why the task would not run, if it would not.
+    pub skip_reason: Option<String>,
+    /// One command per batch of files, none when the task is skipped.
+    pub commands: Vec<CommandPlan>,
+    /// Variables set by the task and its command, on top of the inherited
+    /// environment and the `CRUSTY_HOOKS_*` variables.
+    pub env: BTreeMap<String, String>,
+    pub cwd: String,
+    pub timeout: Option<u64>,
+    pub parallel_batches: bool,
+    pub exclusive_group: Option<String>,
+    pub allow_failure: bool,
+    /// Whether the files it modifies are staged.
+    pub fix: bool,
+    /// Whether it is skipped when it passed on the same inputs before.
+    pub cache: bool,
+}
+
+/// A program and the arguments it would be spawned with.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CommandPlan {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl fmt::Display for CommandPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let words = std::iter::once(&self.program).chain(&self.args);
+
+        match shlex::try_join(words.clone().map(String::as_str)) {
+            Ok(x) => write!(f, "{x}"),
+            Err(_) => write!(f, "{}", words.cloned().collect::<Vec<_>>().join(" ")),
+        }
+    }
+}
+
+impl fmt::Display for HookPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let yes_no = |x: bool| if x { "yes" } else { "no" };
+
+        writeln!(f, "Hook: {}", self.hook)?;
+        write!(f, "Repository: {}", self.root_directory)?;
+
+        if self.skipped {
+            return write!(f, "\nSkipped, as requested by --skip or {SKIP_ENV_VAR}");
+        }
+
+        if self.stages.is_empty() {
+            return write!(f, "\nNo tasks to run");
+        }
+
+        write!(f, "\nJobs: {}", self.jobs)?;
+        write!(f, "\nFail fast: {}", yes_no(self.fail_fast))?;
+
+        if let Some(secs) = self.timeout {
+            write!(f, "\nTimeout: {secs}s")?;
+        }
+
+        write!(f, "\nStash unstaged: {}", yes_no(self.stash_unstaged))?;
+
+        for (i, stage) in self.stages.iter().enumerate() {
+            match self.stages.len() {
+                1 => write!(f, "\nTasks:")?,
+                _ => write!(f, "\nStage {}:", i + 1)?,
+            }
+
+            for task in stage {
+                write!(f, "\n  {}", task.name)?;
+
+                if !task.after.is_empty() {
+                    write!(f, "\n    after: {}", task.after.join(", "))?;
+                }
+
+                if let Some(reason) = &task.skip_reason {
+                    write!(f, "\n    skipped: {reason}")?;
+                    continue;
+                }
+
+                for command in &task.commands {
+                    write!(f, "\n    run: {command}")?;
+                }
+
+                if !task.env.is_empty() {
+                    let env: Vec<String> =
+                        task.env.iter().map(|(k, v)| format!("{k}={v}")).collect();
+                    write!(f, "\n    env: {}", env.join(" "))?;
+                }
+
+                write!(f, "\n    cwd: {}", task.cwd)?;
+
+                if let Some(secs) = task.timeout {
+                    write!(f, "\n    timeout: {secs}s")?;
+                }
+
+                if let Some(group) = &task.exclusive_group {
+                    write!(f, "\n    exclusive_group: {group}")?;
+                }
+
+                for (key, set) in [
+                    ("parallel_batches", task.parallel_batches),
+                    ("allow_failure", task.allow_failure),
+                    ("fix", task.fix),
+                    ("cache", task.cache),
+                ] {
+                    if set {
+                        write!(f, "\n    {key}: true")?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolves the tasks of the `loaded` hook into the commands they would run
+/// with `git_params`, and the order they would run in.
+pub(crate) fn plan_hook(
+    hook_name: &str,
+    git_params: Vec<String>,
+    loaded: LoadedHook,
+    options: &RunOptions,
+) -> Result<HookPlan, String> {
+    let stash_unstaged =
+        options.files.is_none() && loaded.hook.as_ref().is_some_and(|x| x.stash_unstaged);
+    let fail_fast = loaded.hook.as_ref().is_none_or(|x| x.fail_fast);
+    let timeout = loaded.hook.as_ref().and_then(|x| x.timeout);
+
+    let mut context = HookContext::new(
+        loaded.root_directory_path,
+        hook_name,
+        git_params,
+        loaded.stdin,
+        loaded.logging,
+    );
+
+    context.staged_files = loaded.staged_files;
+    context.skip = options.skip.clone();
+    context.unmet_conditions = loaded.unmet_conditions;
+
+    if let Some(jobs) = loaded.jobs {
+        context.jobs = jobs.get();
+    }
+
+    let mut stages = vec![];
+
+    for hook in order_stages(loaded.legacy_hooks, loaded.hook, loaded.legacy_hook)
+        .into_iter()
+        .flatten()
+    {
+        let dependencies = hook.dependencies()?;
+        let tasks = hook.tasks();
+
+        let mut depths = vec![None; tasks.len()];
+        let mut order: Vec<usize> = (0..tasks.len()).collect();
+        // Tasks deeper in the dependency graph wait longer to start.
+        order.sort_by_key(|&i| depth(i, &dependencies, &mut depths));
+
+        let plans = order
+            .into_iter()
+            .map(|i| {
+                let after = dependencies[i]
+                    .iter()
+                    .map(|&x| tasks[x].name().to_string())
+                    .collect();
+
+                plan_task(&tasks[i], after, &context)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        stages.push(plans);
+    }
+
+    Ok(HookPlan {
+        hook: hook_name.to_string(),
+        root_directory: context.root_directory_path.clone(),
+        skipped: loaded.skipped,
+        jobs: context.jobs,
+        fail_fast,
+        timeout,
+        stash_unstaged,
+        stages,
+    })
+}
+
+/// Number of tasks on the longest chain of dependencies of task `i`.
+fn depth(i: usize, dependencies: &[Vec<usize>], depths: &mut [Option<usize>]) -> usize {
+    if let Some(x) = depths[i] {
+        return x;
+    }
+
+    // The dependencies were checked for cycles.
+    let x = dependencies[i]
+        .iter()
+        .map(|&x| depth(x, dependencies, depths) + 1)
+        .max()
+        .unwrap_or(0);
+
+    depths[i] = Some(x);
+
+    x
+}
+
+/// Resolves `task` the way [`task::run_task`] would run it.
+fn plan_task(
+    task: &TaskConfig,
+    after: Vec<String>,
+    context: &HookContext,
+) -> Result<TaskPlan, String> {
+    let name = task.name();
+
+    let mut skip_reason = if context.skip.iter().any(|x| x == name) {
+        Some(format!("requested by --skip or {SKIP_ENV_VAR}"))
+    } else {
+        context.unmet_conditions.get(name).cloned()
+    };
+
+    let files = match task::file_pattern(task) {
+        Some(pattern) if skip_reason.is_none() => {
+            let files =
+                task::match_files(pattern, context.staged_files.as_deref().unwrap_or_default());
+
+            if files.is_empty() {
+                skip_reason = Some(format!("no staged files match `{pattern}`"));
+            }
+
+            Some(task::anchor_files(
+                task,
+                &context.root_directory_path,
+                files,
+            ))
+        }
+        _ => None,
+    };
+
+    let (envs, invocations) = match skip_reason {
+        Some(_) => Default::default(),
+        None => task::invocations(task, files.as_deref(), context).map_err(|e| e.to_string())?,
+    };
+
+    let commands = invocations
+        .into_iter()
+        .filter_map(|x| {
+            let mut x = x.into_iter();
+
+            Some(CommandPlan {
+                program: x.next()?,
+                args: x.collect(),
+            })
+        })
+        .collect();
+
+    let mut env = task.env.clone();
+    env.extend(envs);
+
+    Ok(TaskPlan {
+        name: name.to_string(),
+        after,
+        skip_reason,
+        commands,
+        env,
+        cwd: task::resolve_cwd(&context.root_directory_path, task.cwd.as_deref())
+            .display()
+            .to_string(),
+        timeout: task.timeout,
+        parallel_batches: task.parallel_batches,
+        exclusive_group: task.exclusive_group.clone(),
+        allow_failure: task.allow_failure,
+        fix: task::is_fixer(task),
+        cache: cache::is_cached(task),
+    })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,167 @@
+use super::*;
+use crate::{FileSelection, tests::utils::build_simple_command_runner};
+
+/// Plans `hook_name` of a repository at `/repo` with the config `contents`,
+/// run on the files `a.rs` and `README.md` with the git arguments `x y`.
+fn plan(contents: &str, hook_name: &str, options: RunOptions) -> Result<HookPlan, String> {
+    let run_command = build_simple_command_runner(Ok(Some(String::from("/repo"))));
+    let contents = format!("jobs = 2\n{contents}");
+    let read_file = |_path: &str| Ok(contents.clone());
+    let read_stdin = || Ok(String::new());
+    let file_exists = |path: &str| Ok(path.ends_with("/.crusty-hooks.toml"));
+    let options = RunOptions {
+        files: Some(FileSelection::Paths(vec![
+            String::from("a.rs"),
+            String::from("README.md"),
+        ])),
+        ..options
+    };
+
+    crate::plan(
+        run_command,
+        file_exists,
+        read_file,
+        read_stdin,
+        hook_name,
+        vec![String::from("x"), String::from("y")],
+        options,
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod plan_tests {
+    use super::*;
+
+    #[test]
+    fn resolves_tasks_in_order() {
+        let contents = r#"[hooks.pre-commit]
+            timeout = 120
+            task_timeout = 60
+            tasks = [
+                { name = "fmt", run = "cargo fmt -- {files}", files = "*.rs", fix = true },
+                { name = "clippy", run = "RUST_LOG=debug cargo clippy %rh!", depends_on = ["fmt"], cwd = "crates", env = { A = "1" } },
+                { name = "test", run = "cargo test > 'test log'", depends_on = ["clippy"], shell = "sh", cache = true },
+                { name = "docs", run = "mdbook build", when = { os = "plan9" } },
+                { name = "toml", run = "taplo fmt", files = "*.toml" },
+            ]
+        "#;
+
+        let result = plan(contents, "pre-commit", RunOptions::default()).unwrap();
+
+        insta::assert_snapshot!(result.to_string().replace("\\", "/"), @r#"
+        Hook: pre-commit
+        Repository: /repo
+        Jobs: 2
+        Fail fast: yes
+        Timeout: 120s
+        Stash unstaged: no
+        Tasks:
+          fmt
+            run: cargo fmt -- a.rs
+            cwd: /repo
+            timeout: 60s
+            fix: true
+          docs
+            skipped: only runs on plan9
+          toml
+            skipped: no staged files match `*.toml`
+          clippy
+            after: fmt
+            run: cargo clippy x y
+            env: A=1 RUST_LOG=debug
+            cwd: /repo/crates
+            timeout: 60s
+          test
+            after: clippy
+            run: sh -c "cargo test > 'test log'"
+            cwd: /repo
+            timeout: 60s
+            cache: true
+        "#);
+    }
+
+    #[test]
+    fn orders_replaced_hook_by_legacy_hooks() {
+        let contents = r#"legacy_hooks = "after"
+            [hooks]
+            pre-commit = "cargo test"
+        "#;
+        let options = RunOptions {
+            legacy_hook: Some(String::from("/repo/.git/hooks/pre-commit.crusty-backup")),
+            ..RunOptions::default()
+        };
+
+        let result = plan(contents, "pre-commit", options).unwrap();
+
+        let names: Vec<Vec<&str>> = result
+            .stages
+            .iter()
+            .map(|x| x.iter().map(|x| x.name.as_str()).collect())
+            .collect();
+        assert_eq!(names, [["cargo test"], ["pre-commit (legacy)"]]);
+        assert_eq!(
+            result.stages[1][0].commands,
+            [CommandPlan {
+                program: String::from("/repo/.git/hooks/pre-commit.crusty-backup"),
+                args: vec![String::from("x"), String::from("y")],
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_skipped_tasks_and_hooks() {
+        let contents = r#"[hooks.pre-commit]
+            stash_unstaged = true
+            tasks = ["cargo clippy", "cargo test"]
+        "#;
+        let options = |skip: &str| RunOptions {
+            skip: vec![String::from(skip)],
+            ..RunOptions::default()
+        };
+
+        let result = plan(contents, "pre-commit", options("cargo test")).unwrap();
+        assert_eq!(result.stages[0][0].skip_reason, None);
+        assert_eq!(
+            result.stages[0][1].skip_reason.as_deref(),
+            Some("requested by --skip or CRUSTY_HOOKS_SKIP")
+        );
+        assert!(result.stages[0][1].commands.is_empty());
+        // Run on chosen files, nothing is stashed.
+        assert!(!result.stash_unstaged);
+
+        let result = plan(contents, "pre-commit", options("pre-commit")).unwrap();
+        assert!(result.skipped && result.stages.is_empty());
+    }
+
+    #[test]
+    fn returns_error_on_command_without_program() {
+        let result = plan(
+            "[hooks]\npre-commit = \"RUST_LOG=debug\"",
+            "pre-commit",
+            RunOptions::default(),
+        );
+
+        assert_eq!(
+            result,
+            Err(String::from(
+                "Command `RUST_LOG=debug` does not contain a program to run"
+            ))
+        );
+    }
+
+    #[test]
+    fn has_no_stages_without_hook() {
+        let result = plan(
+            "[hooks]\npre-commit = \"true\"",
+            "pre-push",
+            RunOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            result.to_string(),
+            "Hook: pre-push\nRepository: /repo\nNo tasks to run"
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+/// Hooks that git feeds ref update lines on stdin.
+pub const STDIN_HOOK_NAMES: [&str; 5] = [
+    "pre-push",
+    "pre-receive",
+    "post-receive",
+    "post-rewrite",
+    "reference-transaction",
+];
+
+const REF_COUNT_ENV_VAR: &str = "CRUSTY_HOOKS_REF_COUNT";
+const REF_ENV_VAR_PREFIX: &str = "CRUSTY_HOOKS_REF_";
+
+/// A single ref update line git passed to a hook on stdin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum RefUpdate {
+    /// `<local ref> <local sha> <remote ref> <remote sha>` as given to `pre-push`.
+    Push {
+        local_ref: String,
+        local_sha: String,
+        remote_ref: String,
+        remote_sha: String,
+    },
+    /// `<old sha> <new sha> <ref name>` as given to `pre-receive`, `post-receive`
+    /// and `reference-transaction`.
+    Receive {
+        old_sha: String,
+        new_sha: String,
+        ref_name: String,
+    },
+    /// `<old sha> <new sha> [<extra info>]` as given to `post-rewrite`.
+    Rewrite {
+        old_sha: String,
+        new_sha: String,
+        extra_info: Option<String>,
+    },
+}
+
+impl RefUpdate {
+    fn env_fields(&self) -> Vec<(&'static str, &str)> {
+        match self {
+            RefUpdate::Push {
+                local_ref,
+                local_sha,
+                remote_ref,
+                remote_sha,
+            } => vec![
+                ("LOCAL_REF", local_ref),
+                ("LOCAL_SHA", local_sha),
+                ("REMOTE_REF", remote_ref),
+                ("REMOTE_SHA", remote_sha),
+            ],
+            RefUpdate::Receive {
+                old_sha,
+                new_sha,
+                ref_name,
+            } => vec![
+                ("OLD_SHA", old_sha),
+                ("NEW_SHA", new_sha),
+                ("NAME", ref_name),
+            ],
+            RefUpdate::Rewrite {
+                old_sha,
+                new_sha,
+                extra_info,
+            } => {
+                let mut xs = vec![("OLD_SHA", old_sha.as_str()), ("NEW_SHA", new_sha.as_str())];
+
+                if let Some(extra_info) = extra_info {
+                    xs.push(("EXTRA_INFO", extra_info));
+                }
+
+                xs
+            }
+        }
+    }
+}
+
+pub(crate) fn reads_stdin(hook_name: &str) -> bool {
+    STDIN_HOOK_NAMES.contains(&hook_name)
+}
+
+/// Parses the stdin contents of `hook_name` into ref updates.
+///
+/// Lines that do not match the format git documents for the hook are skipped.
+pub(crate) fn parse_ref_updates(hook_name: &str, stdin: &str) -> Vec<RefUpdate> {
+    stdin
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            let update = parse_ref_update(hook_name, line);
+
+            if update.is_none() {
+                tracing::warn!("Ignoring unexpected {hook_name} stdin line: {line}");
+            }
+
+            update
+        })
+        .collect()
+}
+
+fn parse_ref_update(hook_name: &str, line: &str) -> Option<RefUpdate> {
+    match hook_name {
+        "pre-push" => {
+            let mut parts = line.split_whitespace();
+            let update = RefUpdate::Push {
+                local_ref: parts.next()?.to_string(),
+                local_sha: parts.next()?.to_string(),
+                remote_ref: parts.next()?.to_string(),
+                remote_sha: parts.next()?.to_string(),
+            };
+
+            parts.next().is_none().then_some(update)
+        }
+        "pre-receive" | "post-receive" | "reference-transaction" => {
+            let mut parts = line.split_whitespace();
+            let update = RefUpdate::Receive {
+                old_sha: parts.next()?.to_string(),
+                new_sha: parts.next()?.to_string(),
+                ref_name: parts.next()?.to_string(),
+            };
+
+            parts.next().is_none().then_some(update)
+        }
+        "post-rewrite" => {
+            let mut parts = line.splitn(3, ' ');
+            let old_sha = parts.next()?.to_string();
+            let new_sha = parts.next()?.to_string();
+
+            if old_sha.is_empty() || new_sha.is_empty() {
+                return None;
+            }
+
+            Some(RefUpdate::Rewrite {
+                old_sha,
+                new_sha,
+                extra_info: parts.next().map(|x| x.to_string()),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Builds the `CRUSTY_HOOKS_REF_*` environment variables describing `updates`.
+///
+/// Each update is numbered starting at 1, e.g. `CRUSTY_HOOKS_REF_1_LOCAL_SHA`.
+pub(crate) fn ref_updates_env(updates: &[RefUpdate]) -> HashMap<String, String> {
+    let mut env = HashMap::new();
+
+    env.insert(REF_COUNT_ENV_VAR.to_string(), updates.len().to_string());
+
+    for (i, update) in updates.iter().enumerate() {
+        for (field, value) in update.env_fields() {
+            env.insert(
+                format!("{REF_ENV_VAR_PREFIX}{}_{field}", i + 1),
+                value.to_string(),
+            );
+        }
+    }
+
+    env
+}
+
+#[cfg(test)]
+mod tests;
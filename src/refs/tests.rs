@@ -0,0 +1,129 @@
+use super::*;
+
+const OLD_SHA: &str = "1111111111111111111111111111111111111111";
+const NEW_SHA: &str = "2222222222222222222222222222222222222222";
+
+#[cfg(test)]
+mod reads_stdin_tests {
+    use super::*;
+
+    #[test]
+    fn reads_stdin_for_ref_update_hooks() {
+        for hook in STDIN_HOOK_NAMES {
+            assert!(reads_stdin(hook));
+        }
+    }
+
+    #[test]
+    fn does_not_read_stdin_for_other_hooks() {
+        assert!(!reads_stdin("pre-commit"));
+        assert!(!reads_stdin("commit-msg"));
+    }
+}
+
+#[cfg(test)]
+mod parse_ref_updates_tests {
+    use super::*;
+
+    #[test]
+    fn parses_pre_push_lines() {
+        let stdin = format!(
+            "refs/heads/main {NEW_SHA} refs/heads/main {OLD_SHA}\nrefs/heads/dev {NEW_SHA} refs/heads/dev {OLD_SHA}\n"
+        );
+        let result = parse_ref_updates("pre-push", &stdin);
+        assert_eq!(result.len(), 2);
+        assert_eq!(
+            result[1],
+            RefUpdate::Push {
+                local_ref: String::from("refs/heads/dev"),
+                local_sha: String::from(NEW_SHA),
+                remote_ref: String::from("refs/heads/dev"),
+                remote_sha: String::from(OLD_SHA),
+            }
+        );
+    }
+
+    #[test]
+    fn parses_receive_lines() {
+        for hook in ["pre-receive", "post-receive", "reference-transaction"] {
+            let stdin = format!("{OLD_SHA} {NEW_SHA} refs/heads/main\n");
+            let result = parse_ref_updates(hook, &stdin);
+            assert_eq!(
+                result,
+                vec![RefUpdate::Receive {
+                    old_sha: String::from(OLD_SHA),
+                    new_sha: String::from(NEW_SHA),
+                    ref_name: String::from("refs/heads/main"),
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn parses_rewrite_lines_with_and_without_extra_info() {
+        let stdin = format!("{OLD_SHA} {NEW_SHA}\n{NEW_SHA} {OLD_SHA} some extra info\n");
+        let result = parse_ref_updates("post-rewrite", &stdin);
+        assert_eq!(
+            result,
+            vec![
+                RefUpdate::Rewrite {
+                    old_sha: String::from(OLD_SHA),
+                    new_sha: String::from(NEW_SHA),
+                    extra_info: None,
+                },
+                RefUpdate::Rewrite {
+                    old_sha: String::from(NEW_SHA),
+                    new_sha: String::from(OLD_SHA),
+                    extra_info: Some(String::from("some extra info")),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_malformed_and_blank_lines() {
+        let stdin = format!("garbage\n\nrefs/heads/main {NEW_SHA} refs/heads/main {OLD_SHA}\n");
+        let result = parse_ref_updates("pre-push", &stdin);
+        assert_eq!(result.len(), 1);
+    }
+
+    #[test]
+    fn returns_nothing_for_hooks_without_stdin() {
+        let stdin = format!("{OLD_SHA} {NEW_SHA} refs/heads/main\n");
+        assert!(parse_ref_updates("pre-commit", &stdin).is_empty());
+    }
+}
+
+#[cfg(test)]
+mod ref_updates_env_tests {
+    use super::*;
+
+    #[test]
+    fn numbers_each_update() {
+        let updates = vec![
+            RefUpdate::Receive {
+                old_sha: String::from(OLD_SHA),
+                new_sha: String::from(NEW_SHA),
+                ref_name: String::from("refs/heads/main"),
+            },
+            RefUpdate::Rewrite {
+                old_sha: String::from(NEW_SHA),
+                new_sha: String::from(OLD_SHA),
+                extra_info: None,
+            },
+        ];
+        let env = ref_updates_env(&updates);
+        assert_eq!(env["CRUSTY_HOOKS_REF_COUNT"], "2");
+        assert_eq!(env["CRUSTY_HOOKS_REF_1_OLD_SHA"], OLD_SHA);
+        assert_eq!(env["CRUSTY_HOOKS_REF_1_NAME"], "refs/heads/main");
+        assert_eq!(env["CRUSTY_HOOKS_REF_2_NEW_SHA"], OLD_SHA);
+        assert!(!env.contains_key("CRUSTY_HOOKS_REF_2_EXTRA_INFO"));
+    }
+
+    #[test]
+    fn reports_zero_updates() {
+        let env = ref_updates_env(&[]);
+        assert_eq!(env.len(), 1);
+        assert_eq!(env["CRUSTY_HOOKS_REF_COUNT"], "0");
+    }
+}
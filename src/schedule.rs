@@ -0,0 +1,213 @@
+use crate::{
+    Error, HookConfig, TaskConfig,
+    task::{self, HookContext, TaskFailure},
+};
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use tokio::task::JoinSet;
+
+/// Runs the tasks of `hook`, each one as soon as the tasks it waits for
+/// succeeded, see [`HookConfig::dependencies`]. The tasks depending on a
+/// failed task are skipped.
+///
+/// At most `jobs` tasks, and one task of each exclusive group, run at the
+/// same time. The running tasks are cancelled once the hook takes longer
+/// than its `timeout`.
+pub(crate) async fn run_hook(
+    hook: HookConfig,
+    context: Arc<HookContext>,
+    interrupt: &mut Interrupt,
+) -> Result<(), Error> {
+    let dependencies = hook.dependencies()?;
+
+    let tasks = hook.tasks();
+
+    let mut dependents = vec![vec![]; tasks.len()];
+
+    for (i, xs) in dependencies.iter().enumerate() {
+        for &x in xs {
+            dependents[x].push(i);
+        }
+    }
+
+    // Number of unfinished dependencies of each task.
+    let mut waiting: Vec<usize> = dependencies.iter().map(Vec::len).collect();
+    // Tasks whose dependencies succeeded, in the order they are written in.
+    let mut ready: Vec<usize> = (0..tasks.len()).filter(|&i| waiting[i] == 0).collect();
+    let mut busy_groups = HashSet::new();
+
+    let mut handle = JoinSet::new();
+
+    start_ready(&mut ready, &mut busy_groups, &mut handle, &tasks, &context);
+
+    let run = async {
+        let mut failures = vec![];
+        let mut skipped = vec![false; tasks.len()];
+
+        while let Some(x) = handle.join_next().await {
+            let (i, result) = x.map_err(|e| Error::Other(e.to_string()))?;
+
+            if let Some(group) = &tasks[i].exclusive_group {
+                busy_groups.remove(group.as_str());
+            }
+
+            match result {
+                Ok(()) => {
+                    for &x in &dependents[i] {
+                        waiting[x] -= 1;
+
+                        if waiting[x] == 0 {
+                            ready.push(x);
+                        }
+                    }
+
+                    ready.sort_unstable();
+                }
+                Err(error) => {
+                    failures.push(TaskFailure {
+                        name: tasks[i].name().to_string(),
+                        error,
+                    });
+
+                    if hook.fail_fast {
+                        break;
+                    }
+
+                    // Dependents of a failed task never become ready.
+                    if !context.logging.quiet {
+                        skip_dependents(i, i, &tasks, &dependents, &mut skipped);
+                    }
+                }
+            }
+
+            start_ready(&mut ready, &mut busy_groups, &mut handle, &tasks, &context);
+        }
+
+        task::summarize_failures(failures)
+    };
+
+    let run = async {
+        match hook.timeout {
+            Some(secs) => tokio::time::timeout(Duration::from_secs(secs), run)
+                .await
+                .map_err(|_| Error::Timeout {
+                    name: context.hook_name.clone(),
+                    secs,
+                })?,
+            None => run.await,
+        }
+    };
+
+    let result = tokio::select! {
+        result = run => result,
+        () = interrupt.wait() => Err(Error::Interrupted),
+    };
+
+    // Cancel whatever is still running, which kills the process groups of
+    // the remaining tasks.
+    handle.shutdown().await;
+
+    result
+}
+
+/// Catches Ctrl-C from the moment it is created, instead of letting it kill
+/// the process, so that the hook can put the stashed changes back.
+pub(crate) struct Interrupt {
+    #[cfg(unix)]
+    signal: tokio::signal::unix::Signal,
+    #[cfg(windows)]
+    signal: tokio::signal::windows::CtrlC,
+    received: bool,
+}
+
+impl Interrupt {
+    pub(crate) fn listen() -> Result<Self, Error> {
+        #[cfg(unix)]
+        let signal = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt());
+        #[cfg(windows)]
+        let signal = tokio::signal::windows::ctrl_c();
+
+        match signal {
+            Ok(signal) => Ok(Interrupt {
+                signal,
+                received: false,
+            }),
+            Err(e) => Err(Error::Other(format!("Failed to listen for Ctrl-C: {e}"))),
+        }
+    }
+
+    /// Waits until Ctrl-C is pressed, returning at once if it already was.
+    pub(crate) async fn wait(&mut self) {
+        if !self.received {
+            // The stream only ends once the runtime shuts down.
+            if self.signal.recv().await.is_none() {
+                std::future::pending::<()>().await;
+            }
+
+            self.received = true;
+        }
+    }
+
+    /// Whether Ctrl-C was pressed since the interrupt was created.
+    pub(crate) async fn received(&mut self) -> bool {
+        tokio::select! {
+            biased;
+            () = self.wait() => true,
+            () = std::future::ready(()) => false,
+        }
+    }
+}
+
+/// Starts the ready tasks in order, as long as fewer than `jobs` tasks run,
+/// holding back the tasks whose exclusive group is busy.
+fn start_ready<'a>(
+    ready: &mut Vec<usize>,
+    busy_groups: &mut HashSet<&'a str>,
+    handle: &mut JoinSet<(usize, Result<(), Error>)>,
+    tasks: &'a [TaskConfig],
+    context: &Arc<HookContext>,
+) {
+    ready.retain(|&i| {
+        if handle.len() >= context.jobs {
+            return true;
+        }
+
+        if let Some(group) = &tasks[i].exclusive_group
+            && !busy_groups.insert(group.as_str())
+        {
+            return true;
+        }
+
+        let (task, context) = (tasks[i].clone(), Arc::clone(context));
+
+        handle.spawn(async move { (i, task::run_task(task, context).await) });
+
+        false
+    });
+}
+
+/// Reports the tasks that directly or indirectly depend on `task`, which
+/// depends on the task at `failed`.
+fn skip_dependents(
+    task: usize,
+    failed: usize,
+    tasks: &[TaskConfig],
+    dependents: &[Vec<usize>],
+    skipped: &mut [bool],
+) {
+    for &x in &dependents[task] {
+        if !skipped[x] {
+            skipped[x] = true;
+
+            tracing::info!(
+                "Skipping {}: `{}` failed",
+                tasks[x].name(),
+                tasks[failed].name()
+            );
+
+            skip_dependents(x, failed, tasks, dependents, skipped);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
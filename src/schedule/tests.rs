@@ -0,0 +1,273 @@
+use super::*;
+use crate::{ConfigFile, LoggingConfig};
+use std::path::{Path, PathBuf};
+
+/// A temporary directory the tasks of a test leave their markers in.
+struct Markers(PathBuf);
+
+impl Markers {
+    fn new(name: &str) -> Self {
+        let path = crate::tests::utils::temp_path(&format!("schedule-{name}"));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    fn exists(&self, marker: &str) -> bool {
+        self.0.join(marker).exists()
+    }
+
+    /// Runs the pre-commit hook of `contents` in the marker directory.
+    async fn run(&self, contents: &str) -> Result<(), Error> {
+        let mut config = ConfigFile::try_from_str(contents).unwrap();
+        let hook = config.hooks.remove("pre-commit").unwrap();
+        let mut context = HookContext::new(
+            self.0.display().to_string(),
+            "pre-commit",
+            vec![],
+            None,
+            LoggingConfig {
+                verbose: false,
+                quiet: true,
+                stream: false,
+            },
+        );
+
+        if let Some(jobs) = config.jobs {
+            context.jobs = jobs.get();
+        }
+
+        run_hook(hook, Arc::new(context), &mut Interrupt::listen().unwrap()).await
+    }
+
+    /// The lines the tasks appended to `log`.
+    fn log(&self, log: &str) -> Vec<String> {
+        std::fs::read_to_string(self.0.join(log))
+            .unwrap_or_default()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+}
+
+impl Drop for Markers {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(Path::new(&self.0));
+    }
+}
+
+#[cfg(test)]
+mod run_hook_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn runs_dependents_after_prerequisites() {
+        let markers = Markers::new("order");
+
+        let result = markers
+            .run(
+                r#"[[hooks.pre-commit]]
+                name = "clippy"
+                run = "test -e built"
+                depends_on = ["build"]
+
+                [[hooks.pre-commit]]
+                name = "build"
+                run = "sh -c 'sleep 0.2 && touch built'"
+            "#,
+            )
+            .await;
+
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn runs_ready_tasks_concurrently() {
+        let markers = Markers::new("concurrent");
+
+        // Each task waits for the other one, so they only finish when both
+        // run at the same time.
+        let result = markers
+            .run(
+                r#"jobs = 2
+
+                [hooks.pre-commit]
+                timeout = 5
+
+                [[hooks.pre-commit.tasks]]
+                name = "build"
+                run = "true"
+
+                [[hooks.pre-commit.tasks]]
+                name = "clippy"
+                run = "sh -c 'touch clippy; until test -e test; do sleep 0.05; done'"
+                depends_on = ["build"]
+
+                [[hooks.pre-commit.tasks]]
+                name = "test"
+                run = "sh -c 'touch test; until test -e clippy; do sleep 0.05; done'"
+                depends_on = ["build"]
+            "#,
+            )
+            .await;
+
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn skips_dependents_of_failed_task() {
+        let markers = Markers::new("skip");
+
+        let result = markers
+            .run(
+                r#"[hooks.pre-commit]
+                fail_fast = false
+
+                [[hooks.pre-commit.tasks]]
+                name = "build"
+                run = "false"
+
+                [[hooks.pre-commit.tasks]]
+                name = "clippy"
+                run = "touch clippy"
+                depends_on = ["build"]
+
+                [[hooks.pre-commit.tasks]]
+                name = "test"
+                run = "touch test"
+                depends_on = ["clippy"]
+
+                [[hooks.pre-commit.tasks]]
+                name = "fmt"
+                run = "touch fmt"
+            "#,
+            )
+            .await
+            .unwrap_err();
+
+        assert!(
+            result
+                .to_string()
+                .starts_with("Command `false` failed with exit code"),
+            "{result}"
+        );
+        assert!(!markers.exists("clippy"));
+        assert!(!markers.exists("test"));
+        assert!(markers.exists("fmt"));
+    }
+
+    #[tokio::test]
+    async fn runs_dependents_of_allowed_failures() {
+        let markers = Markers::new("allowed");
+
+        let result = markers
+            .run(
+                r#"[[hooks.pre-commit]]
+                name = "build"
+                run = "false"
+                allow_failure = true
+
+                [[hooks.pre-commit]]
+                run = "touch clippy"
+                depends_on = ["build"]
+            "#,
+            )
+            .await;
+
+        assert!(result.is_ok(), "{result:?}");
+        assert!(markers.exists("clippy"));
+    }
+
+    #[tokio::test]
+    async fn stops_group_on_failure() {
+        let markers = Markers::new("group");
+
+        let result = markers
+            .run(
+                r#"[hooks.pre-commit]
+                fail_fast = false
+                tasks = [["false", "touch second"], ["touch other"]]
+            "#,
+            )
+            .await;
+
+        assert!(result.is_err());
+        assert!(!markers.exists("second"));
+        assert!(markers.exists("other"));
+    }
+
+    /// A task that runs `first`, then logs when it starts and ends.
+    fn logging_task(name: &str, first: &str) -> String {
+        format!(r#"sh -c '{first} echo start {name} >> log; sleep 0.2; echo end {name} >> log'"#)
+    }
+
+    #[tokio::test]
+    async fn limits_running_tasks_to_jobs() {
+        let markers = Markers::new("jobs");
+
+        let result = markers
+            .run(&format!(
+                r#"jobs = 1
+
+                [hooks]
+                pre-commit = [["{}"], ["{}"]]
+            "#,
+                logging_task("a", ""),
+                logging_task("b", ""),
+            ))
+            .await;
+
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(markers.log("log"), ["start a", "end a", "start b", "end b"]);
+    }
+
+    #[tokio::test]
+    async fn runs_one_task_of_exclusive_group_at_a_time() {
+        let markers = Markers::new("exclusive");
+
+        // The unrelated task runs next to the first `cargo` task, which
+        // waits for it.
+        let result = markers
+            .run(&format!(
+                r#"jobs = 3
+
+                [hooks.pre-commit]
+                timeout = 5
+                tasks = [
+                    [{{ run = "{}", exclusive_group = "cargo" }}],
+                    [{{ run = "{}", exclusive_group = "cargo" }}],
+                    ["touch other"],
+                ]
+            "#,
+                logging_task("a", "until test -e other; do sleep 0.05; done;"),
+                logging_task("b", ""),
+            ))
+            .await;
+
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(markers.log("log"), ["start a", "end a", "start b", "end b"]);
+    }
+
+    #[tokio::test]
+    async fn runs_tasks_in_shell_of_hook() {
+        let markers = Markers::new("shell");
+
+        // Without a shell, `test` receives `&&` and the rest as arguments.
+        let result = markers
+            .run(
+                r#"[hooks.pre-commit]
+                shell = "sh"
+                fail_fast = false
+                tasks = [
+                    ["test a = a && touch shell"],
+                    [{ run = "test a = a && touch direct", shell = false }],
+                ]
+            "#,
+            )
+            .await;
+
+        assert!(result.is_err());
+        assert!(markers.exists("shell"));
+        assert!(!markers.exists("direct"));
+    }
+}
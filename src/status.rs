@@ -0,0 +1,193 @@
+use crate::{
+    ConfigFile, Error, config, git,
+    hooks::{self, HOOK_NAMES, HookFileState, HookFileStatus},
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// The installation state of crusty-hooks in a repository.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct StatusReport {
+    /// Version of the running binary.
+    pub version: String,
+    pub root_directory: String,
+    pub hooks_directory: String,
+    /// The configured `core.hooksPath`, if any.
+    pub hooks_path: Option<String>,
+    /// The git hooks, followed by the scripts they source.
+    pub files: Vec<HookFileStatus>,
+    /// Path of the config file, if one was found.
+    pub config_file: Option<String>,
+    /// Why the config file could not be read or parsed.
+    pub config_error: Option<String>,
+    /// Path of the `crusty-hooks` binary the hooks run, if it is on `PATH`.
+    pub binary: Option<String>,
+    /// Whether a CI environment was detected, where `init` skips installing.
+    pub ci: bool,
+    /// Everything that keeps the hooks from working as configured.
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for StatusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let or_none = |x: &Option<String>| x.clone().unwrap_or_else(|| String::from("none"));
+
+        writeln!(f, "crusty-hooks {}", self.version)?;
+        writeln!(f, "Repository: {}", self.root_directory)?;
+        writeln!(f, "Hooks directory: {}", self.hooks_directory)?;
+        writeln!(f, "core.hooksPath: {}", or_none(&self.hooks_path))?;
+        writeln!(f, "Config file: {}", or_none(&self.config_file))?;
+        writeln!(f, "Binary: {}", or_none(&self.binary))?;
+        writeln!(f, "CI: {}", if self.ci { "yes" } else { "no" })?;
+        writeln!(f, "Files:")?;
+
+        let width = self.files.iter().map(|x| x.name.len()).max().unwrap_or(0);
+
+        for file in &self.files {
+            match &file.version {
+                Some(version) => writeln!(f, "  {:width$}  {} ({version})", file.name, file.state)?,
+                None => writeln!(f, "  {:width$}  {}", file.name, file.state)?,
+            }
+        }
+
+        if self.problems.is_empty() {
+            return write!(f, "No problems found");
+        }
+
+        write!(f, "Problems:")?;
+
+        for problem in &self.problems {
+            write!(f, "\n  - {}", problem.replace('\n', "\n    "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Inspects the hooks, scripts and config file of the repository containing
+/// `target_directory`, and whether the binary the hooks run is on `PATH`.
+pub(crate) fn status_directory<F, G, H, P>(
+    run_command: F,
+    read_file: G,
+    file_exists: H,
+    find_program: P,
+    target_directory: Option<&str>,
+    ci: bool,
+) -> Result<StatusReport, Error>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+    G: Fn(&str) -> Result<String, ()>,
+    H: Fn(&str) -> Result<bool, ()>,
+    P: Fn(&str, &Path) -> Option<PathBuf>,
+{
+    let root_directory = match git::get_root_directory_path(&run_command, target_directory) {
+        Ok(Some(path)) => path,
+        _ => return Err(Error::NotAGitRepo),
+    };
+
+    let hooks_directory = match git::get_hooks_directory(&run_command, &root_directory) {
+        Ok(Some(path)) => path,
+        _ => {
+            return Err(Error::Other(String::from(
+                "Failure determining git hooks directory",
+            )));
+        }
+    };
+
+    let hooks_path = git::get_hooks_path_config(&run_command, &root_directory);
+    let files =
+        hooks::inspect_hook_files(&read_file, &file_exists, &root_directory, &hooks_directory)?;
+
+    let config_file = match config::find_config_file(&root_directory, &file_exists)? {
+        path if path == config::NO_CONFIG_FILE_FOUND => None,
+        path => Some(path),
+    };
+
+    let config_error = config_file
+        .as_deref()
+        .and_then(|path| match read_file(path) {
+            Ok(contents) => ConfigFile::try_from_str(&contents)
+                .err()
+                .map(|e| e.to_string().trim_end().to_string()),
+            Err(_) => Some(String::from("Failure reading file")),
+        });
+
+    let binary = find_program("crusty-hooks", Path::new(&root_directory))
+        .map(|x| x.to_string_lossy().into_owned());
+
+    let mut problems = vec![];
+    let written =
+        |x: &&HookFileStatus| matches!(x.state, HookFileState::Installed | HookFileState::Outdated);
+    let outdated: Vec<&str> = files
+        .iter()
+        .filter(|x| x.state == HookFileState::Outdated)
+        .map(|x| x.name.as_str())
+        .collect();
+    // Hooks can be left out with `--skip-hook-list`, but not the scripts.
+    let missing_scripts: Vec<&str> = files
+        .iter()
+        .filter(|x| !HOOK_NAMES.contains(&x.name.as_str()) && !written(x))
+        .map(|x| x.name.as_str())
+        .collect();
+
+    if !files
+        .iter()
+        .filter(|x| HOOK_NAMES.contains(&x.name.as_str()))
+        .any(|x| written(&x))
+    {
+        problems.push(format!(
+            "No hooks are installed in {hooks_directory}. Run `crusty-hooks init`"
+        ));
+    } else if !missing_scripts.is_empty() {
+        problems.push(format!(
+            "The hooks in {hooks_directory} are missing {}. Run `crusty-hooks init` to restore them",
+            missing_scripts.join(", ")
+        ));
+    }
+
+    if !outdated.is_empty() {
+        problems.push(format!(
+            "Written by another version of crusty-hooks: {}. \
+             Run `crusty-hooks init` to update them",
+            outdated.join(", ")
+        ));
+    }
+
+    match (&config_file, &config_error) {
+        (None, _) => problems.push(String::from(
+            "No config file found. Add a .crusty-hooks.toml to the repository root",
+        )),
+        (Some(path), Some(error)) => problems.push(format!("Invalid config file {path}: {error}")),
+        _ => {}
+    }
+
+    if binary.is_none() {
+        problems.push(String::from(
+            "crusty-hooks is not on PATH, so the hooks cannot run it",
+        ));
+    }
+
+    Ok(StatusReport {
+        version: String::from(env!("CARGO_PKG_VERSION")),
+        root_directory,
+        hooks_directory,
+        hooks_path,
+        files,
+        config_file,
+        config_error,
+        binary,
+        ci,
+        problems,
+    })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,162 @@
+use super::*;
+
+/// Answers the git queries of a repository at `/repo` with its hooks in
+/// `.git/hooks`.
+#[allow(clippy::type_complexity)]
+fn runner() -> impl Fn(
+    &str,
+    Option<&str>,
+    bool,
+    Option<&HashMap<String, String>>,
+) -> Result<Option<String>, Option<String>> {
+    |cmd: &str, _dir: Option<&str>, _stream_io: bool, _env: Option<&HashMap<String, String>>| {
+        match cmd {
+            "git rev-parse --show-toplevel" => Ok(Some(String::from("/repo"))),
+            "git rev-parse --git-path hooks" => Ok(Some(String::from(".git/hooks"))),
+            "git config --get core.hooksPath" => Err(None),
+            _ => panic!("{cmd}"),
+        }
+    }
+}
+
+/// Finds the binary in `/usr/bin` when `on_path`.
+fn finder(on_path: bool) -> impl Fn(&str, &Path) -> Option<PathBuf> {
+    move |program: &str, _cwd: &Path| {
+        assert_eq!(program, "crusty-hooks");
+        on_path.then(|| PathBuf::from("/usr/bin/crusty-hooks"))
+    }
+}
+
+/// Reads the files this version of crusty-hooks writes, and `config` as the
+/// config file.
+fn reader(config: &str) -> impl Fn(&str) -> Result<String, ()> {
+    move |path: &str| {
+        Ok(match path.rsplit('/').next().unwrap_or_default() {
+            ".crusty-hooks.toml" => config.to_string(),
+            "cli.sh" => hooks::get_cli_script_file_contents(),
+            "semver.sh" => hooks::get_semver_script_file_contents(),
+            _ => hooks::get_hook_file_contents(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod status_directory_tests {
+    use super::*;
+
+    #[test]
+    fn reports_no_problems_when_installed() {
+        let file_exists = |path: &str| Ok(!path.ends_with("/crusty-hooks.toml"));
+        let result = status_directory(
+            runner(),
+            reader("[hooks]\npre-commit = \"cargo test\""),
+            file_exists,
+            finder(true),
+            None,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(result.problems, Vec::<String>::new());
+        assert_eq!(
+            result.config_file.as_deref(),
+            Some("/repo/.crusty-hooks.toml")
+        );
+        assert_eq!(result.binary.as_deref(), Some("/usr/bin/crusty-hooks"));
+        assert!(
+            result
+                .files
+                .iter()
+                .all(|x| x.state == HookFileState::Installed)
+        );
+    }
+
+    #[test]
+    fn reports_problems() {
+        let read_file = |path: &str| match path {
+            "/repo/.git/hooks/pre-push" => {
+                Ok(String::from("#!/bin/sh\n# crusty-hooks\n# version 0.0.1\n"))
+            }
+            _ => reader("[hooks]\npre-commit = 1")(path),
+        };
+        let file_exists = |path: &str| {
+            Ok(path.ends_with("/pre-push")
+                || path.ends_with("/pre-commit")
+                || path.ends_with("/.crusty-hooks.toml"))
+        };
+        let result =
+            status_directory(runner(), read_file, file_exists, finder(false), None, true).unwrap();
+
+        assert!(result.ci);
+        let version = env!("CARGO_PKG_VERSION");
+        insta::assert_snapshot!(result.to_string().replace(version, "<version>"), @"
+        crusty-hooks <version>
+        Repository: /repo
+        Hooks directory: .git/hooks
+        core.hooksPath: none
+        Config file: /repo/.crusty-hooks.toml
+        Binary: none
+        CI: yes
+        Files:
+          applypatch-msg         missing
+          pre-applypatch         missing
+          post-applypatch        missing
+          pre-commit             installed (<version>)
+          prepare-commit-msg     missing
+          commit-msg             missing
+          post-commit            missing
+          pre-rebase             missing
+          post-checkout          missing
+          post-merge             missing
+          pre-push               outdated (0.0.1)
+          pre-receive            missing
+          update                 missing
+          post-receive           missing
+          post-update            missing
+          push-to-checkout       missing
+          pre-auto-gc            missing
+          post-rewrite           missing
+          sendemail-validate     missing
+          reference-transaction  missing
+          cli.sh                 missing
+          semver.sh              missing
+        Problems:
+          - The hooks in .git/hooks are missing cli.sh, semver.sh. Run `crusty-hooks init` to restore them
+          - Written by another version of crusty-hooks: pre-push. Run `crusty-hooks init` to update them
+          - Invalid config file /repo/.crusty-hooks.toml: TOML parse error at line 2, column 14
+              |
+            2 | pre-commit = 1
+              |              ^
+            invalid type: integer `1`, expected a command string, an array of tasks, an array of arrays of tasks or a hook table
+          - crusty-hooks is not on PATH, so the hooks cannot run it
+        ");
+    }
+
+    #[test]
+    fn reports_missing_hooks_and_config() {
+        let file_exists = |_path: &str| Ok(false);
+        let result =
+            status_directory(runner(), reader(""), file_exists, finder(true), None, false).unwrap();
+
+        insta::assert_debug_snapshot!(result.problems, @r#"
+        [
+            "No hooks are installed in .git/hooks. Run `crusty-hooks init`",
+            "No config file found. Add a .crusty-hooks.toml to the repository root",
+        ]
+        "#);
+    }
+
+    #[test]
+    fn returns_error_outside_repository() {
+        let run_command = crate::tests::utils::build_simple_command_runner(Err(None));
+        let result = status_directory(
+            run_command,
+            reader(""),
+            |_: &str| Ok(false),
+            finder(true),
+            None,
+            false,
+        );
+        assert!(matches!(result, Err(Error::NotAGitRepo)));
+    }
+}
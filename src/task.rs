@@ -0,0 +1,830 @@
+use crate::{
+    Error, FILES_TOKEN, GIT_ARG_ENV_VAR_PREFIX, GIT_PARAMS_ENV_VAR, GIT_PARAMS_TOKEN,
+    LoggingConfig, STAGED_FILES_TOKEN, Shell, TaskConfig,
+    cache::{self, TaskCache},
+    output, refs,
+};
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash as _, Hasher as _},
+    path::{Path, PathBuf},
+    process::{Output, Stdio},
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+use tokio::{io::AsyncWriteExt as _, sync::Semaphore};
+use tracing::instrument;
+
+/// Everything a task needs to know about the hook invocation it belongs to.
+#[derive(Debug)]
+pub(crate) struct HookContext {
+    pub(crate) root_directory_path: String,
+    pub(crate) hook_name: String,
+    pub(crate) git_params: Vec<String>,
+    /// Raw stdin git passed to the hook, replayed to every task.
+    pub(crate) stdin: Option<String>,
+    pub(crate) env: HashMap<String, String>,
+    pub(crate) logging: LoggingConfig,
+    /// Staged files, only listed when a task of the hook selects `files` or
+    /// is a fixer.
+    pub(crate) staged_files: Option<Vec<String>>,
+    /// Staged files modified by fixers, to be added back to the index.
+    pub(crate) fixes: Mutex<Vec<Fix>>,
+    /// Maximum number of tasks running at the same time.
+    pub(crate) jobs: usize,
+    /// Created once `jobs` is final, see [`HookContext::batch_slots`].
+    batch_slots: OnceLock<Semaphore>,
+    /// Results of earlier runs, only set up when a task of the hook is cached.
+    pub(crate) cache: Option<TaskCache>,
+    /// Names of the tasks not to run.
+    pub(crate) skip: Vec<String>,
+    /// Names of the tasks skipped because of `skip`, to be reported.
+    pub(crate) skipped: Mutex<Vec<String>>,
+    /// Names of the tasks whose `when` condition does not hold, and why.
+    pub(crate) unmet_conditions: HashMap<String, String>,
+}
+
+/// The staged files a fixer modified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Fix {
+    pub(crate) name: String,
+    pub(crate) files: Vec<String>,
+    pub(crate) fail_on_fix: bool,
+}
+
+impl HookContext {
+    pub(crate) fn new(
+        root_directory_path: String,
+        hook_name: &str,
+        git_params: Vec<String>,
+        stdin: Option<String>,
+        logging: LoggingConfig,
+    ) -> Self {
+        let mut env = HashMap::new();
+
+        env.insert(GIT_PARAMS_ENV_VAR.to_string(), git_params.join(" "));
+
+        for (i, param) in git_params.iter().enumerate() {
+            env.insert(format!("{GIT_ARG_ENV_VAR_PREFIX}{}", i + 1), param.clone());
+        }
+
+        if let Some(stdin) = &stdin {
+            env.extend(refs::ref_updates_env(&refs::parse_ref_updates(
+                hook_name, stdin,
+            )));
+        }
+
+        Self {
+            root_directory_path,
+            hook_name: hook_name.to_string(),
+            git_params,
+            stdin,
+            env,
+            logging,
+            staged_files: None,
+            fixes: Mutex::default(),
+            jobs: std::thread::available_parallelism().map_or(1, usize::from),
+            batch_slots: OnceLock::new(),
+            cache: None,
+            skip: vec![],
+            skipped: Mutex::default(),
+            unmet_conditions: HashMap::new(),
+        }
+    }
+
+    /// Limits the batches of tasks with `parallel_batches` running at the
+    /// same time to `jobs`.
+    pub(crate) fn batch_slots(&self) -> &Semaphore {
+        self.batch_slots.get_or_init(|| Semaphore::new(self.jobs))
+    }
+}
+
+#[instrument(skip_all, fields(task = task.name()), err)]
+pub(crate) async fn run_task(task: TaskConfig, context: Arc<HookContext>) -> Result<(), Error> {
+    let name = task.name();
+    let logging = context.logging;
+
+    // Counts as a success, so that its dependents still run.
+    if context.skip.iter().any(|x| x == name) {
+        if let Ok(mut skipped) = context.skipped.lock() {
+            skipped.push(name.to_string());
+        }
+
+        return Ok(());
+    }
+
+    if let Some(reason) = context.unmet_conditions.get(name) {
+        if !logging.quiet {
+            tracing::info!("Skipping {name}: {reason}");
+        }
+
+        return Ok(());
+    }
+
+    let matched = match file_pattern(&task) {
+        Some(pattern) => {
+            let files = match_files(pattern, context.staged_files.as_deref().unwrap_or_default());
+
+            if files.is_empty() {
+                if !logging.quiet {
+                    tracing::info!("Skipping {name}: no staged files match `{pattern}`");
+                }
+
+                return Ok(());
+            }
+
+            Some(files)
+        }
+        None => None,
+    };
+
+    // A fixer without `files` may rewrite any staged file.
+    let fixable = is_fixer(&task).then(|| {
+        matched
+            .clone()
+            .or_else(|| context.staged_files.clone())
+            .unwrap_or_default()
+    });
+    let fingerprints = fixable
+        .as_deref()
+        .map(|files| fingerprint_files(&context.root_directory_path, files));
+
+    let files = matched.map(|files| anchor_files(&task, &context.root_directory_path, files));
+
+    let cache_key = context
+        .cache
+        .as_ref()
+        .filter(|_| cache::is_cached(&task))
+        .map(|x| {
+            let key = x.key(
+                &task,
+                files.as_deref(),
+                &context.env,
+                context.stdin.as_deref(),
+            );
+
+            (x, key)
+        });
+
+    if let Some((cache, key)) = &cache_key
+        && cache.contains(key)
+    {
+        if !logging.quiet {
+            tracing::info!("Skipping {name}: cached");
+        }
+
+        return Ok(());
+    }
+
+    if !logging.quiet {
+        match &task.description {
+            Some(description) => tracing::info!("Running {name}: {description}"),
+            None => tracing::info!("Running {name}"),
+        }
+    }
+
+    let result = execute_task(&task, files.as_deref(), &context).await;
+
+    let mut fixed = false;
+
+    // Recorded even when the fixer failed, since its changes are on disk.
+    if let (Some(files), Some(before)) = (fixable, fingerprints) {
+        let after = fingerprint_files(&context.root_directory_path, &files);
+        let files: Vec<String> = files
+            .into_iter()
+            .zip(before.into_iter().zip(after))
+            .filter(|(_, (before, after))| before != after)
+            .map(|(x, _)| x)
+            .collect();
+
+        fixed = !files.is_empty();
+
+        if fixed && let Ok(mut fixes) = context.fixes.lock() {
+            fixes.push(Fix {
+                name: name.to_string(),
+                files,
+                fail_on_fix: task.fail_on_fix,
+            });
+        }
+    }
+
+    // A fix changed the tree the key was computed from.
+    if result.is_ok()
+        && !fixed
+        && let Some((cache, key)) = &cache_key
+        && let Err(e) = cache.insert(key, name)
+    {
+        tracing::warn!("Failed to cache the result of {name}: {e}");
+    }
+
+    match result {
+        Err(e) if task.allow_failure => {
+            tracing::warn!("{e} (failure allowed)");
+        }
+        Err(e) => return Err(e),
+        Ok(()) => {
+            if !logging.quiet {
+                tracing::info!("Finished {name}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn execute_task(
+    task: &TaskConfig,
+    files: Option<&[String]>,
+    context: &Arc<HookContext>,
+) -> Result<(), Error> {
+    let name = task.name();
+    let logging = context.logging;
+
+    let (envs, invocations) = invocations(task, files, context)?;
+
+    let run = async {
+        if task.parallel_batches && invocations.len() > 1 {
+            let mut handle = tokio::task::JoinSet::new();
+
+            for (i, args) in invocations.into_iter().enumerate() {
+                let (task, envs, context) = (task.clone(), envs.clone(), Arc::clone(context));
+
+                handle.spawn(async move {
+                    // The semaphore is never closed.
+                    let _permit = context.batch_slots().acquire().await;
+
+                    (i, execute_command(&task, args, &envs, &context).await)
+                });
+            }
+
+            let mut outputs = Vec::with_capacity(handle.len());
+
+            while let Some(x) = handle.join_next().await {
+                let (i, output) = x.map_err(|e| spawn_error(name, e.into()))?;
+
+                outputs.push((i, output.map_err(|e| spawn_error(name, e))?));
+            }
+
+            outputs.sort_by_key(|(i, _)| *i);
+
+            Ok(outputs.into_iter().map(|(_, output)| output).collect())
+        } else {
+            let mut outputs = Vec::with_capacity(invocations.len());
+
+            for args in invocations {
+                outputs.push(
+                    execute_command(task, args, &envs, context)
+                        .await
+                        .map_err(|e| spawn_error(name, e))?,
+                );
+            }
+
+            Ok::<Vec<Output>, Error>(outputs)
+        }
+    };
+
+    // The process groups of running commands are killed when they are
+    // dropped on timeout.
+    let outputs = match task.timeout {
+        Some(secs) => tokio::time::timeout(Duration::from_secs(secs), run)
+            .await
+            .map_err(|_| Error::Timeout {
+                name: name.to_string(),
+                secs,
+            })??,
+        None => run.await?,
+    };
+
+    let failed: Vec<_> = outputs.iter().filter(|x| !x.status.success()).collect();
+
+    if let Some(first) = failed.first() {
+        let mut stderr = String::new();
+
+        for output in &failed {
+            // Streamed output was already printed as it was produced.
+            if logging.stream {
+                stderr.push_str(&String::from_utf8_lossy(&output.stderr));
+                continue;
+            }
+
+            if !output.stdout.is_empty() {
+                tracing::error!("Stdout: {}", String::from_utf8_lossy(&output.stdout));
+            }
+
+            if !output.stderr.is_empty() {
+                tracing::error!("Stderr: {}", String::from_utf8_lossy(&output.stderr));
+                stderr.push_str(&String::from_utf8_lossy(&output.stderr));
+            }
+        }
+
+        return Err(Error::TaskFailed {
+            name: name.to_string(),
+            command: task.run.clone(),
+            exit_code: first.status.code(),
+            stderr,
+            failed_batches: failed.len(),
+            batches: outputs.len(),
+        });
+    }
+
+    // Streamed output was already printed as it was produced.
+    if logging.verbose && !logging.quiet && !logging.stream {
+        for output in &outputs {
+            output::write_prefixed(output::Stream::Stdout, name, &output.stdout);
+            output::write_prefixed(output::Stream::Stderr, name, &output.stderr);
+        }
+    }
+
+    Ok(())
+}
+
+/// The variables that the command of `task` sets before its program, and
+/// the arguments of each invocation of the command, one per batch of
+/// `files`.
+#[allow(clippy::type_complexity)]
+pub(crate) fn invocations(
+    task: &TaskConfig,
+    files: Option<&[String]>,
+    context: &HookContext,
+) -> Result<(HashMap<String, String>, Vec<Vec<String>>), Error> {
+    let (envs, cmd) = match &task.shell {
+        Some(Shell::Program(shell)) => (
+            HashMap::new(),
+            vec![
+                shell.clone(),
+                String::from("-c"),
+                substitute_script_token(&task.run, GIT_PARAMS_TOKEN, &context.git_params),
+            ],
+        ),
+        _ => {
+            let (envs, cmd) = parse_env_and_command(&task.run);
+
+            (envs, substitute_git_params(cmd, &context.git_params))
+        }
+    };
+
+    if cmd.is_empty() {
+        return Err(Error::Other(format!(
+            "Command `{}` does not contain a program to run",
+            task.run
+        )));
+    }
+
+    let invocations = match files {
+        Some(files) => {
+            let limit = argument_limit(context.env.iter().chain(&task.env).chain(&envs));
+
+            batch_files(&cmd, files, limit)
+                .into_iter()
+                .map(|batch| match &task.shell {
+                    Some(Shell::Program(_)) => substitute_script_files(cmd.clone(), batch),
+                    _ => substitute_files(cmd.clone(), batch),
+                })
+                .collect()
+        }
+        None => vec![cmd],
+    };
+
+    Ok((envs, invocations))
+}
+
+/// The `files` of `task`, relative to the repository root at `root`, as its
+/// command sees them. The root only is the working directory of tasks
+/// without a `cwd`.
+pub(crate) fn anchor_files(task: &TaskConfig, root: &str, files: Vec<String>) -> Vec<String> {
+    match task.cwd {
+        Some(_) => files
+            .into_iter()
+            .map(|x| Path::new(root).join(x).display().to_string())
+            .collect(),
+        None => files,
+    }
+}
+
+/// The error of the task `name`, whose command could not be run.
+fn spawn_error(name: &str, source: std::io::Error) -> Error {
+    Error::TaskSpawn {
+        name: name.to_string(),
+        source,
+    }
+}
+
+/// Runs a single invocation of the command of `task`.
+async fn execute_command(
+    task: &TaskConfig,
+    args: Vec<String>,
+    envs: &HashMap<String, String>,
+    context: &HookContext,
+) -> Result<Output, std::io::Error> {
+    let name = task.name();
+
+    let mut args = args.into_iter();
+
+    let Some(program) = args.next() else {
+        return Err(std::io::Error::other(format!(
+            "Command `{}` does not contain a program to run",
+            task.run
+        )));
+    };
+
+    let mut cmd = tokio::process::Command::new(program);
+
+    cmd.args(args)
+        .current_dir(resolve_cwd(
+            &context.root_directory_path,
+            task.cwd.as_deref(),
+        ))
+        .envs(&context.env)
+        .envs(&task.env)
+        .stdin(if context.stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    // Run every command in its own process group, so that a timed out or
+    // cancelled task can be terminated together with everything it spawned.
+    #[cfg(unix)]
+    cmd.process_group(0);
+
+    if !envs.is_empty() {
+        cmd.envs(envs);
+    }
+
+    let mut child = cmd.spawn()?;
+
+    // The process group is killed when the guard is dropped before the
+    // command exited.
+    let process_group = ProcessGroupGuard::new(&child);
+
+    // Feed stdin concurrently so a child that writes a lot of output
+    // before reading its input cannot deadlock against us.
+    if let (Some(stdin), Some(mut child_stdin)) = (&context.stdin, child.stdin.take()) {
+        let stdin = stdin.clone();
+
+        tokio::spawn(async move {
+            // The child may legitimately exit without reading stdin.
+            let _ = child_stdin.write_all(stdin.as_bytes()).await;
+        });
+    }
+
+    let output = if context.logging.stream {
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+
+        // Only the end of stderr is kept, for the failure summary.
+        let (status, _, stderr) = tokio::try_join!(
+            child.wait(),
+            output::forward_lines(stdout, output::Stream::Stdout, name, 0),
+            output::forward_lines(stderr, output::Stream::Stderr, name, STREAMED_STDERR_LIMIT),
+        )?;
+
+        Output {
+            status,
+            stdout: vec![],
+            stderr,
+        }
+    } else {
+        child.wait_with_output().await?
+    };
+
+    process_group.disarm();
+
+    Ok(output)
+}
+
+/// Bytes of the stderr of a streamed command kept for its failure report.
+const STREAMED_STDERR_LIMIT: usize = 64 * 1024;
+
+/// Linux rejects any single argument longer than this, which also bounds the
+/// argument that an embedded file token joins all files of a batch into.
+const MAX_ARG_LEN: usize = 128 * 1024;
+
+/// Room left for the program path, auxiliary vectors and the like.
+const ARGUMENT_HEADROOM: usize = 4096;
+
+/// Bytes the arguments of one command may take up, given the environment
+/// variables set on top of the inherited environment.
+pub(crate) fn argument_limit<'a>(envs: impl Iterator<Item = (&'a String, &'a String)>) -> usize {
+    #[cfg(unix)]
+    // SAFETY: `sysconf` has no memory safety requirements.
+    let arg_max = usize::try_from(unsafe { libc::sysconf(libc::_SC_ARG_MAX) }).unwrap_or(0);
+
+    // The limit of a command line on Windows, in UTF-16 code units.
+    #[cfg(not(unix))]
+    let arg_max = 32 * 1024;
+
+    let env_len: usize = std::env::vars_os()
+        .map(|(k, v)| argument_size(k.len() + v.len() + 1))
+        .chain(envs.map(|(k, v)| argument_size(k.len() + v.len() + 1)))
+        .sum();
+
+    arg_max
+        .saturating_sub(env_len + ARGUMENT_HEADROOM)
+        .clamp(ARGUMENT_HEADROOM, MAX_ARG_LEN)
+}
+
+/// Bytes an argument of `len` bytes takes up: its NUL terminator and pointer.
+fn argument_size(len: usize) -> usize {
+    len + 1 + std::mem::size_of::<usize>()
+}
+
+/// Splits `files` into batches that, together with the other arguments of
+/// `cmd`, fit into `limit` bytes. A file too long to share a batch gets its own.
+pub(crate) fn batch_files<'a>(
+    cmd: &[String],
+    files: &'a [String],
+    limit: usize,
+) -> Vec<&'a [String]> {
+    let fixed: usize = cmd.iter().map(|x| argument_size(x.len())).sum();
+
+    let mut batches = vec![];
+    let mut start = 0;
+    let mut size = fixed;
+
+    for (i, file) in files.iter().enumerate() {
+        let file_size = argument_size(file.len());
+
+        if i > start && size + file_size > limit {
+            batches.push(&files[start..i]);
+            start = i;
+            size = fixed;
+        }
+
+        size += file_size;
+    }
+
+    if start < files.len() {
+        batches.push(&files[start..]);
+    }
+
+    batches
+}
+
+/// Whether the staged files modified by `task` are added back to the index.
+pub(crate) fn is_fixer(task: &TaskConfig) -> bool {
+    task.fix || task.fail_on_fix
+}
+
+/// Hashes of the contents of `files`, relative to `root`, that tell whether a
+/// fixer modified them. Missing files hash to `None`.
+fn fingerprint_files(root: &str, files: &[String]) -> Vec<Option<u64>> {
+    files
+        .iter()
+        .map(|x| {
+            std::fs::read(Path::new(root).join(x)).ok().map(|contents| {
+                let mut hasher = DefaultHasher::new();
+                contents.hash(&mut hasher);
+                hasher.finish()
+            })
+        })
+        .collect()
+}
+
+/// The error a task failed with, together with the name it is reported under.
+#[derive(Debug)]
+pub(crate) struct TaskFailure {
+    pub(crate) name: String,
+    pub(crate) error: Error,
+}
+
+/// Turns the failures of a hook run into its result.
+///
+/// A single failure is returned as is. Several failures are consolidated into
+/// one error listing every failed task, which is reported as a timeout if any
+/// of the tasks timed out.
+pub(crate) fn summarize_failures(mut failures: Vec<TaskFailure>) -> Result<(), Error> {
+    if failures.len() <= 1 {
+        return failures.pop().map_or(Ok(()), |failure| Err(failure.error));
+    }
+
+    Err(Error::TasksFailed(
+        failures.into_iter().map(|x| (x.name, x.error)).collect(),
+    ))
+}
+
+/// Kills the process group of a task when dropped before being disarmed.
+///
+/// Dropping happens when a task times out or the hook run is cancelled, which
+/// would otherwise leave the processes spawned by the task running.
+struct ProcessGroupGuard {
+    pid: Option<u32>,
+}
+
+impl ProcessGroupGuard {
+    fn new(child: &tokio::process::Child) -> Self {
+        Self { pid: child.id() }
+    }
+
+    /// Keeps the process group alive, once the task exited on its own.
+    fn disarm(mut self) {
+        self.pid = None;
+    }
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.pid.and_then(|pid| i32::try_from(pid).ok()) {
+            // SAFETY: `killpg` has no memory safety requirements. The group
+            // leader has not been reaped yet, so its id cannot have been reused.
+            unsafe {
+                libc::killpg(pid, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+/// Resolves the working directory of a task against the repository root.
+pub(crate) fn resolve_cwd(root_directory_path: &str, cwd: Option<&str>) -> PathBuf {
+    match cwd {
+        Some(cwd) => Path::new(root_directory_path).join(cwd),
+        None => PathBuf::from(root_directory_path),
+    }
+}
+
+/// Path of the executable that `program`, spawned from `cwd`, resolves to:
+/// the file itself when it contains a path separator, otherwise the first
+/// match on `PATH`, also trying the extensions of `PATHEXT` on Windows.
+pub(crate) fn find_program(program: &str, cwd: &Path) -> Option<PathBuf> {
+    let extensions: Vec<String> = match cfg!(windows) {
+        true => std::env::var("PATHEXT")
+            .unwrap_or_else(|_| String::from(".COM;.EXE;.BAT;.CMD"))
+            .split(';')
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect(),
+        false => vec![],
+    };
+    let candidates = |dir: &Path| {
+        let path = dir.join(program);
+        let with_extensions: Vec<PathBuf> = extensions
+            .iter()
+            .map(|x| {
+                let mut path = path.clone().into_os_string();
+                path.push(x);
+                PathBuf::from(path)
+            })
+            .collect();
+
+        std::iter::once(path).chain(with_extensions)
+    };
+
+    if program.chars().any(std::path::is_separator) {
+        return candidates(cwd).find(|x| is_executable(x));
+    }
+
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .flat_map(|dir| candidates(&dir))
+        .find(|x| is_executable(x))
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        path.metadata()
+            .is_ok_and(|x| x.is_file() && x.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+pub(crate) fn parse_env_and_command(input: &str) -> (HashMap<String, String>, Vec<String>) {
+    let mut env_vars = HashMap::new();
+    let mut command_args = Vec::new();
+
+    if let Some(parts) = shlex::split(input) {
+        let mut found_command = false;
+
+        for part in parts {
+            // Check if it's an env var assignment (KEY=VALUE pattern)
+            if !found_command
+                && part.contains('=')
+                && let Some((key, value)) = part.split_once('=')
+            {
+                // Validate that the key looks like a valid env var name
+                if key.chars().all(|c| c.is_alphanumeric() || c == '_')
+                    && !key.is_empty()
+                    && !key.chars().next().unwrap().is_numeric()
+                {
+                    env_vars.insert(key.to_string(), value.to_string());
+                    continue;
+                }
+            }
+
+            // Once we hit a non-env-var pattern, treat rest as command
+            found_command = true;
+            command_args.push(part);
+        }
+    }
+
+    (env_vars, command_args)
+}
+
+/// Replaces [`GIT_PARAMS_TOKEN`] in the parsed command with the git arguments.
+///
+/// An argument that consists solely of the token expands to one argument per
+/// git parameter, so paths containing spaces survive. When the token is
+/// embedded in a larger argument, the parameters are joined with spaces.
+pub(crate) fn substitute_git_params(cmd: Vec<String>, git_params: &[String]) -> Vec<String> {
+    substitute_token(cmd, GIT_PARAMS_TOKEN, git_params)
+}
+
+/// The glob selecting the files of `task`. A command that contains a file
+/// token without setting `files` receives every staged file.
+pub(crate) fn file_pattern(task: &TaskConfig) -> Option<&str> {
+    match &task.files {
+        Some(pattern) => Some(pattern),
+        None if task.run.contains(FILES_TOKEN) || task.run.contains(STAGED_FILES_TOKEN) => {
+            Some("*")
+        }
+        None => None,
+    }
+}
+
+/// Replaces [`FILES_TOKEN`] or [`STAGED_FILES_TOKEN`] in the parsed command
+/// with the files, the same way as [`substitute_git_params`], or appends them
+/// as arguments when the command contains neither token.
+pub(crate) fn substitute_files(mut cmd: Vec<String>, files: &[String]) -> Vec<String> {
+    for token in [FILES_TOKEN, STAGED_FILES_TOKEN] {
+        if cmd.iter().any(|arg| arg.contains(token)) {
+            return substitute_token(cmd, token, files);
+        }
+    }
+
+    cmd.extend(files.iter().cloned());
+
+    cmd
+}
+
+fn substitute_token(cmd: Vec<String>, token: &str, values: &[String]) -> Vec<String> {
+    let mut out = Vec::with_capacity(cmd.len() + values.len());
+
+    for arg in cmd {
+        if arg == token {
+            out.extend(values.iter().cloned());
+        } else if arg.contains(token) {
+            out.push(arg.replace(token, &values.join(" ")));
+        } else {
+            out.push(arg);
+        }
+    }
+
+    out
+}
+
+/// Replaces [`FILES_TOKEN`] or [`STAGED_FILES_TOKEN`] in the script of a
+/// shell command (`<shell> -c <script>`) with the quoted files, or appends
+/// them to the script when it contains neither token.
+pub(crate) fn substitute_script_files(mut cmd: Vec<String>, files: &[String]) -> Vec<String> {
+    if let Some(script) = cmd.last_mut() {
+        *script = match [FILES_TOKEN, STAGED_FILES_TOKEN]
+            .into_iter()
+            .find(|token| script.contains(token))
+        {
+            Some(token) => substitute_script_token(script, token, files),
+            None => format!("{script} {}", quote_arguments(files)),
+        };
+    }
+
+    cmd
+}
+
+/// Replaces `token` in a shell script with the quoted `values`.
+fn substitute_script_token(script: &str, token: &str, values: &[String]) -> String {
+    script.replace(token, &quote_arguments(values))
+}
+
+/// `values` quoted for a shell and separated by spaces.
+fn quote_arguments(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|x| shlex::try_quote(x).map_or_else(|_| x.clone(), String::from))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The files matching the glob `pattern`, in which `*` also matches `/` so
+/// that `*.rs` selects Rust files in every directory.
+pub(crate) fn match_files(pattern: &str, files: &[String]) -> Vec<String> {
+    // The pattern was validated when the config file was parsed.
+    let Ok(pattern) = glob::Pattern::new(pattern) else {
+        return vec![];
+    };
+
+    files
+        .iter()
+        .filter(|x| pattern.matches(x))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,798 @@
+use super::*;
+
+#[cfg(test)]
+mod substitute_git_params_tests {
+    use super::*;
+    use crate::tests::utils::params;
+
+    #[test]
+    fn expands_standalone_token_into_separate_args() {
+        let cmd = params(&["check-msg", "%rh!", "--strict"]);
+        let result = substitute_git_params(cmd, &params(&[".git/COMMIT EDITMSG", "message"]));
+        assert_eq!(
+            result,
+            params(&["check-msg", ".git/COMMIT EDITMSG", "message", "--strict"])
+        );
+    }
+
+    #[test]
+    fn joins_params_for_embedded_token() {
+        let cmd = params(&["echo", "args=%rh!"]);
+        let result = substitute_git_params(cmd, &params(&["origin", "git@host:repo"]));
+        assert_eq!(result, params(&["echo", "args=origin git@host:repo"]));
+    }
+
+    #[test]
+    fn removes_standalone_token_without_params() {
+        let cmd = params(&["echo", "%rh!"]);
+        let result = substitute_git_params(cmd, &[]);
+        assert_eq!(result, params(&["echo"]));
+    }
+}
+
+#[cfg(test)]
+mod substitute_files_tests {
+    use super::*;
+    use crate::tests::utils::params;
+
+    #[test]
+    fn expands_standalone_token_into_separate_args() {
+        let cmd = params(&["rustfmt", "{staged_files}", "--check"]);
+        let result = substitute_files(cmd, &params(&["src/a b.rs", "src/c.rs"]));
+        assert_eq!(
+            result,
+            params(&["rustfmt", "src/a b.rs", "src/c.rs", "--check"])
+        );
+    }
+
+    #[test]
+    fn joins_files_for_embedded_token() {
+        let cmd = params(&["echo", "files={staged_files}"]);
+        let result = substitute_files(cmd, &params(&["a.rs", "b.rs"]));
+        assert_eq!(result, params(&["echo", "files=a.rs b.rs"]));
+    }
+
+    #[test]
+    fn appends_files_without_token() {
+        let cmd = params(&["rustfmt", "--check"]);
+        let result = substitute_files(cmd, &params(&["a.rs"]));
+        assert_eq!(result, params(&["rustfmt", "--check", "a.rs"]));
+    }
+}
+
+#[cfg(test)]
+mod substitute_script_files_tests {
+    use super::*;
+    use crate::tests::utils::params;
+
+    #[test]
+    fn replaces_token_with_quoted_files() {
+        let cmd = params(&["sh", "-c", "rustfmt --check {files} && echo done"]);
+        let result = substitute_script_files(cmd, &params(&["src/a b.rs", "src/c.rs"]));
+        assert_eq!(
+            result,
+            params(&[
+                "sh",
+                "-c",
+                "rustfmt --check 'src/a b.rs' src/c.rs && echo done"
+            ])
+        );
+    }
+
+    #[test]
+    fn appends_quoted_files_without_token() {
+        let cmd = params(&["bash", "-c", "rustfmt --check"]);
+        let result = substitute_script_files(cmd, &params(&["it's.rs"]));
+        assert_eq!(
+            result,
+            params(&["bash", "-c", r#"rustfmt --check "it's.rs""#])
+        );
+    }
+}
+
+#[cfg(test)]
+mod file_pattern_tests {
+    use super::*;
+
+    #[test]
+    fn uses_files_glob() {
+        let task = TaskConfig {
+            files: Some(String::from("*.rs")),
+            ..TaskConfig::from("rustfmt --check")
+        };
+        assert_eq!(file_pattern(&task), Some("*.rs"));
+    }
+
+    #[test]
+    fn selects_every_file_for_token_without_glob() {
+        assert_eq!(
+            file_pattern(&TaskConfig::from("rustfmt {files}")),
+            Some("*")
+        );
+        assert_eq!(
+            file_pattern(&TaskConfig::from("rustfmt {staged_files}")),
+            Some("*")
+        );
+    }
+
+    #[test]
+    fn returns_none_without_files() {
+        assert_eq!(file_pattern(&TaskConfig::from("cargo test")), None);
+    }
+}
+
+#[cfg(test)]
+mod batch_files_tests {
+    use super::*;
+
+    fn files(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("src/file{i:03}.rs")).collect()
+    }
+
+    #[test]
+    fn keeps_files_that_fit_in_one_batch() {
+        let files = files(10);
+        let batches = batch_files(&[String::from("rustfmt")], &files, MAX_ARG_LEN);
+        assert_eq!(batches, [&files[..]]);
+    }
+
+    #[test]
+    fn splits_files_at_limit() {
+        let cmd = [String::from("rustfmt")];
+        let files = files(10);
+        // Every file takes up 15 bytes, its NUL terminator and a pointer.
+        let file_size = 16 + std::mem::size_of::<usize>();
+        let limit = argument_size(cmd[0].len()) + 4 * file_size;
+
+        let batches = batch_files(&cmd, &files, limit);
+
+        assert_eq!(batches, [&files[..4], &files[4..8], &files[8..]]);
+    }
+
+    #[test]
+    fn gives_oversized_file_its_own_batch() {
+        let files = vec![String::from("a.rs"), "b".repeat(100), String::from("c.rs")];
+        let batches = batch_files(&[], &files, 64);
+        assert_eq!(batches, [&files[..1], &files[1..2], &files[2..]]);
+    }
+
+    #[test]
+    fn returns_no_batch_without_files() {
+        assert!(batch_files(&[String::from("rustfmt")], &[], MAX_ARG_LEN).is_empty());
+    }
+
+    #[test]
+    fn limit_stays_within_single_argument_limit() {
+        let limit = argument_limit(std::iter::empty());
+        assert!((ARGUMENT_HEADROOM..=MAX_ARG_LEN).contains(&limit));
+    }
+}
+
+#[cfg(test)]
+mod match_files_tests {
+    use super::*;
+
+    fn files() -> Vec<String> {
+        ["build.rs", "src/lib.rs", "src/task/tests.rs", "README.md"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn matches_extension_in_every_directory() {
+        assert_eq!(
+            match_files("*.rs", &files()),
+            ["build.rs", "src/lib.rs", "src/task/tests.rs"]
+        );
+    }
+
+    #[test]
+    fn matches_directory_prefix() {
+        assert_eq!(
+            match_files("src/**/*.rs", &files()),
+            ["src/lib.rs", "src/task/tests.rs"]
+        );
+    }
+
+    #[test]
+    fn returns_empty_without_match() {
+        assert!(match_files("*.toml", &files()).is_empty());
+    }
+}
+
+#[cfg(test)]
+mod hook_context_tests {
+    use super::*;
+    use crate::tests::utils::params;
+
+    const PUSH_LINE: &str = "refs/heads/main 1111111111111111111111111111111111111111 refs/heads/main 2222222222222222222222222222222222222222\n";
+
+    #[test]
+    fn exports_params_as_env_vars() {
+        let context = HookContext::new(
+            String::from("."),
+            "pre-push",
+            params(&["origin", "git@host:repo"]),
+            None,
+            LoggingConfig::default(),
+        );
+        assert_eq!(
+            context.env["CRUSTY_HOOKS_GIT_PARAMS"],
+            "origin git@host:repo"
+        );
+        assert_eq!(context.env["CRUSTY_HOOKS_ARG_1"], "origin");
+        assert_eq!(context.env["CRUSTY_HOOKS_ARG_2"], "git@host:repo");
+        assert!(!context.env.contains_key("CRUSTY_HOOKS_ARG_3"));
+    }
+
+    #[test]
+    fn exports_ref_updates_as_env_vars() {
+        let context = HookContext::new(
+            String::from("."),
+            "pre-push",
+            vec![],
+            Some(String::from(PUSH_LINE)),
+            LoggingConfig::default(),
+        );
+        assert_eq!(context.env["CRUSTY_HOOKS_REF_COUNT"], "1");
+        assert_eq!(
+            context.env["CRUSTY_HOOKS_REF_1_LOCAL_REF"],
+            "refs/heads/main"
+        );
+        assert_eq!(
+            context.env["CRUSTY_HOOKS_REF_1_REMOTE_SHA"],
+            "2222222222222222222222222222222222222222"
+        );
+    }
+
+    #[test]
+    fn does_not_export_ref_env_vars_without_stdin() {
+        let context = HookContext::new(
+            String::from("."),
+            "pre-commit",
+            vec![],
+            None,
+            LoggingConfig::default(),
+        );
+        assert!(!context.env.contains_key("CRUSTY_HOOKS_REF_COUNT"));
+    }
+}
+
+#[cfg(test)]
+mod resolve_cwd_tests {
+    use super::*;
+
+    #[test]
+    fn defaults_to_root_directory() {
+        assert_eq!(resolve_cwd("/repo", None), PathBuf::from("/repo"));
+    }
+
+    #[test]
+    fn resolves_relative_cwd_against_root() {
+        assert_eq!(
+            resolve_cwd("/repo", Some("crates/core")),
+            PathBuf::from("/repo/crates/core")
+        );
+    }
+
+    #[test]
+    fn keeps_absolute_cwd() {
+        assert_eq!(resolve_cwd("/repo", Some("/tmp")), PathBuf::from("/tmp"));
+    }
+}
+
+#[cfg(all(test, unix))]
+mod find_program_tests {
+    use super::*;
+    use crate::tests::utils::temp_path;
+
+    #[test]
+    fn finds_program_on_path() {
+        let result = find_program("sh", Path::new("/")).unwrap();
+        assert!(result.is_absolute() && result.ends_with("sh"), "{result:?}");
+    }
+
+    #[test]
+    fn resolves_path_against_directory() {
+        assert_eq!(
+            find_program("./sh", Path::new("/bin")),
+            Some(PathBuf::from("/bin/./sh"))
+        );
+        assert_eq!(find_program("./sh", Path::new("/")), None);
+    }
+
+    #[test]
+    fn skips_missing_and_non_executable_files() {
+        let file = temp_path("plain");
+        std::fs::write(&file, "").unwrap();
+
+        assert_eq!(find_program("crusty-hooks-missing", Path::new("/")), None);
+        assert_eq!(find_program(&file.to_string_lossy(), Path::new("/")), None);
+        std::fs::remove_file(file).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod run_task_tests {
+    use super::*;
+    use crate::tests::utils::temp_path;
+
+    fn context() -> Arc<HookContext> {
+        Arc::new(HookContext::new(
+            String::from("."),
+            "pre-commit",
+            vec![],
+            None,
+            LoggingConfig {
+                verbose: false,
+                quiet: true,
+                stream: false,
+            },
+        ))
+    }
+
+    #[tokio::test]
+    async fn returns_err_when_command_fails() {
+        let task = TaskConfig::from("false");
+        let result = run_task(task, context()).await.unwrap_err();
+        assert!(
+            result
+                .to_string()
+                .starts_with("Command `false` failed with exit code")
+        );
+    }
+
+    #[tokio::test]
+    async fn returns_err_when_command_empty() {
+        let task = TaskConfig::from("FOO=bar");
+        let result = run_task(task, context()).await.unwrap_err();
+        assert_eq!(
+            result.to_string(),
+            "Command `FOO=bar` does not contain a program to run"
+        );
+    }
+
+    #[tokio::test]
+    async fn ignores_failure_when_allowed() {
+        let task = TaskConfig {
+            allow_failure: true,
+            ..TaskConfig::from("false")
+        };
+        assert!(run_task(task, context()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn passes_task_env() {
+        let task = TaskConfig {
+            env: [(String::from("FOO"), String::from("bar"))].into(),
+            ..TaskConfig::from(r#"sh -c 'test "$FOO" = bar'"#)
+        };
+        assert!(run_task(task, context()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn inline_env_overrides_task_env() {
+        let task = TaskConfig {
+            env: [(String::from("FOO"), String::from("bar"))].into(),
+            ..TaskConfig::from(r#"FOO=baz sh -c 'test "$FOO" = baz'"#)
+        };
+        assert!(run_task(task, context()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn runs_in_task_cwd() {
+        let task = TaskConfig {
+            cwd: Some(String::from("src")),
+            ..TaskConfig::from("test -f task.rs")
+        };
+        assert!(run_task(task, context()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn returns_err_when_timed_out() {
+        let task = TaskConfig {
+            name: Some(String::from("sleeper")),
+            timeout: Some(1),
+            ..TaskConfig::from("sleep 30")
+        };
+        let result = run_task(task, context()).await.unwrap_err();
+        assert_eq!(result.to_string(), "`sleeper` timed out after 1s");
+    }
+
+    fn shell_task(run: &str) -> TaskConfig {
+        TaskConfig {
+            shell: Some(Shell::Program(String::from("sh"))),
+            ..TaskConfig::from(run)
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_command_through_shell() {
+        let task = shell_task(r#"FOO=bar; test "$FOO" = bar && echo a | grep -q a"#);
+        let result = run_task(task, context()).await;
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn reports_exit_code_of_shell() {
+        let task = shell_task("true && exit 3");
+        let result = run_task(task, context()).await.unwrap_err();
+        assert_eq!(
+            result.to_string(),
+            "Command `true && exit 3` failed with exit code Some(3)"
+        );
+    }
+
+    #[tokio::test]
+    async fn passes_quoted_git_params_to_shell() {
+        let task = shell_task(r#"test "$(printf '%s|' %rh!)" = 'a b|c|'"#);
+        let mut context = Arc::into_inner(context()).unwrap();
+        context.git_params = vec![String::from("a b"), String::from("c")];
+        let result = run_task(task, Arc::new(context)).await;
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn does_not_interpret_operators_without_shell() {
+        let task = TaskConfig::from("true && exit 3");
+        let result = run_task(task, context()).await;
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    fn context_with_staged_files(files: &[&str]) -> Arc<HookContext> {
+        let mut context = Arc::into_inner(context()).unwrap();
+        // The root reported by git is absolute.
+        context.root_directory_path = String::from(env!("CARGO_MANIFEST_DIR"));
+        context.staged_files = Some(files.iter().map(|x| x.to_string()).collect());
+        Arc::new(context)
+    }
+
+    #[tokio::test]
+    async fn skips_task_without_matching_files() {
+        let task = TaskConfig {
+            files: Some(String::from("*.rs")),
+            ..TaskConfig::from("false")
+        };
+        let context = context_with_staged_files(&["README.md"]);
+        assert!(run_task(task, context).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn passes_matching_files() {
+        let task = TaskConfig {
+            files: Some(String::from("*.rs")),
+            ..TaskConfig::from(r#"sh -c 'test "$*" = "src/lib.rs src/main.rs"' sh"#)
+        };
+        let context = context_with_staged_files(&["src/lib.rs", "README.md", "src/main.rs"]);
+        assert!(run_task(task, context).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn passes_files_relative_to_task_cwd() {
+        let task = TaskConfig {
+            cwd: Some(String::from("src")),
+            files: Some(String::from("*.rs")),
+            ..TaskConfig::from("test -f {staged_files}")
+        };
+        let context = context_with_staged_files(&["src/task.rs"]);
+        assert!(run_task(task, context).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn passes_quoted_files_to_shell() {
+        let task = TaskConfig {
+            files: Some(String::from("*.md")),
+            ..shell_task("for x in {files}; do test -f \"$x\" || exit 1; done")
+        };
+        let context = context_with_staged_files(&["README.md", "CHANGELOG.md"]);
+        let result = run_task(task, context).await;
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    /// Enough staged files to exceed the argument limit several times over.
+    fn many_files() -> Vec<String> {
+        (0..40_000)
+            .map(|i| format!("src/some/deeply/nested/module/file{i:05}.rs"))
+            .collect()
+    }
+
+    /// Number of files each invocation received, as logged by the command.
+    fn read_counts(marker: &std::path::Path) -> Vec<usize> {
+        let counts = std::fs::read_to_string(marker).unwrap();
+        let _ = std::fs::remove_file(marker);
+
+        counts.lines().map(|x| x.parse().unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn batches_files_exceeding_argument_limit() {
+        let marker = temp_path("batch-sequential");
+        let files = many_files();
+        let task = TaskConfig {
+            files: Some(String::from("*.rs")),
+            ..TaskConfig::from(
+                format!(r#"sh -c 'echo $# >> {}' sh {{files}}"#, marker.display()).as_str(),
+            )
+        };
+        let mut context = Arc::into_inner(context()).unwrap();
+        context.staged_files = Some(files.clone());
+
+        run_task(task, Arc::new(context)).await.unwrap();
+
+        let counts = read_counts(&marker);
+        assert!(counts.len() > 1, "{counts:?}");
+        assert_eq!(counts.iter().sum::<usize>(), files.len());
+    }
+
+    #[tokio::test]
+    async fn runs_batches_in_parallel() {
+        let marker = temp_path("batch-parallel");
+        let files = many_files();
+        let task = TaskConfig {
+            parallel_batches: true,
+            ..TaskConfig::from(
+                format!(r#"sh -c 'echo $# >> {}' sh {{files}}"#, marker.display()).as_str(),
+            )
+        };
+        let mut context = Arc::into_inner(context()).unwrap();
+        context.staged_files = Some(files.clone());
+
+        run_task(task, Arc::new(context)).await.unwrap();
+
+        let counts = read_counts(&marker);
+        assert!(counts.len() > 1, "{counts:?}");
+        assert_eq!(counts.iter().sum::<usize>(), files.len());
+    }
+
+    #[tokio::test]
+    async fn limits_parallel_batches_to_jobs() {
+        let lock = temp_path("batch-lock");
+        let task = TaskConfig {
+            parallel_batches: true,
+            // Fails when another batch holds the lock.
+            ..TaskConfig::from(
+                format!(
+                    r#"sh -c 'mkdir {0} || exit 3; sleep 0.1; rmdir {0}' sh {{files}}"#,
+                    lock.display()
+                )
+                .as_str(),
+            )
+        };
+        let mut context = Arc::into_inner(context()).unwrap();
+        context.staged_files = Some(many_files());
+        context.jobs = 1;
+
+        let result = run_task(task, Arc::new(context)).await;
+
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn reports_failed_batches_as_one_result() {
+        let task =
+            TaskConfig::from(r#"sh -c 'case "$*" in *file00000.rs*) exit 3;; esac' sh {files}"#);
+        let mut context = Arc::into_inner(context()).unwrap();
+        context.staged_files = Some(many_files());
+
+        let result = run_task(task, Arc::new(context)).await.unwrap_err();
+
+        // The number of batches depends on the size of the environment.
+        let result = result.to_string();
+        assert!(
+            result.contains("failed with exit code Some(3) in 1 of "),
+            "{result}"
+        );
+        assert!(result.ends_with(" batches"), "{result}");
+    }
+}
+
+#[cfg(all(test, unix))]
+mod fix_tests {
+    use super::*;
+    use crate::tests::utils::temp_path;
+
+    /// A context rooted in a temporary directory holding the staged files.
+    fn context(name: &str, files: &[&str]) -> Arc<HookContext> {
+        let root = temp_path(&format!("fix-{name}"));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+
+        for file in files {
+            std::fs::write(root.join(file), "unformatted\n").unwrap();
+        }
+
+        let mut context = HookContext::new(
+            root.display().to_string(),
+            "pre-commit",
+            vec![],
+            None,
+            LoggingConfig {
+                verbose: false,
+                quiet: true,
+                stream: false,
+            },
+        );
+        context.staged_files = Some(files.iter().map(|x| x.to_string()).collect());
+        Arc::new(context)
+    }
+
+    fn fixes(context: Arc<HookContext>) -> Vec<Fix> {
+        let _ = std::fs::remove_dir_all(&context.root_directory_path);
+        Arc::into_inner(context)
+            .unwrap()
+            .fixes
+            .into_inner()
+            .unwrap()
+    }
+
+    #[test]
+    fn fail_on_fix_implies_fix() {
+        assert!(!is_fixer(&TaskConfig::from("cargo fmt")));
+        assert!(is_fixer(&TaskConfig {
+            fix: true,
+            ..TaskConfig::from("cargo fmt")
+        }));
+        assert!(is_fixer(&TaskConfig {
+            fail_on_fix: true,
+            ..TaskConfig::from("cargo fmt")
+        }));
+    }
+
+    #[tokio::test]
+    async fn records_modified_staged_files() {
+        let task = TaskConfig {
+            name: Some(String::from("fmt")),
+            fix: true,
+            ..TaskConfig::from("sh -c 'echo formatted > b.rs'")
+        };
+        let context = context("record", &["a.rs", "b.rs"]);
+
+        run_task(task, Arc::clone(&context)).await.unwrap();
+
+        assert_eq!(
+            fixes(context),
+            [Fix {
+                name: String::from("fmt"),
+                files: vec![String::from("b.rs")],
+                fail_on_fix: false,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn records_fixes_of_failed_tasks() {
+        let task = TaskConfig {
+            fail_on_fix: true,
+            ..TaskConfig::from("sh -c 'echo formatted > a.rs; exit 1'")
+        };
+        let context = context("failed", &["a.rs"]);
+
+        assert!(run_task(task, Arc::clone(&context)).await.is_err());
+
+        let fixes = fixes(context);
+        assert_eq!(fixes.len(), 1);
+        assert!(fixes[0].fail_on_fix);
+    }
+
+    #[tokio::test]
+    async fn ignores_files_not_matching_glob() {
+        let task = TaskConfig {
+            fix: true,
+            files: Some(String::from("*.toml")),
+            ..TaskConfig::from("sh -c 'echo formatted > a.rs' sh")
+        };
+        let context = context("glob", &["a.rs", "Cargo.toml"]);
+
+        run_task(task, Arc::clone(&context)).await.unwrap();
+
+        assert_eq!(fixes(context), []);
+    }
+
+    #[tokio::test]
+    async fn ignores_modifications_of_other_tasks() {
+        let task = TaskConfig::from("sh -c 'echo formatted > a.rs'");
+        let context = context("check", &["a.rs"]);
+
+        run_task(task, Arc::clone(&context)).await.unwrap();
+
+        assert_eq!(fixes(context), []);
+    }
+}
+
+#[cfg(test)]
+mod process_group_tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    fn is_running(pid: &str) -> bool {
+        // Zombies count as terminated, since the container init may not reap them.
+        std::fs::read_to_string(format!("/proc/{pid}/stat")).is_ok_and(|stat| {
+            !stat
+                .rsplit(')')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .starts_with('Z')
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn kills_spawned_processes_on_timeout() {
+        let pid_file = crate::tests::utils::temp_path("process-group");
+        let task = TaskConfig {
+            timeout: Some(1),
+            ..TaskConfig::from(
+                format!("sh -c 'sleep 30 & echo $! > {}; wait'", pid_file.display()).as_str(),
+            )
+        };
+        let context = Arc::new(HookContext::new(
+            String::from("."),
+            "pre-commit",
+            vec![],
+            None,
+            LoggingConfig::default(),
+        ));
+
+        let result = run_task(task, context).await.unwrap_err();
+        assert!(matches!(result, Error::Timeout { secs: 1, .. }));
+
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let _ = std::fs::remove_file(&pid_file);
+        let pid = pid.trim();
+
+        for _ in 0..50 {
+            if !is_running(pid) {
+                return;
+            }
+
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        panic!("Process {pid} spawned by the timed out task is still running");
+    }
+}
+
+#[cfg(test)]
+mod summarize_failures_tests {
+    use super::*;
+
+    fn failure(name: &str, timed_out: bool) -> TaskFailure {
+        TaskFailure {
+            name: name.to_string(),
+            error: match timed_out {
+                true => Error::Timeout {
+                    name: name.to_string(),
+                    secs: 10,
+                },
+                false => Error::Other(format!("{name} broke")),
+            },
+        }
+    }
+
+    #[test]
+    fn succeeds_without_failures() {
+        assert!(summarize_failures(vec![]).is_ok());
+    }
+
+    #[test]
+    fn returns_single_failure_unchanged() {
+        let result = summarize_failures(vec![failure("clippy", true)]).unwrap_err();
+        assert!(matches!(result, Error::Timeout { .. }));
+        assert_eq!(result.to_string(), "`clippy` timed out after 10s");
+    }
+
+    #[test]
+    fn lists_every_failure() {
+        let result =
+            summarize_failures(vec![failure("clippy", false), failure("test", false)]).unwrap_err();
+        assert_eq!(result.exit_code(), 1);
+
+        insta::assert_snapshot!(result, @"
+        2 tasks failed:
+          - clippy: clippy broke
+          - test: test broke
+        ");
+    }
+}
@@ -24,6 +24,140 @@ pub(crate) mod utils {
             },
         )
     }
+
+    /// Owned copies of `xs`, as commands and git arguments are passed.
+    pub(crate) fn params(xs: &[&str]) -> Vec<String> {
+        xs.iter().map(|x| x.to_string()).collect()
+    }
+
+    /// A path in the temporary directory, unique to `name` and the test
+    /// process.
+    pub(crate) fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("crusty-hooks-{name}-{}", std::process::id()))
+    }
+
+    /// A git repository in a temporary directory, with a committed file `f`,
+    /// removed when dropped.
+    #[cfg(unix)]
+    pub(crate) struct TempRepo(std::path::PathBuf);
+
+    #[cfg(unix)]
+    impl TempRepo {
+        pub(crate) fn new(name: &str) -> Self {
+            let path = temp_path(name);
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            let repo = Self(path);
+
+            repo.git("init -q");
+            repo.write("f", "a\n");
+            repo.git("add f");
+            repo.git("-c user.name=a -c user.email=a@b commit -q -m init");
+
+            repo
+        }
+
+        /// Like [`TempRepo::new`], with the `committed` files committed on
+        /// top of the initial commit, the `staged` files added to the index,
+        /// and then the `unstaged` files written to the working tree only.
+        pub(crate) fn with_changes(
+            name: &str,
+            committed: &[(&str, &str)],
+            staged: &[(&str, &str)],
+            unstaged: &[(&str, &str)],
+        ) -> Self {
+            let repo = Self::new(name);
+
+            for (changes, commit) in [(committed, true), (staged, false)] {
+                if changes.is_empty() {
+                    continue;
+                }
+
+                for (file, contents) in changes {
+                    repo.write(file, contents);
+                    repo.git(&format!("add {file}"));
+                }
+
+                if commit {
+                    repo.git("-c user.name=a -c user.email=a@b commit -q -m changes");
+                }
+            }
+
+            for (file, contents) in unstaged {
+                repo.write(file, contents);
+            }
+
+            repo
+        }
+
+        pub(crate) fn git(&self, args: &str) -> String {
+            nias::get_command_runner()(&format!("git {args}"), self.dir(), false, None)
+                .unwrap()
+                .unwrap_or_default()
+        }
+
+        pub(crate) fn write(&self, file: &str, contents: &str) {
+            std::fs::write(self.0.join(file), contents).unwrap();
+        }
+
+        pub(crate) fn read(&self, file: &str) -> String {
+            std::fs::read_to_string(self.0.join(file)).unwrap_or_default()
+        }
+
+        fn dir(&self) -> Option<&str> {
+            self.0.to_str()
+        }
+
+        /// Runs `hook_name` in the repository with the quiet config `contents`.
+        pub(crate) async fn run_hook(
+            &self,
+            hook_name: &str,
+            contents: &str,
+        ) -> Result<(), crate::Error> {
+            self.run_hook_with(hook_name, contents, super::RunOptions::default())
+                .await
+        }
+
+        /// Like [`TempRepo::run_hook`], with `options`.
+        pub(crate) async fn run_hook_with(
+            &self,
+            hook_name: &str,
+            contents: &str,
+            options: super::RunOptions,
+        ) -> Result<(), crate::Error> {
+            let contents = format!("{contents}\n[logging]\nquiet = true\n");
+            let runner = nias::get_command_runner();
+            let run_command =
+                |cmd: &str,
+                 dir: Option<&str>,
+                 stream_io: bool,
+                 env: Option<&HashMap<String, String>>| {
+                    runner(cmd, dir.or(self.dir()), stream_io, env)
+                };
+            let read_file = |_file_path: &str| Ok(contents.clone());
+            let read_stdin = || panic!("");
+            let file_exists =
+                |path: &str| Ok(std::path::Path::new(path).ends_with(".crusty-hooks.toml"));
+
+            super::run(
+                run_command,
+                file_exists,
+                read_file,
+                read_stdin,
+                hook_name,
+                vec![],
+                options,
+            )
+            .await
+        }
+    }
+
+    #[cfg(unix)]
+    impl Drop for TempRepo {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -33,41 +167,88 @@ mod init_directory_tests {
 
     #[test]
     fn returns_error_when_root_directory_detect_fails() {
-        let exp_err = "Failure determining git repo root directory";
-        let run_command = build_simple_command_runner(Err(Some(String::from(exp_err))));
+        let run_command = build_simple_command_runner(Err(Some(String::from("Not a repository"))));
+        let read_file = |_file_path: &str| panic!("Should not get here");
         let write_file = |_file_path: &str, _contents: &str, _x: bool| {
             panic!("Should not get here");
         };
         let file_exists = |_path: &str| panic!("Should not get here");
-        let result = init(run_command, write_file, file_exists, vec![]);
-        assert_eq!(result, Err(String::from(exp_err)));
+        let result = init(
+            run_command,
+            read_file,
+            write_file,
+            file_exists,
+            None,
+            vec![],
+            vec![],
+        );
+        assert!(matches!(result, Err(Error::NotAGitRepo)));
     }
 
     #[test]
     fn should_return_error_when_hook_creation_fails() {
         let run_command = build_simple_command_runner(Ok(Some(String::from(""))));
-        let write_file = |_file_path: &str, _contents: &str, _x: bool| Err(String::from(""));
-        let file_exists = |_path: &str| panic!("Should not get here");
-        let result = init(run_command, write_file, file_exists, vec![]);
-        assert_eq!(result, Err(String::from("Unable to create git hooks")));
+        let read_file = |_file_path: &str| panic!("Should not get here");
+        let write_file =
+            |_file_path: &str, _contents: &str, _x: bool| Err(String::from("Permission denied"));
+        let file_exists = |_path: &str| Ok(false);
+        let result = init(
+            run_command,
+            read_file,
+            write_file,
+            file_exists,
+            None,
+            vec![],
+            vec![],
+        );
+        let result = result.unwrap_err();
+        assert!(matches!(result, Error::HookWrite { .. }));
+        insta::assert_snapshot!(result, @"Unable to write //applypatch-msg: Permission denied");
     }
 
     #[test]
     fn should_return_error_when_config_creation_fails() {
         let run_command = build_simple_command_runner(Ok(Some(String::from(""))));
+        let read_file = |_file_path: &str| panic!("Should not get here");
         let write_file = |_file_path: &str, _contents: &str, _x: bool| Ok(());
-        let file_exists = |_path: &str| Err(());
-        let result = init(run_command, write_file, file_exists, vec![]);
-        assert_eq!(result, Err(String::from("Unable to create config file")));
+        let file_exists = |path: &str| {
+            if path.ends_with(".toml") {
+                Err(())
+            } else {
+                Ok(false)
+            }
+        };
+        let result = init(
+            run_command,
+            read_file,
+            write_file,
+            file_exists,
+            None,
+            vec![],
+            vec![],
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Unable to create config file"
+        );
     }
 
     #[test]
     fn should_return_ok_on_success() {
         let run_command = build_simple_command_runner(Ok(Some(String::from(""))));
+        let read_file = |_file_path: &str| panic!("Should not get here");
         let write_file = |_file_path: &str, _contents: &str, _x: bool| Ok(());
         let file_exists = |_path: &str| Ok(false);
-        let result = init(run_command, write_file, file_exists, vec![]);
-        assert_eq!(result, Ok(()));
+        let result = init(
+            run_command,
+            read_file,
+            write_file,
+            file_exists,
+            None,
+            vec![],
+            vec![],
+        );
+        assert!(result.is_ok());
     }
 }
 
@@ -89,10 +270,186 @@ mod init_tests {
                 Ok(Some(String::from(".")))
             }
         };
+        let read_file = |_file_path: &str| panic!("Should not get here");
         let write_file = |_file_path: &str, _contents: &str, _x: bool| Ok(());
         let file_exists = |_path: &str| Ok(false);
-        let result = init(run_command, write_file, file_exists, vec![]);
-        assert_eq!(result, Ok(()));
+        let result = init(
+            run_command,
+            read_file,
+            write_file,
+            file_exists,
+            None,
+            vec![],
+            vec![],
+        );
+        assert!(result.is_ok());
+    }
+}
+
+#[cfg(test)]
+mod uninstall_tests {
+    use super::utils::build_simple_command_runner;
+    use super::*;
+    use std::cell::RefCell;
+
+    #[allow(clippy::type_complexity)]
+    fn build_git_runner() -> impl Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>> {
+        |cmd: &str, _: Option<&str>, _: bool, _: Option<&HashMap<String, String>>| match cmd {
+            utils::GIT_REV_PARSE_CMD => Ok(Some(String::from("/repo"))),
+            _ => Ok(Some(String::from(".git/hooks"))),
+        }
+    }
+
+    #[test]
+    fn returns_error_when_root_directory_detect_fails() {
+        let run_command = build_simple_command_runner(Err(Some(String::from("Not a repository"))));
+        let result = uninstall(
+            run_command,
+            |_: &str| panic!("Should not get here"),
+            |_: &str, _: &str, _: bool| panic!("Should not get here"),
+            |_: &str| panic!("Should not get here"),
+            |_: &str| panic!("Should not get here"),
+            false,
+        );
+        assert!(matches!(result, Err(Error::NotAGitRepo)));
+    }
+
+    #[test]
+    fn returns_error_when_hook_removal_fails() {
+        let result = uninstall(
+            build_git_runner(),
+            |_: &str| Err(()),
+            |_: &str, _: &str, _: bool| Ok(()),
+            |_: &str| Ok(true),
+            |_: &str| Ok(()),
+            false,
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Unable to remove git hooks: Fatal error encountered while trying to remove git hook files"
+        );
+    }
+
+    #[test]
+    fn keeps_config_file_by_default() {
+        let removed = RefCell::new(vec![]);
+        let result = uninstall(
+            build_git_runner(),
+            |_: &str| Err(()),
+            |_: &str, _: &str, _: bool| Ok(()),
+            |path: &str| Ok(path.ends_with(".crusty-hooks.toml")),
+            |path: &str| {
+                removed.borrow_mut().push(path.to_string());
+                Ok(())
+            },
+            false,
+        );
+        assert_eq!(result.unwrap(), UninstallSummary::default());
+        assert!(removed.into_inner().is_empty());
+    }
+
+    #[test]
+    fn removes_config_file_when_requested() {
+        let exp_path = "/repo/.crusty-hooks.toml";
+        let result = uninstall(
+            build_git_runner(),
+            |_: &str| Err(()),
+            |_: &str, _: &str, _: bool| Ok(()),
+            |path: &str| Ok(path == exp_path),
+            |path: &str| {
+                assert_eq!(path, exp_path);
+                Ok(())
+            },
+            true,
+        );
+        assert_eq!(
+            result.unwrap(),
+            UninstallSummary {
+                removed: vec![String::from(exp_path)],
+                restored: vec![],
+                unset_hooks_path: None,
+            }
+        );
+    }
+
+    /// Uninstalls from a repository at `/repo` whose crusty-hooks pre-commit
+    /// hook is in `hooks_directory`, with `core.hooksPath` set to
+    /// `hooks_path`, returning the summary and the git commands run.
+    fn uninstall_from(
+        hooks_directory: &str,
+        hooks_path: Option<&str>,
+    ) -> (Result<UninstallSummary, Error>, Vec<String>) {
+        uninstall_from_with(hooks_directory, hooks_path, Ok(None))
+    }
+
+    /// Like [`uninstall_from`], with `unset` as the result of unsetting
+    /// `core.hooksPath`.
+    fn uninstall_from_with(
+        hooks_directory: &str,
+        hooks_path: Option<&str>,
+        unset: Result<Option<String>, Option<String>>,
+    ) -> (Result<UninstallSummary, Error>, Vec<String>) {
+        let commands = RefCell::new(vec![]);
+        let run_command =
+            |cmd: &str, _: Option<&str>, _: bool, _: Option<&HashMap<String, String>>| {
+                commands.borrow_mut().push(cmd.to_string());
+                match cmd {
+                    utils::GIT_REV_PARSE_CMD => Ok(Some(String::from("/repo"))),
+                    "git rev-parse --git-path hooks" => Ok(Some(hooks_directory.to_string())),
+                    "git config --get core.hooksPath" => {
+                        hooks_path.map(|x| Some(x.to_string())).ok_or(None)
+                    }
+                    "git config --unset core.hooksPath" => unset.clone(),
+                    _ => Ok(None),
+                }
+            };
+        let pre_commit = format!("/repo/{hooks_directory}/pre-commit");
+        let result = uninstall(
+            run_command,
+            |_: &str| Ok(crate::hooks::get_hook_file_contents()),
+            |_: &str, _: &str, _: bool| panic!("Should not get here"),
+            |path: &str| Ok(path == pre_commit),
+            |_: &str| Ok(()),
+            false,
+        );
+        (result, commands.into_inner())
+    }
+
+    #[test]
+    fn unsets_hooks_path_of_removed_hooks() {
+        let (result, commands) = uninstall_from(".githooks", Some(".githooks"));
+        assert_eq!(
+            result.unwrap(),
+            UninstallSummary {
+                removed: vec![String::from("/repo/.githooks/pre-commit")],
+                restored: vec![],
+                unset_hooks_path: Some(String::from(".githooks")),
+            }
+        );
+        assert!(commands.contains(&String::from("git config --unset core.hooksPath")));
+    }
+
+    #[test]
+    fn returns_error_when_hooks_path_unset_fails() {
+        let (result, _) = uninstall_from_with(".githooks", Some(".githooks"), Err(None));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Unable to remove git hooks: Failed to unset core.hooksPath .githooks"
+        );
+    }
+
+    #[test]
+    fn keeps_hooks_path_when_unset_or_not_installed_into() {
+        for hooks_path in [None, Some("/elsewhere")] {
+            let (result, commands) = uninstall_from(".git/hooks", hooks_path);
+            assert_eq!(result.unwrap().unset_hooks_path, None);
+            assert!(!commands.contains(&String::from("git config --unset core.hooksPath")));
+        }
     }
 }
 
@@ -106,11 +463,20 @@ mod run_tests {
         let exp_err = "Failure determining git repo root directory";
         let run_command = build_simple_command_runner(Err(Some(String::from(exp_err))));
         let read_file = |_file_path: &str| panic!("");
+        let read_stdin = || panic!("");
         let file_exists = |_path: &str| panic!("");
 
-        let result = run(run_command, file_exists, read_file, "")
-            .await
-            .unwrap_err();
+        let result = run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "",
+            vec![],
+            RunOptions::default(),
+        )
+        .await
+        .unwrap_err();
 
         insta::assert_snapshot!(result, @"Failure determining git repo root directory");
     }
@@ -119,11 +485,20 @@ mod run_tests {
     async fn returns_error_when_config_file_missing() {
         let run_command = build_simple_command_runner(Ok(Some(String::from(""))));
         let read_file = |_file_path: &str| Err(());
+        let read_stdin = || panic!("");
         let file_exists = |_path: &str| Ok(false);
 
-        let result = run(run_command, file_exists, read_file, "")
-            .await
-            .unwrap_err();
+        let result = run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "",
+            vec![],
+            RunOptions::default(),
+        )
+        .await
+        .unwrap_err();
 
         insta::assert_snapshot!(result, @"No config file found");
     }
@@ -132,13 +507,22 @@ mod run_tests {
     async fn returns_error_when_config_contents_unloadable() {
         let run_command = build_simple_command_runner(Ok(Some(String::from(""))));
         let read_file = |_file_path: &str| Err(());
+        let read_stdin = || panic!("");
         let file_exists = |_path: &str| Ok(true);
 
-        let result = run(run_command, file_exists, read_file, "")
-            .await
-            .unwrap_err();
+        let result = run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "",
+            vec![],
+            RunOptions::default(),
+        )
+        .await
+        .unwrap_err();
 
-        insta::assert_snapshot!(result, @"Failed to parse config file");
+        insta::assert_snapshot!(result, @"Failed to read /.crusty-hooks.toml");
     }
 
     #[tokio::test]
@@ -146,14 +530,23 @@ mod run_tests {
         let contents = "abc";
         let run_command = build_simple_command_runner(Ok(Some(String::from(""))));
         let read_file = |_file_path: &str| Ok(String::from(contents));
+        let read_stdin = || panic!("");
         let file_exists = |_path: &str| Ok(true);
 
-        let result = run(run_command, file_exists, read_file, "pre-push")
-            .await
-            .unwrap_err();
+        let result = run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "pre-push",
+            vec![],
+            RunOptions::default(),
+        )
+        .await
+        .unwrap_err();
 
-        insta::assert_snapshot!(result, @r"
-        TOML parse error at line 1, column 4
+        insta::assert_snapshot!(result, @"
+        Invalid config file /.crusty-hooks.toml: TOML parse error at line 1, column 4
           |
         1 | abc
           |    ^
@@ -163,34 +556,1231 @@ mod run_tests {
 
     #[tokio::test]
     async fn returns_err_when_script_fails() {
-        let exp_err = "crashed";
         let contents = r#"[hooks]
-            pre-commit = "cargo test"
+            pre-commit = "ls /does/not/exist"
 
             [logging]
             verbose = false
         "#;
+        let run_command = build_simple_command_runner(Ok(Some(String::from("."))));
+        let read_file = |_file_path: &str| Ok(String::from(contents));
+        let read_stdin = || panic!("");
+        let file_exists = |_path: &str| Ok(true);
+        let result = run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "pre-commit",
+            vec![],
+            RunOptions::default(),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(
+            result
+                .to_string()
+                .starts_with("Command `ls /does/not/exist` failed with exit code")
+        );
+    }
+
+    #[tokio::test]
+    async fn runs_every_documented_hook_form() {
+        let contents = r#"[hooks]
+            pre-commit = "true"
+            pre-push = ["true", "true"]
+            post-commit = [["true"], ["true", "true"]]
+        "#;
+
+        for hook in ["pre-commit", "pre-push", "post-commit"] {
+            let run_command = build_simple_command_runner(Ok(Some(String::from("."))));
+            let read_file = |_file_path: &str| Ok(String::from(contents));
+            let read_stdin = || Ok(String::new());
+            let file_exists = |_path: &str| Ok(true);
+            let result = run(
+                run_command,
+                file_exists,
+                read_file,
+                read_stdin,
+                hook,
+                vec![],
+                RunOptions::default(),
+            )
+            .await;
+
+            assert!(result.is_ok(), "{hook} failed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod git_params_tests {
+    use super::utils::{build_simple_command_runner, params};
+    use super::*;
+
+    #[tokio::test]
+    async fn passes_params_to_commands() {
+        let contents = r#"[hooks]
+            commit-msg = [
+                ["test %rh! = .git/COMMIT_EDITMSG"],
+                ["sh -c 'test \"$CRUSTY_HOOKS_ARG_1\" = .git/COMMIT_EDITMSG'"]
+            ]
+        "#;
+        let run_command = build_simple_command_runner(Ok(Some(String::from("."))));
+        let read_file = |_file_path: &str| Ok(String::from(contents));
+        let read_stdin = || panic!("");
+        let file_exists = |_path: &str| Ok(true);
+
+        let result = run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "commit-msg",
+            params(&[".git/COMMIT_EDITMSG"]),
+            RunOptions::default(),
+        )
+        .await;
+
+        assert!(result.is_ok());
+    }
+}
+
+#[cfg(test)]
+mod stdin_tests {
+    use super::utils::build_simple_command_runner;
+    use super::*;
+
+    const PUSH_LINE: &str = "refs/heads/main 1111111111111111111111111111111111111111 refs/heads/main 2222222222222222222222222222222222222222\n";
+
+    #[tokio::test]
+    async fn replays_stdin_to_every_task() {
+        let contents = r#"[hooks]
+            pre-push = [
+                ["grep -q refs/heads/main", "grep -q 1111111111111111111111111111111111111111"],
+                ["grep -q 2222222222222222222222222222222222222222"]
+            ]
+        "#;
+        let run_command = build_simple_command_runner(Ok(Some(String::from("."))));
+        let read_file = |_file_path: &str| Ok(String::from(contents));
+        let read_stdin = || Ok(String::from(PUSH_LINE));
+        let file_exists = |_path: &str| Ok(true);
+
+        let result = run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "pre-push",
+            vec![],
+            RunOptions::default(),
+        )
+        .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn does_not_read_stdin_for_other_hooks() {
+        let contents = r#"[hooks]
+            pre-commit = [["true"]]
+        "#;
+        let run_command = build_simple_command_runner(Ok(Some(String::from("."))));
+        let read_file = |_file_path: &str| Ok(String::from(contents));
+        let read_stdin = || panic!("Should not read stdin");
+        let file_exists = |_path: &str| Ok(true);
+
+        let result = run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "pre-commit",
+            vec![],
+            RunOptions::default(),
+        )
+        .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn does_not_read_stdin_for_chosen_files() {
+        let contents = r#"[hooks]
+            pre-push = [["true"]]
+        "#;
+        let run_command = build_simple_command_runner(Ok(Some(String::from("."))));
+        let read_file = |_file_path: &str| Ok(String::from(contents));
+        let read_stdin = || panic!("Should not read stdin");
+        let file_exists = |_path: &str| Ok(true);
+        let options = RunOptions {
+            files: Some(FileSelection::Paths(vec![String::from("a")])),
+            ..RunOptions::default()
+        };
+
+        let result = run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "pre-push",
+            vec![],
+            options,
+        )
+        .await;
+
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn returns_error_when_stdin_unreadable() {
+        let contents = r#"[hooks]
+            pre-push = [["true"]]
+        "#;
+        let run_command = build_simple_command_runner(Ok(Some(String::from("."))));
+        let read_file = |_file_path: &str| Ok(String::from(contents));
+        let read_stdin = || Err(());
+        let file_exists = |_path: &str| Ok(true);
+
+        let result = run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "pre-push",
+            vec![],
+            RunOptions::default(),
+        )
+        .await
+        .unwrap_err();
+
+        insta::assert_snapshot!(result, @"Failed to read hook stdin");
+    }
+
+    #[tokio::test]
+    async fn checks_current_branch_when_stdin_is_blank() {
+        let contents = r#"[[hooks.pre-push]]
+            run = "sh -c 'exit 3'"
+            when = { branch = "*" }
+        "#;
+        let run_command = build_simple_command_runner(Ok(Some(String::from("."))));
+        let read_file = |_file_path: &str| Ok(String::from(contents));
+        let read_stdin = || Ok(String::from(" \n"));
+        let file_exists = |_path: &str| Ok(true);
+
+        let result = run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "pre-push",
+            vec![],
+            RunOptions::default(),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(
+            result.to_string().ends_with("exit code Some(3)"),
+            "{result}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod logging_tests {
+    use super::utils::build_simple_command_runner;
+    use super::*;
+    use crate::output::{Stream, capture};
+
+    /// Writes `out` to stdout and `err` to stderr.
+    const COMMAND: &str = "sh -c 'echo out; echo err >&2'";
+
+    /// Runs `command` with the `logging` settings, returning the result and
+    /// the lines of task output written, in order.
+    async fn run_with_logging(
+        logging: &str,
+        command: &str,
+    ) -> (Result<(), Error>, Vec<(Stream, String)>) {
+        let contents = format!(
+            r#"[hooks]
+            pre-commit = [["{command}"]]
+
+            [logging]
+            {logging}
+        "#
+        );
+        let run_command = build_simple_command_runner(Ok(Some(String::from("."))));
+        let read_file = |_file_path: &str| Ok(contents.clone());
+        let read_stdin = || panic!("");
+        let file_exists = |_path: &str| Ok(true);
+
+        capture::start();
+
+        let result = run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "pre-commit",
+            vec![],
+            RunOptions::default(),
+        )
+        .await;
+
+        (result, capture::take())
+    }
+
+    /// Each output line of [`COMMAND`], once, prefixed with the task name.
+    fn command_lines() -> Vec<(Stream, String)> {
+        vec![
+            (Stream::Stdout, format!("[{COMMAND}] out\n")),
+            (Stream::Stderr, format!("[{COMMAND}] err\n")),
+        ]
+    }
+
+    #[tokio::test]
+    async fn streams_successful_output() {
+        let (result, mut lines) = run_with_logging("stream = true", COMMAND).await;
+        assert!(result.is_ok(), "{result:?}");
+        // Stdout and stderr are forwarded concurrently.
+        lines.sort_by_key(|(stream, _)| *stream == Stream::Stderr);
+        assert_eq!(lines, command_lines());
+    }
+
+    #[tokio::test]
+    async fn streams_output_once_when_verbose() {
+        let (result, mut lines) = run_with_logging("stream = true\nverbose = true", COMMAND).await;
+        assert!(result.is_ok(), "{result:?}");
+        lines.sort_by_key(|(stream, _)| *stream == Stream::Stderr);
+        assert_eq!(lines, command_lines());
+    }
+
+    #[tokio::test]
+    async fn streams_failing_output() {
+        let (result, lines) = run_with_logging("stream = true", "ls /does/not/exist").await;
+        let Err(Error::TaskFailed {
+            command, stderr, ..
+        }) = result
+        else {
+            panic!("{result:?}");
+        };
+        assert_eq!(command, "ls /does/not/exist");
+        assert!(stderr.contains("/does/not/exist"), "{stderr}");
+        assert_eq!(lines, [(Stream::Stderr, format!("[{command}] {stderr}"))]);
+    }
+
+    #[tokio::test]
+    async fn prints_output_when_verbose() {
+        let (result, lines) = run_with_logging("verbose = true", COMMAND).await;
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(lines, command_lines());
+    }
+
+    #[tokio::test]
+    async fn prints_nothing_by_default() {
+        let (result, lines) = run_with_logging("", COMMAND).await;
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(lines, []);
+    }
+
+    #[tokio::test]
+    async fn reports_failure_when_quiet() {
+        let (result, _) = run_with_logging("quiet = true", "false").await;
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .starts_with("Command `false` failed")
+        );
+    }
+}
+
+#[cfg(test)]
+mod timeout_tests {
+    use super::utils::build_simple_command_runner;
+    use super::*;
+
+    #[tokio::test]
+    async fn times_out_whole_hook() {
+        let contents = r#"[hooks.pre-commit]
+            timeout = 1
+            tasks = ["sleep 0.6", "sleep 0.6"]
+
+            [logging]
+            quiet = true
+        "#;
+        let run_command = build_simple_command_runner(Ok(Some(String::from("."))));
+        let read_file = |_file_path: &str| Ok(String::from(contents));
+        let read_stdin = || panic!("");
+        let file_exists = |_path: &str| Ok(true);
+
+        let result = run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "pre-commit",
+            vec![],
+            RunOptions::default(),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(matches!(result, Error::Timeout { secs: 1, .. }));
+        assert_eq!(result.exit_code(), TIMEOUT_ERROR_CODE);
+        assert_eq!(result.to_string(), "`pre-commit` timed out after 1s");
+    }
+
+    #[tokio::test]
+    async fn applies_task_timeout_to_tasks() {
+        let contents = r#"[hooks.pre-commit]
+            task_timeout = 1
+            tasks = [["sleep 30"], ["true"]]
+
+            [logging]
+            quiet = true
+        "#;
+        let run_command = build_simple_command_runner(Ok(Some(String::from("."))));
+        let read_file = |_file_path: &str| Ok(String::from(contents));
+        let read_stdin = || panic!("");
+        let file_exists = |_path: &str| Ok(true);
+
+        let result = run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "pre-commit",
+            vec![],
+            RunOptions::default(),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(matches!(result, Error::Timeout { secs: 1, .. }));
+        assert_eq!(result.exit_code(), TIMEOUT_ERROR_CODE);
+        assert_eq!(result.to_string(), "`sleep 30` timed out after 1s");
+    }
+
+    #[tokio::test]
+    async fn own_timeout_overrides_task_timeout() {
+        let contents = r#"[hooks.pre-commit]
+            task_timeout = 1
+            tasks = [{ run = "sleep 2", timeout = 10 }]
+
+            [logging]
+            quiet = true
+        "#;
+        let run_command = build_simple_command_runner(Ok(Some(String::from("."))));
+        let read_file = |_file_path: &str| Ok(String::from(contents));
+        let read_stdin = || panic!("");
+        let file_exists = |_path: &str| Ok(true);
+
+        let result = run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "pre-commit",
+            vec![],
+            RunOptions::default(),
+        )
+        .await;
+
+        assert!(result.is_ok());
+    }
+}
+
+#[cfg(test)]
+mod fail_fast_tests {
+    use super::utils::build_simple_command_runner;
+    use super::*;
+
+    async fn run_pre_commit(contents: String) -> Result<(), crate::Error> {
+        let run_command = build_simple_command_runner(Ok(Some(String::from("."))));
+        let read_file = |_file_path: &str| Ok(contents.clone());
+        let read_stdin = || panic!("");
+        let file_exists = |_path: &str| Ok(true);
+
+        run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "pre-commit",
+            vec![],
+            RunOptions::default(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn reports_every_failure_when_not_failing_fast() {
+        let marker = utils::temp_path("run-all");
+        let contents = format!(
+            r#"jobs = 3
+
+            [hooks.pre-commit]
+            fail_fast = false
+            tasks = [
+                [{{ name = "clippy", run = "false" }}],
+                [{{ name = "test", run = "sh -c 'sleep 1; touch {}; exit 3'" }}],
+                ["true"],
+            ]
+
+            [logging]
+            quiet = true
+        "#,
+            marker.display()
+        );
+
+        let result = run_pre_commit(contents).await.unwrap_err().to_string();
+        let finished = std::fs::remove_file(&marker).is_ok();
+
+        assert!(finished);
+        assert!(result.starts_with("2 tasks failed:"), "{result}");
+        assert!(result.contains("\n  - clippy: Command `false` failed"));
+        assert!(result.contains("\n  - test: Command `sh -c"));
+    }
+
+    #[tokio::test]
+    async fn cancels_remaining_tasks_when_failing_fast() {
+        let marker = utils::temp_path("fail-fast");
+        let contents = format!(
+            r#"jobs = 2
+
+            [hooks.pre-commit]
+            tasks = [
+                ["false"],
+                ["sh -c 'sleep 1; touch {}'"],
+            ]
+
+            [logging]
+            quiet = true
+        "#,
+            marker.display()
+        );
+
+        let result = run_pre_commit(contents).await.unwrap_err().to_string();
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        let finished = std::fs::remove_file(&marker).is_ok();
+
+        assert!(result.starts_with("Command `false` failed"), "{result}");
+        assert!(!finished, "cancelled task kept running");
+    }
+}
+
+#[cfg(test)]
+mod skip_tests {
+    use super::utils::build_simple_command_runner;
+    use super::*;
+
+    async fn run_pre_commit(contents: &str, skip: &[&str]) -> Result<(), String> {
+        let run_command = build_simple_command_runner(Ok(Some(String::from("."))));
+        let contents = format!("{contents}\n[logging]\nquiet = true\n");
+        let read_file = |_file_path: &str| Ok(contents.clone());
+        let read_stdin = || panic!("");
+        let file_exists = |_path: &str| Ok(true);
+        let options = RunOptions {
+            skip: skip.iter().map(|x| x.to_string()).collect(),
+            ..RunOptions::default()
+        };
+
+        run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "pre-commit",
+            vec![],
+            options,
+        )
+        .await
+        .map_err(|e| e.to_string())
+    }
+
+    #[tokio::test]
+    async fn skips_named_tasks() {
+        let contents = r#"[hooks.pre-commit]
+            fail_fast = false
+            tasks = [
+                { name = "clippy", run = "false" },
+                { name = "test", run = "false" },
+                "true",
+            ]
+        "#;
+
+        assert!(run_pre_commit(contents, &[]).await.is_err());
+        assert_eq!(run_pre_commit(contents, &["clippy", "test"]).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn skips_hook() {
+        let contents = r#"[hooks]
+            pre-commit = "false"
+        "#;
+
+        assert_eq!(run_pre_commit(contents, &["pre-commit"]).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn runs_dependents_of_skipped_tasks() {
+        let contents = r#"[[hooks.pre-commit]]
+            name = "build"
+            run = "false"
+
+            [[hooks.pre-commit]]
+            name = "clippy"
+            run = "sh -c 'exit 3'"
+            depends_on = ["build"]
+        "#;
+
+        let result = run_pre_commit(contents, &["build"]).await.unwrap_err();
+
+        assert!(result.ends_with("exit code Some(3)"), "{result}");
+    }
+}
+
+#[cfg(all(test, unix))]
+mod condition_tests {
+    use super::utils::TempRepo;
+
+    #[tokio::test]
+    async fn skips_tasks_whose_conditions_do_not_hold() {
+        let repo = TempRepo::new("conditions");
+        repo.git("checkout -q -b feature");
+        repo.write("f", "b\n");
+        repo.git("add f");
+
+        let result = repo
+            .run_hook(
+                "pre-commit",
+                r#"[[hooks.pre-commit]]
+                run = "false"
+                when = { branch = "release/*" }
+
+                [[hooks.pre-commit]]
+                run = "false"
+                when = { changed = ["Cargo.lock"] }
+
+                [[hooks.pre-commit]]
+                run = "sh -c 'exit 3'"
+                when = { branch = "feat*", changed = ["f"] }
+            "#,
+            )
+            .await
+            .unwrap_err();
+
+        assert!(
+            result.to_string().ends_with("exit code Some(3)"),
+            "{result}"
+        );
+    }
+}
+
+#[cfg(all(test, unix))]
+mod legacy_hook_tests {
+    use super::utils::build_simple_command_runner;
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A legacy hook and a log that it and the configured task append to.
+    struct Fixture {
+        hook: PathBuf,
+        log: PathBuf,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let prefix = format!("crusty-hooks-legacy-{name}-{}", std::process::id());
+            let hook = std::env::temp_dir().join(format!("{prefix}.crusty-backup"));
+            let log = std::env::temp_dir().join(format!("{prefix}.log"));
+
+            std::fs::write(
+                &hook,
+                format!("#!/bin/sh\necho \"legacy $*\" >> {}\n", log.display()),
+            )
+            .unwrap();
+            std::fs::set_permissions(&hook, std::os::unix::fs::PermissionsExt::from_mode(0o755))
+                .unwrap();
+
+            Self { hook, log }
+        }
+
+        fn options(&self) -> RunOptions {
+            RunOptions {
+                legacy_hook: Some(self.hook.display().to_string()),
+                ..RunOptions::default()
+            }
+        }
+
+        fn config(&self, legacy_hooks: &str) -> String {
+            format!(
+                r#"legacy_hooks = "{legacy_hooks}"
+
+                [hooks]
+                pre-commit = "sh -c 'echo task >> {}'"
+
+                [logging]
+                quiet = true
+            "#,
+                self.log.display()
+            )
+        }
+
+        fn log(&self) -> String {
+            std::fs::read_to_string(&self.log).unwrap_or_default()
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.hook);
+            let _ = std::fs::remove_file(&self.log);
+        }
+    }
+
+    async fn run_with_legacy_hook(
+        contents: Option<String>,
+        options: RunOptions,
+        hook_name: &str,
+    ) -> Result<(), crate::Error> {
+        let run_command = build_simple_command_runner(Ok(Some(String::from("."))));
+        let file_exists = |_path: &str| Ok(contents.is_some());
+        let read_file = |_file_path: &str| contents.clone().ok_or(());
+        let read_stdin = || panic!("");
+
+        run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            hook_name,
+            vec![String::from(".git/COMMIT_EDITMSG")],
+            options,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn runs_legacy_hook_before_tasks() {
+        let fixture = Fixture::new("before");
+
+        run_with_legacy_hook(
+            Some(fixture.config("before")),
+            fixture.options(),
+            "pre-commit",
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(fixture.log(), "legacy .git/COMMIT_EDITMSG\ntask\n");
+    }
+
+    #[tokio::test]
+    async fn runs_legacy_hook_after_tasks() {
+        let fixture = Fixture::new("after");
+
+        run_with_legacy_hook(
+            Some(fixture.config("after")),
+            fixture.options(),
+            "pre-commit",
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(fixture.log(), "task\nlegacy .git/COMMIT_EDITMSG\n");
+    }
+
+    #[tokio::test]
+    async fn skips_legacy_hook() {
+        let fixture = Fixture::new("skip");
+
+        run_with_legacy_hook(
+            Some(fixture.config("skip")),
+            fixture.options(),
+            "pre-commit",
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(fixture.log(), "task\n");
+    }
+
+    #[tokio::test]
+    async fn runs_legacy_hook_of_unconfigured_hook() {
+        let fixture = Fixture::new("unconfigured");
+
+        run_with_legacy_hook(
+            Some(fixture.config("before")),
+            fixture.options(),
+            "commit-msg",
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(fixture.log(), "legacy .git/COMMIT_EDITMSG\n");
+    }
+
+    #[tokio::test]
+    async fn runs_legacy_hook_without_config_file() {
+        let fixture = Fixture::new("no-config");
+
+        let result = run_with_legacy_hook(None, fixture.options(), "pre-commit")
+            .await
+            .unwrap_err();
+
+        insta::assert_snapshot!(result, @"No config file found");
+        assert_eq!(fixture.log(), "legacy .git/COMMIT_EDITMSG\n");
+    }
+}
+
+#[cfg(test)]
+mod staged_files_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn passes_matching_staged_files() {
+        let marker = utils::temp_path("staged");
+        let contents = format!(
+            r#"[hooks.pre-commit]
+            tasks = [
+                {{ run = "sh -c 'echo \"$*\" > {}' sh", files = "*.rs" }},
+                {{ run = "false", files = "*.toml" }},
+            ]
+
+            [logging]
+            quiet = true
+        "#,
+            marker.display()
+        );
         let run_command = |cmd: &str,
                            _dir: Option<&str>,
                            _stream_io: bool,
                            _env: Option<&HashMap<String, String>>| {
-            if cmd == "cargo test" {
-                return Err(Some(String::from(exp_err)));
+            match cmd {
+                utils::GIT_REV_PARSE_CMD => Ok(Some(String::from("."))),
+                _ => Ok(Some(String::from("src/lib.rs\0README.md\0build.rs\0"))),
             }
-            Ok(Some(String::from("")))
         };
-        let read_file = |_file_path: &str| Ok(String::from(contents));
+        let read_file = |_file_path: &str| Ok(contents.clone());
+        let read_stdin = || panic!("");
         let file_exists = |_path: &str| Ok(true);
-        let result = run(run_command, file_exists, read_file, "pre-commit")
+
+        let result = run(
+            run_command,
+            file_exists,
+            read_file,
+            read_stdin,
+            "pre-commit",
+            vec![],
+            RunOptions::default(),
+        )
+        .await;
+        let files = std::fs::read_to_string(&marker);
+        let _ = std::fs::remove_file(&marker);
+
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(files.unwrap(), "src/lib.rs build.rs\n");
+    }
+}
+
+#[cfg(all(test, unix))]
+mod stash_unstaged_tests {
+    use super::utils::TempRepo;
+
+    /// A repository with a staged change, an unstaged change on top of it and
+    /// an untracked file.
+    fn repo(name: &str) -> TempRepo {
+        TempRepo::with_changes(
+            &format!("stash-{name}"),
+            &[],
+            &[("f", "staged\n")],
+            &[("f", "unstaged\n"), ("untracked", "untracked\n")],
+        )
+    }
+
+    async fn run_pre_commit(repo: &TempRepo, tasks: &str) -> Result<(), crate::Error> {
+        repo.run_hook(
+            "pre-commit",
+            &format!(
+                r#"[hooks.pre-commit]
+                stash_unstaged = true
+                tasks = {tasks}
+            "#
+            ),
+        )
+        .await
+    }
+
+    fn assert_restored(repo: &TempRepo) {
+        assert_eq!(repo.read("f"), "unstaged\n");
+        assert_eq!(repo.read("untracked"), "untracked\n");
+        assert_eq!(repo.git("diff --cached --name-only"), "f");
+        assert_eq!(repo.git("stash list"), "");
+    }
+
+    #[tokio::test]
+    async fn runs_tasks_against_index() {
+        let repo = repo("index");
+
+        let result = run_pre_commit(
+            &repo,
+            r#""sh -c 'grep -q staged f && ! test -e untracked'""#,
+        )
+        .await;
+
+        assert!(result.is_ok(), "{result:?}");
+        assert_restored(&repo);
+    }
+
+    #[tokio::test]
+    async fn restores_changes_when_task_fails() {
+        let repo = repo("fail");
+
+        let result = run_pre_commit(&repo, r#""false""#).await;
+
+        assert!(result.is_err());
+        assert_restored(&repo);
+    }
+
+    #[tokio::test]
+    async fn restores_changes_when_task_times_out() {
+        let repo = repo("timeout");
+
+        let result = run_pre_commit(&repo, r#"[{ run = "sleep 30", timeout = 1 }]"#)
             .await
             .unwrap_err();
 
-        insta::assert_snapshot!(result, @r#"
-        TOML parse error at line 2, column 26
-          |
-        2 |             pre-commit = "cargo test"
-          |                          ^^^^^^^^^^^^
-        invalid type: string "cargo test", expected a sequence
-        "#);
+        assert!(result.to_string().contains("timed out"), "{result}");
+        assert_restored(&repo);
+    }
+
+    #[tokio::test]
+    async fn discards_unstaged_edits_of_tasks() {
+        let repo = repo("edits");
+
+        let result = run_pre_commit(&repo, r#""sh -c 'echo edited > f'""#).await;
+
+        assert!(result.is_ok(), "{result:?}");
+        assert_restored(&repo);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod fix_tests {
+    use super::utils::TempRepo;
+
+    /// A repository with a staged change to `f`, and `g` committed but not
+    /// staged.
+    fn repo(name: &str) -> TempRepo {
+        TempRepo::with_changes(
+            &format!("fix-{name}"),
+            &[("g", "g\n")],
+            &[("f", "staged\n")],
+            &[],
+        )
+    }
+
+    /// A fixer appending `fixed` to every file it is passed.
+    const FIXER: &str = r#"run = """sh -c 'for x; do echo fixed >> $x; done' sh {files}""""#;
+
+    #[tokio::test]
+    async fn stages_fixed_files() {
+        let repo = repo("stage");
+
+        let result = repo
+            .run_hook(
+                "pre-commit",
+                &format!("[[hooks.pre-commit]]\n{FIXER}\nfix = true"),
+            )
+            .await;
+
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(repo.git("show :f"), "staged\nfixed");
+        assert_eq!(repo.git("diff --name-only"), "");
+        assert_eq!(repo.read("g"), "g\n");
+    }
+
+    #[tokio::test]
+    async fn rejects_hook_on_fail_on_fix() {
+        let repo = repo("fail");
+
+        let result = repo
+            .run_hook(
+                "pre-commit",
+                &format!("[[hooks.pre-commit]]\nname = \"fixer\"\n{FIXER}\nfail_on_fix = true"),
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            result.to_string(),
+            "fixer fixed staged files. Review the staged fixes and commit again"
+        );
+        assert_eq!(repo.git("show :f"), "staged\nfixed");
+    }
+
+    #[tokio::test]
+    async fn passes_when_nothing_fixed() {
+        let repo = repo("clean");
+
+        let result = repo
+            .run_hook(
+                "pre-commit",
+                "[[hooks.pre-commit]]\nrun = \"true\"\nfail_on_fix = true",
+            )
+            .await;
+
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn does_not_stage_partly_staged_files() {
+        let repo = repo("partial");
+        repo.write("f", "staged\nunstaged\n");
+
+        let result = repo
+            .run_hook(
+                "pre-commit",
+                &format!("[[hooks.pre-commit]]\n{FIXER}\nfix = true"),
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            result.to_string(),
+            "Fixes to f were not staged, because the files have unstaged changes. \
+             Stage the fixes and commit again, or set `stash_unstaged = true`"
+        );
+        assert_eq!(repo.git("show :f"), "staged");
+    }
+
+    #[tokio::test]
+    async fn stages_fixes_of_partly_staged_files_when_stashed() {
+        let repo = repo("stash");
+        // Far enough from the fix for the unstaged change to apply on top.
+        let staged = "1\n2\n3\n4\n5\n6\n7\n8\n";
+        repo.write("f", staged);
+        repo.git("add f");
+        repo.write("f", &format!("unstaged\n{staged}"));
+
+        let result = repo
+            .run_hook(
+                "pre-commit",
+                &format!("[hooks.pre-commit]\nstash_unstaged = true\n[[hooks.pre-commit.tasks]]\n{FIXER}\nfix = true"),
+            )
+            .await;
+
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(repo.git("show :f"), format!("{staged}fixed"));
+        assert_eq!(repo.read("f"), format!("unstaged\n{staged}fixed\n"));
+    }
+}
+
+#[cfg(all(test, unix))]
+mod cache_tests {
+    use super::utils::TempRepo;
+    use super::*;
+
+    /// A cached task counting its runs in `runs`, which git ignores.
+    const CONFIG: &str = r#"[[hooks.pre-commit]]
+        run = "sh -c 'echo run >> .git/runs'"
+        cache = true
+    "#;
+
+    fn runs(repo: &TempRepo) -> usize {
+        repo.read(".git/runs").lines().count()
+    }
+
+    #[tokio::test]
+    async fn skips_task_that_passed_on_same_tree() {
+        let repo = TempRepo::new("cache-hit");
+
+        assert!(repo.run_hook("pre-commit", CONFIG).await.is_ok());
+        assert!(repo.run_hook("pre-commit", CONFIG).await.is_ok());
+        assert_eq!(runs(&repo), 1);
+
+        // Untracked files are part of the tree too.
+        repo.write("g", "g\n");
+        assert!(repo.run_hook("pre-commit", CONFIG).await.is_ok());
+        assert_eq!(runs(&repo), 2);
+    }
+
+    #[tokio::test]
+    async fn runs_task_with_no_cache() {
+        let repo = TempRepo::new("cache-disabled");
+        let options = || RunOptions {
+            no_cache: true,
+            ..RunOptions::default()
+        };
+
+        assert!(repo.run_hook("pre-commit", CONFIG).await.is_ok());
+        assert!(
+            repo.run_hook_with("pre-commit", CONFIG, options())
+                .await
+                .is_ok()
+        );
+        assert_eq!(runs(&repo), 2);
+
+        // The result of the uncached run is still recorded.
+        assert!(repo.run_hook("pre-commit", CONFIG).await.is_ok());
+        assert_eq!(runs(&repo), 2);
+    }
+
+    #[tokio::test]
+    async fn does_not_cache_failures() {
+        let repo = TempRepo::new("cache-failure");
+        let config = r#"[[hooks.pre-commit]]
+            run = "sh -c 'echo run >> .git/runs; false'"
+            cache = true
+        "#;
+
+        assert!(repo.run_hook("pre-commit", config).await.is_err());
+        assert!(repo.run_hook("pre-commit", config).await.is_err());
+        assert_eq!(runs(&repo), 2);
+    }
+
+    #[tokio::test]
+    async fn only_reruns_when_inputs_change() {
+        let repo = TempRepo::new("cache-inputs");
+        let config = r#"[[hooks.pre-commit]]
+            run = "sh -c 'echo run >> .git/runs'"
+            inputs = ["*.rs"]
+        "#;
+
+        assert!(repo.run_hook("pre-commit", config).await.is_ok());
+        repo.write("f", "b\n");
+        assert!(repo.run_hook("pre-commit", config).await.is_ok());
+        assert_eq!(runs(&repo), 1);
+
+        repo.write("lib.rs", "");
+        assert!(repo.run_hook("pre-commit", config).await.is_ok());
+        assert_eq!(runs(&repo), 2);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod manual_run_tests {
+    use super::utils::TempRepo;
+    use super::*;
+
+    /// A repository with `f` and `g` committed on top of the initial commit,
+    /// and `h` staged.
+    fn repo(name: &str) -> TempRepo {
+        TempRepo::with_changes(
+            &format!("manual-{name}"),
+            &[("f", "b\n"), ("g", "g\n")],
+            &[("h", "h\n")],
+            &[],
+        )
+    }
+
+    /// A task writing the files it is passed to `out`.
+    const LIST_FILES: &str = r#"[[hooks.pre-commit]]
+        name = "list"
+        run = """sh -c 'echo "$*" > out' sh {files}"""
+    "#;
+
+    async fn run_on(repo: &TempRepo, files: FileSelection) -> String {
+        let options = RunOptions {
+            files: Some(files),
+            ..RunOptions::default()
+        };
+        let result = repo.run_hook_with("pre-commit", LIST_FILES, options).await;
+
+        assert!(result.is_ok(), "{result:?}");
+
+        repo.read("out")
+    }
+
+    #[tokio::test]
+    async fn runs_on_staged_files_by_default() {
+        let repo = repo("staged");
+
+        assert!(repo.run_hook("pre-commit", LIST_FILES).await.is_ok());
+        assert_eq!(repo.read("out"), "h\n");
+    }
+
+    #[tokio::test]
+    async fn runs_on_chosen_files() {
+        let repo = repo("files");
+
+        assert_eq!(run_on(&repo, FileSelection::All).await, "f g h\n");
+        assert_eq!(
+            run_on(&repo, FileSelection::Paths(vec![String::from("./g")])).await,
+            "g\n"
+        );
+        assert_eq!(
+            run_on(
+                &repo,
+                FileSelection::Refs {
+                    from: String::from("HEAD~1"),
+                    to: String::from("HEAD"),
+                }
+            )
+            .await,
+            "f g\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn leaves_fixes_unstaged() {
+        let repo = repo("fix");
+        let contents = r#"[[hooks.pre-commit]]
+            name = "fixer"
+            run = """sh -c 'for x; do echo fixed >> $x; done' sh {files}"""
+            fail_on_fix = true
+        "#;
+        let options = RunOptions {
+            files: Some(FileSelection::Paths(vec![String::from("g")])),
+            ..RunOptions::default()
+        };
+
+        let result = repo
+            .run_hook_with("pre-commit", contents, options)
+            .await
+            .unwrap_err();
+
+        assert_eq!(result.to_string(), "fixer fixed files. Review the fixes");
+        assert_eq!(repo.read("g"), "g\nfixed\n");
+        assert_eq!(repo.git("diff --cached --name-only"), "h");
+    }
+
+    #[tokio::test]
+    async fn runs_only_chosen_tasks() {
+        let repo = repo("tasks");
+        let contents = r#"[[hooks.pre-commit]]
+            name = "build"
+            run = "false"
+
+            [[hooks.pre-commit]]
+            name = "clippy"
+            run = "sh -c 'exit 3'"
+            depends_on = ["build"]
+
+            [[hooks.pre-commit]]
+            name = "test"
+            run = "false"
+        "#;
+        let run_tasks = |tasks: &[&str]| {
+            let options = RunOptions {
+                tasks: tasks.iter().map(|x| x.to_string()).collect(),
+                ..RunOptions::default()
+            };
+
+            repo.run_hook_with("pre-commit", contents, options)
+        };
+
+        let result = run_tasks(&["clippy"]).await.unwrap_err();
+        assert!(
+            result.to_string().ends_with("exit code Some(3)"),
+            "{result}"
+        );
+
+        let result = run_tasks(&["lint"]).await.unwrap_err();
+        assert_eq!(result.to_string(), "pre-commit has no task named `lint`");
     }
 }
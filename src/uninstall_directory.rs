@@ -0,0 +1,50 @@
+use crate::{Error, config, git, hooks::UninstallSummary};
+use std::collections::HashMap;
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn uninstall_directory<F, G, H, I, J>(
+    run_command: F,
+    read_file: G,
+    write_file: H,
+    file_exists: I,
+    remove_file: J,
+    target_directory: Option<&str>,
+    remove_config: bool,
+) -> Result<UninstallSummary, Error>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+    G: Fn(&str) -> Result<String, ()>,
+    H: Fn(&str, &str, bool) -> Result<(), String>,
+    I: Fn(&str) -> Result<bool, ()>,
+    J: Fn(&str) -> Result<(), String>,
+{
+    let root_directory_path = match git::get_root_directory_path(&run_command, target_directory) {
+        Ok(Some(path)) => path,
+        _ => return Err(Error::NotAGitRepo),
+    };
+
+    let mut summary = git::remove_hooks(
+        &run_command,
+        &read_file,
+        &write_file,
+        &file_exists,
+        &remove_file,
+        &root_directory_path,
+    )
+    .map_err(|e| Error::Other(format!("Unable to remove git hooks: {e}")))?;
+
+    if remove_config {
+        match config::remove_config_file(&remove_file, &file_exists, &root_directory_path) {
+            Ok(Some(path)) => summary.removed.push(path),
+            Ok(None) => {}
+            Err(_) => return Err(Error::Other(String::from("Unable to remove config file"))),
+        }
+    }
+
+    Ok(summary)
+}
@@ -0,0 +1,126 @@
+use crate::{ConfigFile, Error, GIT_PARAMS_TOKEN, HOOK_NAMES, Shell, config, git, task};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Keys of the config file, which unlike those of the tables in it are not
+/// rejected when unknown.
+const CONFIG_FILE_KEYS: [&str; 4] = ["legacy_hooks", "jobs", "logging", "hooks"];
+
+/// A config file that parsed, with what looks wrong about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Validation {
+    pub path: String,
+    pub warnings: Vec<String>,
+}
+
+/// Parses the config `contents`, returning what looks wrong about it, or the
+/// parse error, which displays its line, column and a snippet pointing at it.
+///
+/// `program_exists` tells whether the program of a command, or the shell it
+/// runs in, can be found from the working directory of its task.
+pub fn validate_config<F>(contents: &str, program_exists: F) -> Result<Vec<String>, toml::de::Error>
+where
+    F: Fn(&str, Option<&str>) -> bool,
+{
+    let config = ConfigFile::try_from_str(contents)?;
+    let mut warnings = vec![];
+
+    if let Ok(table) = contents.parse::<toml::Table>() {
+        for key in table.keys() {
+            if !CONFIG_FILE_KEYS.contains(&key.as_str()) {
+                warnings.push(format!("Unknown key `{key}`, which is ignored"));
+            }
+        }
+    }
+
+    let mut hooks: Vec<_> = config.hooks.iter().collect();
+    hooks.sort_by_key(|(name, _)| name.as_str());
+
+    for (name, hook) in hooks {
+        if !HOOK_NAMES.contains(&name.as_str()) {
+            warnings.push(format!("Unknown hook `{name}`, which git never runs"));
+        }
+
+        if hook.groups.is_empty() {
+            warnings.push(format!("Hook `{name}` has no tasks"));
+        } else if hook.groups.iter().any(Vec::is_empty) {
+            warnings.push(format!("Hook `{name}` has an empty group of tasks"));
+        }
+
+        for x in hook.tasks() {
+            let (_, cmd) = task::parse_env_and_command(&x.run);
+
+            let program = match &x.shell {
+                Some(Shell::Program(shell)) => shell,
+                _ => match cmd.first() {
+                    Some(program) => program,
+                    None => {
+                        warnings.push(format!("Task `{}` of `{name}` has no command", x.name()));
+                        continue;
+                    }
+                },
+            };
+
+            // Only known once the hook runs.
+            if program.contains(GIT_PARAMS_TOKEN) || program.starts_with('$') {
+                continue;
+            }
+
+            if !program_exists(program, x.cwd.as_deref()) {
+                warnings.push(format!(
+                    "Task `{}` of `{name}` runs `{program}`, which cannot be found",
+                    x.name()
+                ));
+            }
+        }
+    }
+
+    Ok(warnings)
+}
+
+/// Finds and validates the config file of the repository containing
+/// `target_directory`, looking up programs with `find_program`.
+pub(crate) fn validate_directory<F, G, H, P>(
+    run_command: F,
+    read_file: G,
+    file_exists: H,
+    find_program: P,
+    target_directory: Option<&str>,
+) -> Result<Validation, Error>
+where
+    F: Fn(
+        &str,
+        Option<&str>,
+        bool,
+        Option<&HashMap<String, String>>,
+    ) -> Result<Option<String>, Option<String>>,
+    G: Fn(&str) -> Result<String, ()>,
+    H: Fn(&str) -> Result<bool, ()>,
+    P: Fn(&str, &Path) -> Option<PathBuf>,
+{
+    let root_directory = match git::get_root_directory_path(&run_command, target_directory) {
+        Ok(Some(path)) => path,
+        _ => return Err(Error::NotAGitRepo),
+    };
+
+    let (path, contents) =
+        config::get_config_file_contents(&read_file, &file_exists, &root_directory)?;
+
+    let program_exists = |program: &str, cwd: Option<&str>| {
+        find_program(program, &task::resolve_cwd(&root_directory, cwd)).is_some()
+    };
+
+    let warnings =
+        validate_config(&contents, program_exists).map_err(|source| Error::ConfigParse {
+            path: path.clone(),
+            span: source.span(),
+            source: Box::new(source),
+        })?;
+
+    Ok(Validation { path, warnings })
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,157 @@
+use super::*;
+use crate::tests::utils::build_simple_command_runner;
+
+/// Finds every program but `cargo-nextest`.
+fn program_exists(program: &str, _cwd: Option<&str>) -> bool {
+    program != "cargo-nextest"
+}
+
+fn find_nothing(_program: &str, _cwd: &Path) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(test)]
+mod validate_config_tests {
+    use super::*;
+
+    #[test]
+    fn returns_no_warnings_for_valid_config() {
+        let contents = r#"legacy_hooks = "skip"
+
+            [hooks]
+            pre-commit = [["cargo clippy", "cargo test"], ["RUST_LOG=debug cargo doc"]]
+            commit-msg = "commitlint --edit %rh!"
+        "#;
+        assert_eq!(validate_config(contents, program_exists), Ok(vec![]));
+    }
+
+    #[test]
+    fn warns_about_likely_mistakes() {
+        let contents = r#"jbos = 2
+
+            [hooks]
+            pre-comit = "cargo test"
+            pre-push = [[], ["cargo-nextest run"]]
+            post-merge = []
+            post-checkout = "$HOOK_SCRIPT"
+        "#;
+        let result = validate_config(contents, program_exists).unwrap();
+
+        insta::assert_debug_snapshot!(result, @r#"
+        [
+            "Unknown key `jbos`, which is ignored",
+            "Hook `post-merge` has no tasks",
+            "Unknown hook `pre-comit`, which git never runs",
+            "Hook `pre-push` has an empty group of tasks",
+            "Task `cargo-nextest run` of `pre-push` runs `cargo-nextest`, which cannot be found",
+        ]
+        "#);
+    }
+
+    #[test]
+    fn looks_programs_up_from_task_directory() {
+        let contents = r#"[[hooks.pre-commit]]
+            run = "./check.sh"
+            cwd = "scripts"
+        "#;
+        let program_exists =
+            |program: &str, cwd: Option<&str>| program == "./check.sh" && cwd == Some("scripts");
+        assert_eq!(validate_config(contents, program_exists), Ok(vec![]));
+    }
+
+    #[test]
+    fn looks_up_shell_of_shell_tasks() {
+        let contents = r#"[hooks.pre-commit]
+            shell = "fish"
+            tasks = ["cargo-nextest run || true", { run = "cargo test", shell = "bash" }]
+        "#;
+        let program_exists = |program: &str, _cwd: Option<&str>| program == "bash";
+        let result = validate_config(contents, program_exists).unwrap();
+
+        insta::assert_debug_snapshot!(result, @r#"
+        [
+            "Task `cargo-nextest run || true` of `pre-commit` runs `fish`, which cannot be found",
+        ]
+        "#);
+    }
+
+    #[test]
+    fn returns_located_parse_error() {
+        let contents = r#"[[hooks.pre-commit]]
+            run = "cargo test"
+            timeot = 5
+        "#;
+        let result = validate_config(contents, program_exists).unwrap_err();
+
+        insta::assert_snapshot!(result, @"
+        TOML parse error at line 3, column 13
+          |
+        3 |             timeot = 5
+          |             ^^^^^^
+        unknown field `timeot`, expected one of `name`, `run`, `env`, `cwd`, `timeout`, `allow_failure`, `description`, `files`, `parallel_batches`, `fix`, `fail_on_fix`, `depends_on`, `exclusive_group`, `shell`, `cache`, `inputs`, `when`
+        ");
+    }
+}
+
+#[cfg(test)]
+mod validate_directory_tests {
+    use super::*;
+
+    #[test]
+    fn validates_config_file_of_repository() {
+        let run_command = |cmd: &str,
+                           dir: Option<&str>,
+                           _stream_io: bool,
+                           _env: Option<&HashMap<String, String>>| {
+            match cmd {
+                "git rev-parse --show-toplevel" if dir.is_none() => Ok(Some(String::from("/repo"))),
+                _ => Err(None),
+            }
+        };
+        let read_file = |_path: &str| Ok(String::from("[hooks]\npre-commit = \"cargo test\""));
+        let file_exists = |path: &str| Ok(path == "/repo/.crusty-hooks.toml");
+        let find_program = |program: &str, cwd: &Path| {
+            (program == "cargo" && cwd == Path::new("/repo")).then(|| PathBuf::from("/bin/cargo"))
+        };
+
+        let result =
+            validate_directory(run_command, read_file, file_exists, find_program, None).unwrap();
+
+        assert_eq!(
+            result,
+            Validation {
+                path: String::from("/repo/.crusty-hooks.toml"),
+                warnings: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn prefixes_parse_error_with_path() {
+        let run_command = build_simple_command_runner(Ok(Some(String::from("/repo"))));
+        let read_file = |_path: &str| Ok(String::from("[hooks]\npre-commit = 1"));
+        let file_exists = |_path: &str| Ok(true);
+
+        let result = validate_directory(run_command, read_file, file_exists, find_nothing, None)
+            .unwrap_err();
+
+        assert!(matches!(result, Error::ConfigParse { span: Some(_), .. }));
+        assert!(
+            result.to_string().starts_with(
+                "Invalid config file /repo/.crusty-hooks.toml: TOML parse error at line 2, column 14"
+            ),
+            "{result}"
+        );
+    }
+
+    #[test]
+    fn returns_error_without_config_file() {
+        let run_command = build_simple_command_runner(Ok(Some(String::from("/repo"))));
+        let read_file = |_path: &str| panic!("Should not get here");
+        let file_exists = |_path: &str| Ok(false);
+
+        let result = validate_directory(run_command, read_file, file_exists, find_nothing, None);
+
+        assert!(matches!(result, Err(Error::ConfigNotFound)));
+    }
+}